@@ -0,0 +1,62 @@
+//! Measures what `History` pays per step: cloning the CPU and then storing
+//! to memory, which forces the clone to copy the touched page. Both should
+//! stay flat as the amount of memory a program has touched grows.
+//!
+//! Run with `cargo bench --bench memory_clone`.
+
+#[path = "../src/cpu/mod.rs"]
+mod cpu;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use cpu::{
+    control_unit::Mem, data_memory::DataMem, paged_memory::PAGE_SIZE,
+    single_cycle_cpu::SingleCycleCPU,
+};
+
+const ITERATIONS: u32 = 10_000;
+
+fn main() {
+    println!(
+        "{:>10} {:>12} {:>16} {:>20}",
+        "pages", "bytes", "clone (ns)", "clone + store (ns)"
+    );
+
+    for pages in [1, 16, 256, 4096, 16384] {
+        let mut cpu = SingleCycleCPU::default();
+        let heap = cpu
+            .data_memory
+            .sbrk(pages * PAGE_SIZE as u32)
+            .expect("heap should fit");
+        (0..pages).for_each(|page| {
+            cpu.data_memory
+                .store(page, heap + page * PAGE_SIZE as u32, Mem::Word)
+                .expect("heap should be writable");
+        });
+
+        let start = Instant::now();
+        (0..ITERATIONS).for_each(|_| {
+            black_box(cpu.clone());
+        });
+        let clone_ns = start.elapsed().as_nanos() / ITERATIONS as u128;
+
+        let start = Instant::now();
+        (0..ITERATIONS).for_each(|i| {
+            let mut copy = cpu.clone();
+            copy.data_memory
+                .store(i, heap + (i % pages) * PAGE_SIZE as u32, Mem::Word)
+                .expect("heap should be writable");
+            black_box(copy);
+        });
+        let store_ns = start.elapsed().as_nanos() / ITERATIONS as u128;
+
+        println!(
+            "{:>10} {:>12} {:>16} {:>20}",
+            cpu.data_memory.page_count(),
+            pages as usize * PAGE_SIZE,
+            clone_ns,
+            store_ns
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    str::FromStr,
+};
+
+use num::FromPrimitive;
+
+use crate::cpu::{
+    cpu_interface::{CPUInterface, RegisterKind},
+    registers::REGISTER_NAMES,
+    watchpoints::{Comparison, Trigger, WatchTarget, Watchpoint},
+};
+use crate::source_map::SourceMap;
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(RegisterKind),
+    Value(u32),
+}
+
+impl Operand {
+    fn value(&self, cpu: &impl CPUInterface) -> u32 {
+        match self {
+            Operand::Register(reg) => cpu.get_register(*reg),
+            Operand::Value(value) => *value,
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    /// Accepts `$pc`, registers by name or number such as `$t0` and `$8`,
+    /// and decimal or `0x` hexadecimal numbers, which may be negative.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "$pc" {
+            return Ok(Operand::Register(RegisterKind::RegPC));
+        }
+        match parse_register(s) {
+            Some(reg) => reg
+                .map(|reg| FromPrimitive::from_u32(reg).unwrap())
+                .map(Operand::Register),
+            None => parse_number(s).map(Operand::Value),
+        }
+    }
+}
+
+/// A general purpose register by name or number such as `$t0` or `$8`,
+/// `None` if `s` does not look like one.
+fn parse_register(s: &str) -> Option<Result<u32, String>> {
+    let number = REGISTER_NAMES
+        .iter()
+        .position(|name| *name == s)
+        .or_else(|| s.strip_prefix('$')?.parse::<usize>().ok())?;
+    Some(if number < REGISTER_NAMES.len() {
+        Ok(number as u32)
+    } else {
+        Err(format!("No register {s}"))
+    })
+}
+
+/// A decimal or `0x` hexadecimal number, which may be negative.
+fn parse_number(s: &str) -> Result<u32, String> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => digits.parse::<u32>(),
+    }
+    .map_err(|_| format!("Expected a register or number, found \"{s}\""))?;
+    Ok(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+/// A comparison such as `$t0 == 10` or `$a0 >= $a1` that must hold for a
+/// breakpoint to stop the program. Ordering compares values as signed.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    left: Operand,
+    comparison: Comparison,
+    right: Operand,
+    text: String,
+}
+
+impl Condition {
+    pub fn holds(&self, cpu: &impl CPUInterface) -> bool {
+        self.comparison
+            .holds(self.left.value(cpu), self.right.value(cpu))
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (left, comparison, right) = Comparison::ALL
+            .iter()
+            .find_map(|(op, comparison)| {
+                s.split_once(op)
+                    .map(|(left, right)| (left, *comparison, right))
+            })
+            .ok_or_else(|| String::from("Expected a comparison using ==, !=, <, <=, > or >="))?;
+
+        Ok(Condition {
+            left: left.parse()?,
+            comparison,
+            right: right.parse()?,
+            text: s.trim().to_string(),
+        })
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Breakpoints set on source lines. Each one stops the program when the PC
+/// reaches the first instruction assembled from its line and its condition,
+/// if any, holds.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    lines: BTreeMap<usize, Option<Condition>>,
+    addresses: HashMap<u32, usize>,
+}
+
+impl Breakpoints {
+    /// Sets a breakpoint on `line`, replacing the condition of an existing
+    /// one.
+    pub fn insert(
+        &mut self,
+        line: usize,
+        condition: Option<Condition>,
+        source_map: &SourceMap,
+    ) -> Result<(), String> {
+        let address = source_map
+            .addresses(line)
+            .next()
+            .ok_or_else(|| format!("No instruction on line {}", line + 1))?;
+        self.lines.insert(line, condition);
+        self.addresses.insert(address, line);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, line: usize) {
+        self.lines.remove(&line);
+        self.addresses.retain(|_, bp_line| *bp_line != line);
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.lines.contains_key(&line)
+    }
+
+    /// Lines with a breakpoint and their conditions.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Option<&Condition>)> + '_ {
+        self.lines
+            .iter()
+            .map(|(line, condition)| (*line, condition.as_ref()))
+    }
+
+    /// Maps the breakpoints onto a reassembled program, dropping those whose
+    /// line no longer holds an instruction.
+    pub fn remap(&mut self, source_map: &SourceMap) {
+        let lines = std::mem::take(&mut self.lines);
+        self.addresses.clear();
+        lines.into_iter().for_each(|(line, condition)| {
+            let _ = self.insert(line, condition, source_map);
+        });
+    }
+
+    /// The line of the breakpoint `cpu` has stopped at, if any.
+    pub fn hit(&self, cpu: &impl CPUInterface) -> Option<usize> {
+        let line = *self.addresses.get(&cpu.get_register(RegisterKind::RegPC))?;
+        match &self.lines[&line] {
+            Some(condition) if !condition.holds(cpu) => None,
+            _ => Some(line),
+        }
+    }
+}
+
+/// Builds the watchpoint described in the breakpoint manager. `target` is a
+/// register or the address of the first of `len` bytes, and `condition` an
+/// optional comparison such as `== 0` the new value of a change must meet.
+pub fn parse_watchpoint(
+    target: &str,
+    len: u32,
+    trigger: Trigger,
+    condition: &str,
+) -> Result<Watchpoint, String> {
+    let target = target.trim();
+    let target = match parse_register(target) {
+        Some(reg) => WatchTarget::Register(reg?),
+        None => WatchTarget::Memory {
+            start: parse_number(target)?,
+            len: len.max(1),
+        },
+    };
+
+    let condition = condition.trim();
+    let trigger = match trigger {
+        Trigger::Change(_) if !condition.is_empty() => {
+            let (comparison, value) = Comparison::ALL
+                .iter()
+                .find_map(|(op, comparison)| {
+                    condition.strip_prefix(op).map(|value| (*comparison, value))
+                })
+                .ok_or_else(|| {
+                    String::from("Expected a value to compare with, such as == 0 or > 10")
+                })?;
+            Trigger::Change(Some((comparison, parse_number(value.trim())?)))
+        }
+        trigger => trigger,
+    };
+    Ok(Watchpoint { target, trigger })
+}
+
+#[cfg(test)]
+mod tests {
+    use mips_assembler::parse;
+
+    use super::*;
+    use crate::cpu::single_cycle_cpu::SingleCycleCPU;
+
+    const PROGRAM: &str = "\
+.text
+main:
+    li $t0, 3
+loop:
+    addi $t0, $t0, -1
+    bne $t0, $zero, loop
+    li $v0, 10
+    syscall
+";
+
+    /// Runs the program to the end, returning the PC and line of every
+    /// breakpoint hit on the way.
+    fn hits(breakpoints: &Breakpoints) -> Vec<(u32, usize)> {
+        let (words, data) = parse(PROGRAM).unwrap();
+        let mut cpu = SingleCycleCPU::new_from_memory(words, data.into());
+        let mut hits = Vec::new();
+        while !cpu.is_halted() && cpu.get_error().is_none() {
+            cpu.step();
+            if let Some(line) = breakpoints.hit(&cpu) {
+                hits.push((cpu.get_register(RegisterKind::RegPC), line));
+            }
+        }
+        hits
+    }
+
+    fn source_map() -> SourceMap {
+        let (words, data) = parse(PROGRAM).unwrap();
+        SourceMap::new(PROGRAM, &words, data.len())
+    }
+
+    #[test]
+    fn breakpoints_in_a_loop_stop_on_every_iteration() {
+        let source_map = source_map();
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.insert(4, None, &source_map).unwrap();
+        let addr = source_map.addresses(4).next().unwrap();
+        assert_eq!(hits(&breakpoints), [(addr, 4); 3]);
+    }
+
+    #[test]
+    fn conditions_filter_hits() {
+        let source_map = source_map();
+        let mut breakpoints = Breakpoints::default();
+        let condition = "$t0 == 1".parse().unwrap();
+        breakpoints.insert(4, Some(condition), &source_map).unwrap();
+        assert_eq!(hits(&breakpoints).len(), 1);
+    }
+
+    #[test]
+    fn lines_without_instructions_take_no_breakpoint() {
+        let mut breakpoints = Breakpoints::default();
+        let error = breakpoints.insert(3, None, &source_map()).unwrap_err();
+        assert_eq!(error, "No instruction on line 4");
+        assert!(!breakpoints.contains(3));
+    }
+}
@@ -17,13 +17,17 @@ pub enum AluOperation {
     None,
 }
 
-pub fn alu_control(alu_op: AluOp, function_code: u32) -> AluOperation {
-    match alu_op {
+/// The operation the ALU performs for `alu_op`, `None` for an R-type
+/// function it does not implement.
+pub fn alu_control(alu_op: AluOp, function_code: u32) -> Option<AluOperation> {
+    Some(match alu_op {
         AluOp::RType => match function_code {
             0x20 => AluOperation::Add,
             0x21 => AluOperation::Addu,
             0x24 => AluOperation::And,
             0x08 => AluOperation::None,
+            0x0C => AluOperation::None,
+            0x0D => AluOperation::None,
             0x27 => AluOperation::Nor,
             0x25 => AluOperation::Or,
             0x2A => AluOperation::Slt,
@@ -32,7 +36,7 @@ pub fn alu_control(alu_op: AluOp, function_code: u32) -> AluOperation {
             0x02 => AluOperation::Srl,
             0x22 => AluOperation::Sub,
             0x23 => AluOperation::Subu,
-            _ => panic!("unhandled function code: {function_code:#x}"),
+            _ => return None,
         },
         AluOp::Add => AluOperation::Add,
         AluOp::Addu => AluOperation::Addu,
@@ -42,7 +46,7 @@ pub fn alu_control(alu_op: AluOp, function_code: u32) -> AluOperation {
         AluOp::Slt => AluOperation::Slt,
         AluOp::Sltu => AluOperation::Sltu,
         AluOp::None => AluOperation::None,
-    }
+    })
 }
 
 pub fn alu(op_a: u32, op_b: u32, shamt: u32, operation: AluOperation) -> u32 {
@@ -0,0 +1,542 @@
+use std::fmt::Display;
+use std::ops::{Deref, DerefMut};
+
+use super::{
+    control_unit::Mem,
+    data_memory::{DataMem, MemoryFault},
+    paged_memory::PagedMemory,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplacementPolicy {
+    #[default]
+    Lru,
+    Fifo,
+    Random,
+}
+
+/// Write-back caches allocate a line on a write miss and only mark it dirty,
+/// write-through caches forward every write and do not allocate on a miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WritePolicy {
+    #[default]
+    WriteBack,
+    WriteThrough,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub size: u32,
+    pub block_size: u32,
+    pub associativity: u32,
+    pub replacement: ReplacementPolicy,
+    pub write_policy: WritePolicy,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: 1024,
+            block_size: 16,
+            associativity: 1,
+            replacement: ReplacementPolicy::default(),
+            write_policy: WritePolicy::default(),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn lines(&self) -> u32 {
+        (self.size / self.block_size).max(1)
+    }
+
+    pub fn sets(&self) -> u32 {
+        (self.lines() / self.associativity.clamp(1, self.lines())).max(1)
+    }
+
+    pub fn ways(&self) -> u32 {
+        self.lines() / self.sets()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheAccessKind {
+    Read,
+    Write,
+    Fetch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissKind {
+    Compulsory,
+    Capacity,
+    Conflict,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccessStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl AccessStats {
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.accesses() {
+            0 => 0.0,
+            accesses => self.hits as f64 / accesses as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub reads: AccessStats,
+    pub writes: AccessStats,
+    pub fetches: AccessStats,
+    pub compulsory_misses: u64,
+    pub capacity_misses: u64,
+    pub conflict_misses: u64,
+    pub writebacks: u64,
+}
+
+impl CacheStats {
+    pub fn total(&self) -> AccessStats {
+        AccessStats {
+            hits: self.reads.hits + self.writes.hits + self.fetches.hits,
+            misses: self.reads.misses + self.writes.misses + self.fetches.misses,
+        }
+    }
+
+    fn record(&mut self, kind: CacheAccessKind, miss: Option<MissKind>) {
+        let stats = match kind {
+            CacheAccessKind::Read => &mut self.reads,
+            CacheAccessKind::Write => &mut self.writes,
+            CacheAccessKind::Fetch => &mut self.fetches,
+        };
+        match miss {
+            None => stats.hits += 1,
+            Some(miss) => {
+                stats.misses += 1;
+                match miss {
+                    MissKind::Compulsory => self.compulsory_misses += 1,
+                    MissKind::Capacity => self.capacity_misses += 1,
+                    MissKind::Conflict => self.conflict_misses += 1,
+                }
+            }
+        }
+    }
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        [
+            ("Reads", self.reads),
+            ("Writes", self.writes),
+            ("Fetches", self.fetches),
+            ("Total", self.total()),
+        ]
+        .iter()
+        .filter(|(_, stats)| stats.accesses() > 0)
+        .try_for_each(|(name, stats)| {
+            writeln!(
+                f,
+                "{name}: {} hits, {} misses ({:.1}% hit rate)",
+                stats.hits,
+                stats.misses,
+                stats.hit_rate() * 100.0
+            )
+        })?;
+        write!(
+            f,
+            "Misses: {} compulsory, {} capacity, {} conflict\nWritebacks: {}",
+            self.compulsory_misses, self.capacity_misses, self.conflict_misses, self.writebacks
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheLine {
+    pub valid: bool,
+    pub dirty: bool,
+    pub tag: u32,
+    last_used: u64,
+    filled: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheAccess {
+    pub kind: CacheAccessKind,
+    pub addr: u32,
+    pub set: usize,
+    pub way: Option<usize>,
+    pub miss: Option<MissKind>,
+}
+
+/// Timing model of a single cache level. Memory stays the source of truth
+/// for values: lines only hold tags, views read a line's block from memory,
+/// and the dirty bit only feeds the writeback count.
+///
+/// Every field is cheap to clone, since `History` keeps a copy of the CPU per
+/// step: lines are plain values in one vector and the blocks seen so far are
+/// a bitset in copy-on-write pages.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    config: CacheConfig,
+    /// Lines of set `n` at `n * ways..(n + 1) * ways`.
+    lines: Vec<CacheLine>,
+    stats: CacheStats,
+    last_access: Option<CacheAccess>,
+    clock: u64,
+    rng_state: u32,
+    /// Bit `block % 8` of byte `block / 8` is set once the block was accessed.
+    seen_blocks: PagedMemory,
+    /// Blocks held by a fully associative LRU cache of the same size, with
+    /// when they were last used.
+    fully_associative: Vec<(u32, u64)>,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            lines: vec![CacheLine::default(); (config.sets() * config.ways()) as usize],
+            stats: CacheStats::default(),
+            last_access: None,
+            clock: 0,
+            rng_state: 0x2545F491,
+            seen_blocks: PagedMemory::default(),
+            fully_associative: Vec::with_capacity(config.lines() as usize),
+        }
+    }
+
+    pub fn config(&self) -> CacheConfig {
+        self.config
+    }
+
+    /// The lines of each set, in set order.
+    pub fn sets(&self) -> impl Iterator<Item = &[CacheLine]> {
+        self.lines.chunks(self.config.ways() as usize)
+    }
+
+    /// Address of the first byte of the block `line` of `set` holds.
+    pub fn block_addr(&self, set: usize, line: &CacheLine) -> u32 {
+        (line.tag * self.config.sets() + set as u32) * self.config.block_size
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn last_access(&self) -> Option<CacheAccess> {
+        self.last_access
+    }
+
+    /// Records an access to `addr` and returns whether it hit.
+    pub fn access(&mut self, addr: u32, kind: CacheAccessKind) -> bool {
+        self.clock += 1;
+
+        let block = addr / self.config.block_size;
+        let set = (block % self.config.sets()) as usize;
+        let tag = block / self.config.sets();
+
+        let first_touch = self.mark_seen(block);
+        let fully_associative_hit = self.touch_fully_associative(block);
+
+        let way = self
+            .set(set)
+            .iter()
+            .position(|line| line.valid && line.tag == tag);
+        let miss = match (way, first_touch, fully_associative_hit) {
+            (Some(_), _, _) => None,
+            (None, true, _) => Some(MissKind::Compulsory),
+            (None, false, false) => Some(MissKind::Capacity),
+            (None, false, true) => Some(MissKind::Conflict),
+        };
+        self.stats.record(kind, miss);
+
+        let allocate =
+            kind != CacheAccessKind::Write || self.config.write_policy == WritePolicy::WriteBack;
+        let way = match way {
+            Some(way) => Some(way),
+            None if allocate => Some(self.evict(set)),
+            None => None,
+        };
+
+        if let Some(way) = way {
+            let clock = self.clock;
+            let write_back = self.config.write_policy == WritePolicy::WriteBack;
+            let line = &mut self.lines[set * self.config.ways() as usize + way];
+
+            if miss.is_some() {
+                line.valid = true;
+                line.dirty = false;
+                line.tag = tag;
+                line.filled = clock;
+            }
+            if kind == CacheAccessKind::Write && write_back {
+                line.dirty = true;
+            }
+            line.last_used = clock;
+        }
+
+        self.last_access = Some(CacheAccess {
+            kind,
+            addr,
+            set,
+            way,
+            miss,
+        });
+        miss.is_none()
+    }
+
+    pub fn clear_last_access(&mut self) {
+        self.last_access = None;
+    }
+
+    fn set(&self, set: usize) -> &[CacheLine] {
+        let ways = self.config.ways() as usize;
+        &self.lines[set * ways..(set + 1) * ways]
+    }
+
+    fn evict(&mut self, set: usize) -> usize {
+        let ways = self.config.ways() as usize;
+        let lines = &self.lines[set * ways..(set + 1) * ways];
+        let victim = match lines.iter().position(|line| !line.valid) {
+            Some(way) => way,
+            None => match self.config.replacement {
+                ReplacementPolicy::Lru => Self::oldest(lines, |line| line.last_used),
+                ReplacementPolicy::Fifo => Self::oldest(lines, |line| line.filled),
+                ReplacementPolicy::Random => {
+                    self.rng_state ^= self.rng_state << 13;
+                    self.rng_state ^= self.rng_state >> 17;
+                    self.rng_state ^= self.rng_state << 5;
+                    self.rng_state as usize % lines.len()
+                }
+            },
+        };
+
+        let line = lines[victim];
+        if line.valid && line.dirty {
+            self.stats.writebacks += 1;
+        }
+        victim
+    }
+
+    fn oldest(lines: &[CacheLine], age: impl Fn(&CacheLine) -> u64) -> usize {
+        lines
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, line)| age(line))
+            .map_or(0, |(way, _)| way)
+    }
+
+    /// Notes that `block` was accessed and returns whether this is the first
+    /// time.
+    fn mark_seen(&mut self, block: u32) -> bool {
+        let (addr, bit) = (block / 8, 1 << (block % 8));
+        let byte = self.seen_blocks.read_byte(addr);
+        if byte & bit != 0 {
+            return false;
+        }
+        self.seen_blocks.write_byte(addr, byte | bit);
+        true
+    }
+
+    /// Replays the access on a fully associative LRU cache of the same size,
+    /// which tells capacity misses apart from conflict misses.
+    fn touch_fully_associative(&mut self, block: u32) -> bool {
+        let clock = self.clock;
+        let entries = &mut self.fully_associative;
+        if let Some(entry) = entries.iter_mut().find(|(b, _)| *b == block) {
+            entry.1 = clock;
+            return true;
+        }
+        if entries.len() < self.config.lines() as usize {
+            entries.push((block, clock));
+        } else if let Some(lru) = entries.iter_mut().min_by_key(|(_, used)| *used) {
+            *lru = (block, clock);
+        }
+        false
+    }
+}
+
+/// Places optional instruction and data caches in front of a memory. Loads
+/// and stores go through the data cache, fetches have to be reported with
+/// `record_fetch` since a Harvard CPU fetches from its own instruction memory.
+#[derive(Debug, Clone, Default)]
+pub struct CachedMemory<M> {
+    memory: M,
+    data_cache: Option<Cache>,
+    instruction_cache: Option<Cache>,
+}
+
+impl<M: DataMem> CachedMemory<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            data_cache: None,
+            instruction_cache: None,
+        }
+    }
+
+    pub fn configure(&mut self, data: Option<CacheConfig>, instruction: Option<CacheConfig>) {
+        self.data_cache = data.map(Cache::new);
+        self.instruction_cache = instruction.map(Cache::new);
+    }
+
+    pub fn data_cache(&self) -> Option<&Cache> {
+        self.data_cache.as_ref()
+    }
+
+    pub fn instruction_cache(&self) -> Option<&Cache> {
+        self.instruction_cache.as_ref()
+    }
+
+    /// Forgets the last access of both caches, so a step that does not use
+    /// a cache does not show the access of an earlier one.
+    pub fn clear_last_access(&mut self) {
+        self.data_cache
+            .iter_mut()
+            .chain(&mut self.instruction_cache)
+            .for_each(Cache::clear_last_access);
+    }
+
+    pub fn record_fetch(&mut self, addr: u32) {
+        if let Some(cache) = &mut self.instruction_cache {
+            cache.access(addr, CacheAccessKind::Fetch);
+        }
+    }
+}
+
+impl<M: DataMem> DataMem for CachedMemory<M> {
+    fn load(&mut self, addr: u32, size: Mem) -> Result<u32, MemoryFault> {
+        let data = self.memory.load(addr, size)?;
+        if let (Some(cache), false) = (&mut self.data_cache, matches!(size, Mem::None)) {
+            cache.access(addr, CacheAccessKind::Read);
+        }
+        Ok(data)
+    }
+
+    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), MemoryFault> {
+        self.memory.store(data, addr, size)?;
+        if let (Some(cache), false) = (&mut self.data_cache, matches!(size, Mem::None)) {
+            cache.access(addr, CacheAccessKind::Write);
+        }
+        Ok(())
+    }
+
+    fn read_byte(&self, addr: u32) -> Option<u8> {
+        self.memory.read_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: u32, value: u8) -> bool {
+        self.memory.write_byte(addr, value)
+    }
+}
+
+impl<M> Deref for CachedMemory<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.memory
+    }
+}
+
+impl<M> DerefMut for CachedMemory<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(associativity: u32, write_policy: WritePolicy) -> Cache {
+        Cache::new(CacheConfig {
+            size: 64,
+            block_size: 16,
+            associativity,
+            replacement: ReplacementPolicy::Lru,
+            write_policy,
+        })
+    }
+
+    fn misses(cache: &Cache) -> (u64, u64, u64) {
+        let stats = cache.stats();
+        (
+            stats.compulsory_misses,
+            stats.capacity_misses,
+            stats.conflict_misses,
+        )
+    }
+
+    #[test]
+    fn first_access_to_a_block_is_compulsory() {
+        let mut cache = cache(1, WritePolicy::WriteBack);
+        assert!(!cache.access(0x10010000, CacheAccessKind::Read));
+        assert!(cache.access(0x1001000c, CacheAccessKind::Read));
+        assert!(!cache.access(0x10010010, CacheAccessKind::Read));
+        assert_eq!(misses(&cache), (2, 0, 0));
+        assert_eq!(cache.stats().reads.hits, 1);
+    }
+
+    #[test]
+    fn blocks_sharing_a_set_conflict() {
+        // Four sets, so blocks 64 bytes apart share one.
+        let mut cache = cache(1, WritePolicy::WriteBack);
+        [0x0, 0x40, 0x0, 0x40].into_iter().for_each(|addr| {
+            cache.access(addr, CacheAccessKind::Read);
+        });
+        assert_eq!(misses(&cache), (2, 0, 2));
+    }
+
+    #[test]
+    fn working_set_larger_than_the_cache_misses_on_capacity() {
+        let mut cache = cache(4, WritePolicy::WriteBack);
+        (0..5).chain(0..1).for_each(|block| {
+            cache.access(block * 16, CacheAccessKind::Read);
+        });
+        assert_eq!(misses(&cache), (5, 1, 0));
+    }
+
+    #[test]
+    fn write_back_allocates_and_writes_dirty_lines_back() {
+        let mut cache = cache(1, WritePolicy::WriteBack);
+        assert!(!cache.access(0x0, CacheAccessKind::Write));
+        assert!(cache.access(0x4, CacheAccessKind::Read));
+        assert!(cache.sets().next().unwrap()[0].dirty);
+
+        cache.access(0x40, CacheAccessKind::Read);
+        assert_eq!(cache.stats().writebacks, 1);
+        assert!(!cache.sets().next().unwrap()[0].dirty);
+    }
+
+    #[test]
+    fn write_through_does_not_allocate_on_write_miss() {
+        let mut cache = cache(1, WritePolicy::WriteThrough);
+        assert!(!cache.access(0x0, CacheAccessKind::Write));
+        assert_eq!(cache.last_access().unwrap().way, None);
+        assert!(!cache.access(0x0, CacheAccessKind::Read));
+        assert!(cache.access(0x0, CacheAccessKind::Write));
+        assert!(!cache.sets().next().unwrap()[0].dirty);
+
+        cache.access(0x40, CacheAccessKind::Read);
+        assert_eq!(cache.stats().writebacks, 0);
+        assert_eq!(cache.stats().writes.misses, 1);
+        assert_eq!(cache.stats().writes.hits, 1);
+    }
+
+    #[test]
+    fn lines_know_their_block() {
+        let mut cache = cache(2, WritePolicy::WriteBack);
+        cache.access(0x10010034, CacheAccessKind::Read);
+        let access = cache.last_access().unwrap();
+        let line = cache.sets().nth(access.set).unwrap()[access.way.unwrap()];
+        assert_eq!(cache.block_addr(access.set, &line), 0x10010030);
+    }
+}
@@ -0,0 +1,154 @@
+/// A call made with `jal` or `jalr` that has not returned yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// Address of the call instruction.
+    pub call_site: u32,
+    /// Address of the first instruction of the callee.
+    pub target: u32,
+    /// `$sp` when the callee was entered.
+    pub sp: u32,
+}
+
+impl Frame {
+    /// Where the callee is expected to return to.
+    pub fn return_address(&self) -> u32 {
+        self.call_site.wrapping_add(4)
+    }
+}
+
+/// A `jr $ra` that did not go back to the instruction after the call it
+/// returned from, usually because `$ra` was overwritten and not restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MismatchedReturn {
+    /// Address of the `jr $ra`.
+    pub pc: u32,
+    /// The frame it returned from.
+    pub frame: Frame,
+    /// Where it went instead.
+    pub target: u32,
+}
+
+/// A callee saved register (`$s0`-`$s7`, `$fp` or `$ra`) stored to memory
+/// with `sw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedRegister {
+    pub addr: u32,
+    pub reg: u32,
+    /// Frames on the stack when it was saved, the slot is forgotten once the
+    /// frame saving it returns.
+    depth: usize,
+}
+
+/// Mismatched returns kept, older ones are only counted so a program that
+/// keeps returning elsewhere does not grow every copy of the stack.
+const MAX_MISMATCHES: usize = 16;
+
+/// Shadow call stack kept by a core from the calls and returns it executes.
+#[derive(Debug, Clone, Default)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    saved: Vec<SavedRegister>,
+    /// The most recent mismatched returns, oldest first.
+    mismatches: Vec<MismatchedReturn>,
+    mismatch_count: usize,
+}
+
+impl CallStack {
+    pub fn call(&mut self, call_site: u32, target: u32, sp: u32) {
+        self.frames.push(Frame {
+            call_site,
+            target,
+            sp,
+        });
+    }
+
+    /// Pops the innermost frame for a `jr $ra` at `pc` that jumped to
+    /// `target`, noting it when that is not the frame's return address.
+    pub fn ret(&mut self, pc: u32, target: u32) {
+        match self.frames.pop() {
+            Some(frame) if frame.return_address() != target => {
+                if self.mismatches.len() == MAX_MISMATCHES {
+                    self.mismatches.remove(0);
+                }
+                self.mismatches.push(MismatchedReturn { pc, frame, target });
+                self.mismatch_count += 1;
+            }
+            _ => {}
+        }
+        let depth = self.frames.len();
+        self.saved.retain(|saved| saved.depth <= depth);
+    }
+
+    /// Notes a `sw` of `reg` to `addr` when `reg` is callee saved.
+    pub fn save(&mut self, addr: u32, reg: u32) {
+        self.saved.retain(|saved| saved.addr != addr);
+        if matches!(reg, 16..=23 | 30 | 31) {
+            self.saved.push(SavedRegister {
+                addr,
+                reg,
+                depth: self.frames.len(),
+            });
+        }
+    }
+
+    /// Frames from the innermost out.
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().rev()
+    }
+
+    /// Slots holding callee saved registers of the frames still on the
+    /// stack.
+    pub fn saved_registers(&self) -> &[SavedRegister] {
+        &self.saved
+    }
+
+    /// The most recent mismatched returns, oldest first.
+    pub fn mismatches(&self) -> &[MismatchedReturn] {
+        &self.mismatches
+    }
+
+    /// Mismatched returns taken, including those no longer kept.
+    pub fn mismatch_count(&self) -> usize {
+        self.mismatch_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_pop_their_call() {
+        let mut stack = CallStack::default();
+        stack.call(0x0040_0000, 0x0040_0100, 0x7FFF_EFFC);
+        stack.call(0x0040_0104, 0x0040_0200, 0x7FFF_EFF0);
+        assert_eq!(stack.frames().next().unwrap().target, 0x0040_0200);
+
+        stack.ret(0x0040_0204, 0x0040_0108);
+        assert_eq!(stack.frames().count(), 1);
+        assert!(stack.mismatches().is_empty());
+    }
+
+    #[test]
+    fn only_recent_mismatches_are_kept() {
+        let mut stack = CallStack::default();
+        (0..MAX_MISMATCHES as u32 + 4).for_each(|n| {
+            stack.call(0x0040_0000, 0x0040_0100, 0);
+            stack.ret(0x0040_0104 + n * 4, 0);
+        });
+        assert_eq!(stack.mismatch_count(), MAX_MISMATCHES + 4);
+        assert_eq!(stack.mismatches().len(), MAX_MISMATCHES);
+        assert_eq!(stack.mismatches()[0].pc, 0x0040_0104 + 4 * 4);
+    }
+
+    #[test]
+    fn saved_registers_are_forgotten_when_their_frame_returns() {
+        let mut stack = CallStack::default();
+        stack.call(0x0040_0000, 0x0040_0100, 0);
+        stack.save(0x7FFF_EFF8, 31);
+        stack.save(0x7FFF_EFF4, 8);
+        assert_eq!(stack.saved_registers().len(), 1);
+        stack.ret(0x0040_0108, 0x0040_0004);
+        assert!(stack.saved_registers().is_empty());
+    }
+}
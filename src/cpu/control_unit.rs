@@ -1,4 +1,7 @@
-use super::{BEQ_OPCODE, BNE_OPCODE, JR_FUNCT};
+use super::{
+    BEQ_OPCODE, BNE_OPCODE, BREAK_FUNCT, COP0_OPCODE, JALR_FUNCT, JAL_OPCODE, JR_FUNCT,
+    SYSCALL_FUNCT,
+};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub enum AluOp {
@@ -61,11 +64,25 @@ pub enum Mem {
     Word,
 }
 
-pub fn control_unit(opcode: u32, function: u32) -> ControlUnitOutput {
-    match opcode {
+/// Decodes `opcode`, and `function` for R-type instructions, into the
+/// datapath's control signals. `None` for instructions the datapath does not
+/// implement, which raise a Reserved Instruction exception.
+pub fn control_unit(opcode: u32, function: u32) -> Option<ControlUnitOutput> {
+    Some(match opcode {
         // RTYPE
         0x00 => {
-            if function == JR_FUNCT {
+            if function == SYSCALL_FUNCT || function == BREAK_FUNCT {
+                ControlUnitOutput {
+                    reg_dst: RegDst::RD,
+                    alu_src: false,
+                    mem_to_reg: MemToReg::ALUResult,
+                    reg_write: false,
+                    mem_read: Mem::None,
+                    mem_write: Mem::None,
+                    pc_src: PCSrc::PC,
+                    alu_op: AluOp::None,
+                }
+            } else if function == JR_FUNCT {
                 ControlUnitOutput {
                     reg_dst: RegDst::RD,
                     alu_src: false,
@@ -76,6 +93,17 @@ pub fn control_unit(opcode: u32, function: u32) -> ControlUnitOutput {
                     pc_src: PCSrc::RegJump,
                     alu_op: AluOp::RType,
                 }
+            } else if function == JALR_FUNCT {
+                ControlUnitOutput {
+                    reg_dst: RegDst::RD,
+                    alu_src: false,
+                    mem_to_reg: MemToReg::PCInc,
+                    reg_write: true,
+                    mem_read: Mem::None,
+                    mem_write: Mem::None,
+                    pc_src: PCSrc::RegJump,
+                    alu_op: AluOp::None,
+                }
             } else {
                 ControlUnitOutput {
                     reg_dst: RegDst::RD,
@@ -288,16 +316,27 @@ pub fn control_unit(opcode: u32, function: u32) -> ControlUnitOutput {
             alu_op: AluOp::Sltu,
         },
         // JAL
-        0x03 => ControlUnitOutput {
+        JAL_OPCODE => ControlUnitOutput {
             reg_dst: RegDst::RA,
             alu_src: false,
-            mem_to_reg: MemToReg::ALUResult,
+            mem_to_reg: MemToReg::PCInc,
             reg_write: true,
             mem_read: Mem::None,
             mem_write: Mem::None,
             pc_src: PCSrc::Jump,
             alu_op: AluOp::Sltu,
         },
-        _ => panic!("unhandled opcode: {opcode:#x}"),
-    }
+        // COP0, executed outside the datapath
+        COP0_OPCODE => ControlUnitOutput {
+            reg_dst: RegDst::RT,
+            alu_src: false,
+            mem_to_reg: MemToReg::ALUResult,
+            reg_write: false,
+            mem_read: Mem::None,
+            mem_write: Mem::None,
+            pc_src: PCSrc::PC,
+            alu_op: AluOp::None,
+        },
+        _ => return None,
+    })
 }
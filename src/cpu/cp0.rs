@@ -0,0 +1,224 @@
+use std::fmt::Display;
+
+pub const CP0_INDEX: u32 = 0;
+pub const CP0_RANDOM: u32 = 1;
+pub const CP0_ENTRY_LO0: u32 = 2;
+pub const CP0_ENTRY_LO1: u32 = 3;
+pub const CP0_CONTEXT: u32 = 4;
+pub const CP0_PAGE_MASK: u32 = 5;
+pub const CP0_WIRED: u32 = 6;
+pub const CP0_BAD_VADDR: u32 = 8;
+pub const CP0_COUNT: u32 = 9;
+pub const CP0_ENTRY_HI: u32 = 10;
+pub const CP0_COMPARE: u32 = 11;
+pub const CP0_STATUS: u32 = 12;
+pub const CP0_CAUSE: u32 = 13;
+pub const CP0_EPC: u32 = 14;
+pub const CP0_EBASE: u32 = 15;
+pub const CP0_ERROR_EPC: u32 = 30;
+
+pub const STATUS_IE: u32 = 1 << 0;
+pub const STATUS_EXL: u32 = 1 << 1;
+pub const STATUS_ERL: u32 = 1 << 2;
+
+const INTERRUPT_MASK: u32 = 0xFF00;
+const HARDWARE_INTERRUPT_SHIFT: u32 = 10;
+const CAUSE_IP7: u32 = 1 << 15;
+const CAUSE_TI: u32 = 1 << 30;
+
+/// Registers shown in the register view, with their number and name.
+pub const CP0_NAMES: [(u32, &str, &str); 15] = [
+    (CP0_INDEX, "c0 $0", "Index"),
+    (CP0_RANDOM, "c0 $1", "Random"),
+    (CP0_ENTRY_LO0, "c0 $2", "EntryLo0"),
+    (CP0_ENTRY_LO1, "c0 $3", "EntryLo1"),
+    (CP0_CONTEXT, "c0 $4", "Context"),
+    (CP0_PAGE_MASK, "c0 $5", "PageMask"),
+    (CP0_WIRED, "c0 $6", "Wired"),
+    (CP0_BAD_VADDR, "c0 $8", "BadVAddr"),
+    (CP0_COUNT, "c0 $9", "Count"),
+    (CP0_ENTRY_HI, "c0 $10", "EntryHi"),
+    (CP0_COMPARE, "c0 $11", "Compare"),
+    (CP0_STATUS, "c0 $12", "Status"),
+    (CP0_CAUSE, "c0 $13", "Cause"),
+    (CP0_EPC, "c0 $14", "EPC"),
+    (CP0_EBASE, "c0 $15", "EBase"),
+];
+
+const EXCEPTION_BASE: u32 = 0x80000000;
+const CPU_NUM_MASK: u32 = 0x3FF;
+const GENERAL_VECTOR_OFFSET: u32 = 0x180;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionCode {
+    Interrupt = 0,
+    TlbModified = 1,
+    TlbLoad = 2,
+    TlbStore = 3,
+    ReservedInstruction = 10,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exception {
+    pub code: ExceptionCode,
+    pub bad_vaddr: Option<u32>,
+    pub refill: bool,
+}
+
+impl Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.code {
+            ExceptionCode::Interrupt => "interrupt",
+            ExceptionCode::TlbModified => "TLB modified",
+            ExceptionCode::TlbLoad if self.refill => "TLB refill on load",
+            ExceptionCode::TlbStore if self.refill => "TLB refill on store",
+            ExceptionCode::TlbLoad => "TLB invalid on load",
+            ExceptionCode::TlbStore => "TLB invalid on store",
+            ExceptionCode::ReservedInstruction => "reserved instruction",
+        };
+        write!(f, "{name} exception")?;
+        match self.bad_vaddr {
+            Some(addr) => write!(f, " for address 0x{addr:08x}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// System control coprocessor. Exceptions are precise and there are no
+/// branch delay slots, so EPC always holds the faulting instruction. EBase is
+/// fully writable so exception handlers can live in `.text`.
+#[derive(Debug, Clone)]
+pub struct Cp0 {
+    registers: [u32; 32],
+}
+
+impl Default for Cp0 {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Cp0 {
+    /// Creates the coprocessor of core `cpu_num`, which software can read
+    /// from the CPUNum field in the low bits of EBase.
+    pub fn new(cpu_num: u32) -> Self {
+        let mut registers = [0; 32];
+        registers[CP0_EBASE as usize] = EXCEPTION_BASE | (cpu_num & CPU_NUM_MASK);
+        Self { registers }
+    }
+
+    pub fn read(&self, reg: u32) -> u32 {
+        self.registers[reg as usize & 0x1F]
+    }
+
+    /// Writes `value` as MTC0 would. Random and BadVAddr are read only, and
+    /// only the software interrupt bits of Cause can be set.
+    pub fn write(&mut self, reg: u32, value: u32) {
+        match reg & 0x1F {
+            CP0_RANDOM | CP0_BAD_VADDR => {}
+            CP0_CAUSE => {
+                let cause = &mut self.registers[CP0_CAUSE as usize];
+                *cause = (*cause & !0x300) | (value & 0x300);
+            }
+            CP0_EBASE => {
+                let ebase = &mut self.registers[CP0_EBASE as usize];
+                *ebase = (value & !0xFFF) | (*ebase & CPU_NUM_MASK);
+            }
+            CP0_COMPARE => {
+                self.registers[CP0_COMPARE as usize] = value;
+                self.registers[CP0_CAUSE as usize] &= !(CAUSE_TI | CAUSE_IP7);
+            }
+            reg => self.registers[reg as usize] = value,
+        }
+    }
+
+    /// Updates registers that hardware maintains without an instruction
+    /// writing them.
+    pub fn set(&mut self, reg: u32, value: u32) {
+        self.registers[reg as usize & 0x1F] = value;
+    }
+
+    pub fn status(&self) -> u32 {
+        self.read(CP0_STATUS)
+    }
+
+    /// Advances Count by one instruction and raises the timer interrupt on
+    /// IP7 when it reaches Compare.
+    pub fn tick(&mut self) {
+        let count = self.read(CP0_COUNT).wrapping_add(1);
+        self.set(CP0_COUNT, count);
+        if count == self.read(CP0_COMPARE) {
+            let cause = self.read(CP0_CAUSE);
+            self.set(CP0_CAUSE, cause | CAUSE_TI | CAUSE_IP7);
+        }
+    }
+
+    /// Sets Cause.IP2 to IP6 from the device interrupt `lines`, a bit per
+    /// line starting at IP2. IP7 is shared with the Count/Compare timer.
+    pub fn set_hardware_interrupts(&mut self, lines: u32) {
+        let cause = self.read(CP0_CAUSE);
+        let timer = if cause & CAUSE_TI != 0 { CAUSE_IP7 } else { 0 };
+        let lines = (lines << HARDWARE_INTERRUPT_SHIFT) & INTERRUPT_MASK;
+        self.set(
+            CP0_CAUSE,
+            (cause & !(INTERRUPT_MASK & !0x300)) | lines | timer,
+        );
+    }
+
+    /// Whether an unmasked interrupt is pending and interrupts are enabled.
+    pub fn interrupt_pending(&self) -> bool {
+        let status = self.status();
+        status & (STATUS_IE | STATUS_EXL | STATUS_ERL) == STATUS_IE
+            && status & self.read(CP0_CAUSE) & INTERRUPT_MASK != 0
+    }
+
+    /// Address of the handler `exception` would enter. TLB refills go to the
+    /// refill vector unless they happen inside another handler.
+    pub fn exception_vector(&self, exception: Exception) -> u32 {
+        let nested = self.status() & STATUS_EXL != 0;
+        let offset = if exception.refill && !nested {
+            0
+        } else {
+            GENERAL_VECTOR_OFFSET
+        };
+        (self.read(CP0_EBASE) & !0xFFF) + offset
+    }
+
+    /// Records `exception` raised by the instruction at `pc` and returns the
+    /// address of the handler to continue at.
+    pub fn enter_exception(&mut self, exception: Exception, pc: u32) -> u32 {
+        let vector = self.exception_vector(exception);
+        let status = self.status();
+        let nested = status & STATUS_EXL != 0;
+
+        if !nested {
+            self.set(CP0_EPC, pc);
+        }
+
+        let cause = self.read(CP0_CAUSE);
+        self.set(CP0_CAUSE, (cause & !0x7C) | ((exception.code as u32) << 2));
+
+        if let Some(addr) = exception.bad_vaddr {
+            self.set(CP0_BAD_VADDR, addr);
+            let context = self.read(CP0_CONTEXT);
+            self.set(CP0_CONTEXT, (context & 0xFF800000) | ((addr >> 13) << 4));
+            let entry_hi = self.read(CP0_ENTRY_HI);
+            self.set(CP0_ENTRY_HI, (addr & 0xFFFFE000) | (entry_hi & 0xFF));
+        }
+
+        self.set(CP0_STATUS, status | STATUS_EXL);
+        vector
+    }
+
+    /// Leaves an exception handler and returns the address to resume at.
+    pub fn exception_return(&mut self) -> u32 {
+        let status = self.status();
+        if status & STATUS_ERL != 0 {
+            self.set(CP0_STATUS, status & !STATUS_ERL);
+            self.read(CP0_ERROR_EPC)
+        } else {
+            self.set(CP0_STATUS, status & !STATUS_EXL);
+            self.read(CP0_EPC)
+        }
+    }
+}
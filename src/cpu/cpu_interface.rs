@@ -1,8 +1,21 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use num_derive::FromPrimitive;
 
-use super::{alu::AluOperation, control_unit::ControlUnitOutput};
+use super::{
+    alu::AluOperation,
+    cache::{Cache, CacheConfig},
+    call_stack::CallStack,
+    control_unit::ControlUnitOutput,
+    data_memory::MemoryArchitecture,
+    devices::Device,
+    dirty_regions::DirtyRegions,
+    mmu::{AddressTranslation, Tlb},
+    profiler::Profile,
+    statistics::Statistics,
+    watchpoints::{WatchHit, Watchpoint},
+    DATA_MEM_START,
+};
 
 #[derive(Debug, Clone, Copy, FromPrimitive)]
 pub enum RegisterKind {
@@ -41,6 +54,36 @@ pub enum RegisterKind {
     RegPC = 32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    Exit(i32),
+    Break(u32),
+    EndOfText,
+    HaltAddress(u32),
+}
+
+impl HaltReason {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HaltReason::Exit(code) => *code,
+            HaltReason::Break(code) => *code as i32,
+            HaltReason::EndOfText | HaltReason::HaltAddress(_) => 0,
+        }
+    }
+}
+
+impl Display for HaltReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Program exited with code {}", self.exit_code())?;
+        match self {
+            HaltReason::Exit(_) => Ok(()),
+            HaltReason::Break(_) => write!(f, " (break)"),
+            HaltReason::EndOfText => write!(f, " (dropped off end of .text)"),
+            HaltReason::HaltAddress(addr) => write!(f, " (reached halt address 0x{addr:08x})"),
+        }
+    }
+}
+
 pub trait CPUInterface: Send {
     fn get_memory_size(&self) -> u32;
     fn get_instruction_size(&self) -> u32;
@@ -48,10 +91,61 @@ pub trait CPUInterface: Send {
     fn get_register(&self, reg: RegisterKind) -> u32;
     fn get_memory_byte(&self, address: u32) -> Option<u8>;
 
+    /// Overwrites a register between steps, writes to `$zero` are ignored.
+    fn set_register(&mut self, reg: RegisterKind, value: u32);
+    /// Overwrites the byte at `address`, which counts from the start of
+    /// `.data` like `get_memory_byte`. Returns whether it is mapped.
+    fn set_memory_byte(&mut self, address: u32, value: u8) -> bool;
+
+    /// Reads the little endian word at absolute `address` without counting
+    /// as an access.
+    fn get_memory_word(&self, address: u32) -> Option<u32> {
+        (0..4).try_fold(0, |word, offset| {
+            let byte =
+                self.get_memory_byte(address.wrapping_add(offset).wrapping_sub(DATA_MEM_START))?;
+            Some(word | (byte as u32) << (offset * 8))
+        })
+    }
+
     fn get_control_signals(&self) -> ControlUnitOutput;
     fn get_alu_signals(&self) -> AluOperation;
 
     fn get_error(&self) -> Option<String>;
+    fn get_halt_reason(&self) -> Option<HaltReason>;
+    fn set_halt_address(&mut self, address: Option<u32>);
+    fn set_memory_architecture(&mut self, architecture: MemoryArchitecture);
+
+    fn configure_caches(&mut self, data: Option<CacheConfig>, instruction: Option<CacheConfig>);
+    fn get_data_cache(&self) -> Option<&Cache>;
+    fn get_instruction_cache(&self) -> Option<&Cache>;
+
+    fn set_address_translation(&mut self, translation: AddressTranslation);
+    fn get_cp0_register(&self, reg: u32) -> u32;
+    fn get_tlb(&self) -> Option<&Tlb>;
+
+    fn attach_device(&mut self, device: Box<dyn Device>);
+    fn get_devices(&self) -> &[Box<dyn Device>];
+
+    fn get_statistics(&self) -> Statistics;
+    fn get_profile(&self) -> Profile;
+
+    /// Replaces the watchpoints enforced on memory and registers.
+    fn set_watchpoints(&mut self, watchpoints: &[Watchpoint]);
+    /// The watchpoint set off by the last step, if any.
+    fn get_watch_hit(&self) -> Option<WatchHit>;
+
+    fn get_call_stack(&self) -> &CallStack;
+
+    /// Address and width of the memory written by the last step, if any.
+    fn get_last_store(&self) -> Option<(u32, u32)>;
+
+    /// Absolute addresses of the memory written since the last call, by the
+    /// program or by `set_memory_byte`, so views only redraw what changed.
+    fn take_dirty_memory(&mut self) -> DirtyRegions;
+
+    fn is_halted(&self) -> bool {
+        self.get_halt_reason().is_some()
+    }
 
     fn step(&mut self);
 }
@@ -60,10 +154,11 @@ impl Debug for dyn CPUInterface {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "CPU with memory size: {}, instruction size: {}, and error: {:?}",
+            "CPU with memory size: {}, instruction size: {}, error: {:?}, and halt reason: {:?}",
             self.get_memory_size(),
             self.get_instruction_size(),
-            self.get_error()
+            self.get_error(),
+            self.get_halt_reason()
         )
     }
 }
@@ -1,60 +1,540 @@
-use super::{control_unit::Mem, DATA_MEM_START};
+use std::fmt::Display;
 
-pub type DataMemory = Vec<u8>;
+use super::{
+    control_unit::Mem,
+    dirty_regions::DirtyRegions,
+    paged_memory::{PagedMemory, PAGE_SIZE},
+    watchpoints::Watchpoints,
+    DATA_MEM_START, EXTERN_START, HEAP_START, INST_MEM_START, KERNEL_DATA_START, KERNEL_TEXT_START,
+    MMIO_START, STACK_END, STACK_LIMIT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Reserved,
+    Text,
+    Extern,
+    Data,
+    Heap,
+    Stack,
+    KernelText,
+    KernelData,
+    Mmio,
+}
+
+impl Segment {
+    pub fn of(addr: u32) -> Segment {
+        match addr {
+            0..=0x003FFFFF => Segment::Reserved,
+            INST_MEM_START..=0x0FFFFFFF => Segment::Text,
+            EXTERN_START..=0x1000FFFF => Segment::Extern,
+            DATA_MEM_START..=0x1003FFFF => Segment::Data,
+            HEAP_START..=0x7EFFFFFF => Segment::Heap,
+            STACK_LIMIT..=0x7FFFFFFF => Segment::Stack,
+            KERNEL_TEXT_START..=0x8FFFFFFF => Segment::KernelText,
+            KERNEL_DATA_START..=0xFFFEFFFF => Segment::KernelData,
+            MMIO_START..=0xFFFFFFFF => Segment::Mmio,
+        }
+    }
+}
+
+/// Where instructions are fetched from. Harvard keeps text in a separate
+/// instruction memory, as drawn in the single cycle diagram, while von
+/// Neumann places it in data memory so programs can read and patch code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryArchitecture {
+    #[default]
+    Harvard,
+    VonNeumann {
+        protect_text: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Permissions {
+    pub const READ_WRITE: Permissions = Permissions {
+        read: true,
+        write: true,
+        execute: false,
+    };
+    pub const READ_EXECUTE: Permissions = Permissions {
+        read: true,
+        write: false,
+        execute: true,
+    };
+    pub const ALL: Permissions = Permissions {
+        read: true,
+        write: true,
+        execute: true,
+    };
+
+    fn allows(&self, access: AccessKind) -> bool {
+        match access {
+            AccessKind::Load => self.read,
+            AccessKind::Store => self.write,
+            AccessKind::Fetch => self.execute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Load,
+    Store,
+    Fetch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultCause {
+    Null,
+    Unmapped,
+    Unaligned,
+    NotReadable,
+    ReadOnly,
+    NotExecutable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryFault {
+    pub access: AccessKind,
+    pub cause: FaultCause,
+    pub addr: u32,
+}
+
+impl Display for MemoryFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let access = match self.access {
+            AccessKind::Load => "load from",
+            AccessKind::Store => "store to",
+            AccessKind::Fetch => "instruction fetch from",
+        };
+        let cause = match self.cause {
+            FaultCause::Null => "null",
+            FaultCause::Unmapped => "unmapped",
+            FaultCause::Unaligned => "unaligned",
+            FaultCause::NotReadable => "unreadable",
+            FaultCause::ReadOnly => "read-only",
+            FaultCause::NotExecutable => "non-executable",
+        };
+        write!(f, "{access} {cause} address 0x{:08x}", self.addr)
+    }
+}
+
+/// A contiguous range of the address space that loads and stores may use.
+#[derive(Debug, Clone)]
+struct Region {
+    segment: Segment,
+    start: u32,
+    end: u32,
+    permissions: Permissions,
+}
+
+impl Region {
+    fn new(segment: Segment, start: u32, end: u32, permissions: Permissions) -> Self {
+        Self {
+            segment,
+            start,
+            end,
+            permissions,
+        }
+    }
+
+    fn contains(&self, addr: u32) -> bool {
+        addr >= self.start && addr < self.end
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DataMemory {
+    regions: Vec<Region>,
+    pages: PagedMemory,
+    static_data_size: u32,
+    heap_break: u32,
+    watchpoints: Watchpoints,
+    /// Address and width of the last store, until cleared.
+    last_store: Option<(u32, u32)>,
+    /// Bytes written by stores or debuggers since the views last caught up.
+    dirty: DirtyRegions,
+}
 
 pub trait DataMem {
-    fn load(&self, addr: u32, size: Mem) -> Option<u32>;
-    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), ()>;
+    fn load(&mut self, addr: u32, size: Mem) -> Result<u32, MemoryFault>;
+    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), MemoryFault>;
+    /// Reads a mapped byte without counting as an access, for views and
+    /// debuggers.
+    fn read_byte(&self, addr: u32) -> Option<u8>;
+    /// Writes a mapped byte without checking permissions or setting off
+    /// watchpoints, for debuggers. Returns whether `addr` is mapped.
+    fn write_byte(&mut self, addr: u32, value: u8) -> bool;
+}
+
+pub fn access_width(size: Mem) -> u32 {
+    match size {
+        Mem::None => 0,
+        Mem::Byte => 1,
+        Mem::Half => 2,
+        Mem::Word => 4,
+    }
 }
 
 impl DataMem for DataMemory {
-    fn load(&self, addr: u32, size: Mem) -> Option<u32> {
-        let offset_addr = addr.wrapping_sub(DATA_MEM_START);
-        match size {
-            Mem::None => None,
-            Mem::Byte => self.get(offset_addr as usize).map(|e| *e as u32),
-            Mem::Half => Some(
-                (0..2)
-                    .map(|v| self.get(offset_addr as usize + v).unwrap_or(&0))
-                    .fold(0, |acc, e| acc + *e as u32),
-            ),
-            Mem::Word => Some(
-                (0..4)
-                    .map(|v| self.get(offset_addr as usize + v).unwrap_or(&0))
-                    .fold(0, |acc, e| acc + *e as u32),
-            ),
-        }
-    }
-
-    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), ()> {
-        let offset_addr = addr.wrapping_sub(DATA_MEM_START);
-        let data = data.to_be_bytes();
-        match size {
-            Mem::None => Ok(()),
-            Mem::Byte => {
-                if let Some(elem) = self.get_mut(offset_addr as usize) {
-                    *elem = data[0];
-                    Ok(())
-                } else {
-                    Err(())
-                }
-            }
-            Mem::Half => (0..2).try_for_each(|v| {
-                if let Some(elem) = self.get_mut(offset_addr as usize + v) {
-                    *elem = data[v];
-                    Ok(())
-                } else {
-                    Err(())
-                }
-            }),
-            Mem::Word => (0..4).try_for_each(|v| {
-                if let Some(elem) = self.get_mut(offset_addr as usize + v) {
-                    *elem = data[v];
-                    Ok(())
-                } else {
-                    Err(())
-                }
-            }),
+    fn load(&mut self, addr: u32, size: Mem) -> Result<u32, MemoryFault> {
+        let width = access_width(size);
+        if width == 0 {
+            return Ok(0);
+        }
+        self.check(addr, width, AccessKind::Load)?;
+
+        let data = self.peek(addr, width);
+        self.watchpoints.load(addr, width, data);
+        Ok(data)
+    }
+
+    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), MemoryFault> {
+        let width = access_width(size);
+        if width == 0 {
+            return Ok(());
+        }
+        self.check(addr, width, AccessKind::Store)?;
+
+        if !self.watchpoints.is_empty() {
+            let old = self.peek(addr, width);
+            let mask = u32::MAX >> (32 - width * 8);
+            self.watchpoints.store(addr, width, old, data & mask);
+        }
+        data.to_le_bytes()
+            .iter()
+            .take(width as usize)
+            .enumerate()
+            .for_each(|(offset, byte)| {
+                self.pages
+                    .write_byte(addr.wrapping_add(offset as u32), *byte)
+            });
+        self.last_store = Some((addr, width));
+        self.dirty.mark(addr, width);
+        Ok(())
+    }
+
+    fn read_byte(&self, addr: u32) -> Option<u8> {
+        self.region(addr).map(|_| self.pages.read_byte(addr))
+    }
+
+    fn write_byte(&mut self, addr: u32, value: u8) -> bool {
+        let mapped = self.region(addr).is_some();
+        if mapped {
+            self.pages.write_byte(addr, value);
+            self.dirty.mark(addr, 1);
+        }
+        mapped
+    }
+}
+
+impl Default for DataMemory {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for DataMemory {
+    fn from(static_data: Vec<u8>) -> Self {
+        Self::new(static_data)
+    }
+}
+
+impl DataMemory {
+    /// Builds the SPIM/MARS address space with `static_data` placed at the
+    /// start of `.data`. The text segment lives in instruction memory.
+    pub fn new(static_data: Vec<u8>) -> Self {
+        let mut pages = PagedMemory::default();
+        pages.write_bytes(DATA_MEM_START, &static_data);
+
+        Self {
+            regions: vec![
+                Region::new(
+                    Segment::Extern,
+                    EXTERN_START,
+                    DATA_MEM_START,
+                    Permissions::READ_WRITE,
+                ),
+                Region::new(
+                    Segment::Data,
+                    DATA_MEM_START,
+                    HEAP_START,
+                    Permissions::READ_WRITE,
+                ),
+                Region::new(
+                    Segment::Heap,
+                    HEAP_START,
+                    HEAP_START,
+                    Permissions::READ_WRITE,
+                ),
+                Region::new(
+                    Segment::Stack,
+                    STACK_LIMIT,
+                    STACK_END,
+                    Permissions::READ_WRITE,
+                ),
+                Region::new(
+                    Segment::KernelText,
+                    KERNEL_TEXT_START,
+                    KERNEL_DATA_START,
+                    Permissions::ALL,
+                ),
+                Region::new(
+                    Segment::KernelData,
+                    KERNEL_DATA_START,
+                    MMIO_START,
+                    Permissions::READ_WRITE,
+                ),
+            ],
+            pages,
+            static_data_size: static_data.len() as u32,
+            heap_break: HEAP_START,
+            watchpoints: Watchpoints::default(),
+            last_store: None,
+            dirty: DirtyRegions::default(),
         }
     }
+
+    /// Copies `instructions` into the text segment and makes it addressable
+    /// by loads, and by stores unless `writable` is false.
+    pub fn map_text(&mut self, instructions: &[u32], writable: bool) {
+        self.unmap_text();
+
+        instructions.iter().enumerate().for_each(|(idx, inst)| {
+            self.pages
+                .write_bytes(INST_MEM_START + idx as u32 * 4, &inst.to_le_bytes())
+        });
+
+        let permissions = if writable {
+            Permissions::ALL
+        } else {
+            Permissions::READ_EXECUTE
+        };
+        self.regions.insert(
+            0,
+            Region::new(Segment::Text, INST_MEM_START, EXTERN_START, permissions),
+        );
+    }
+
+    /// Makes `len` bytes from `start` readable and writable where no other
+    /// region covers them, for devices that keep their state in memory.
+    pub fn map_window(&mut self, start: u32, len: u32) {
+        self.regions.push(Region::new(
+            Segment::of(start),
+            start,
+            start.saturating_add(len),
+            Permissions::READ_WRITE,
+        ));
+    }
+
+    pub fn unmap_text(&mut self) {
+        self.regions
+            .retain(|region| region.segment != Segment::Text);
+    }
+
+    pub fn fetch(&self, addr: u32) -> Result<u32, MemoryFault> {
+        self.check(addr, 4, AccessKind::Fetch)?;
+        Ok(u32::from_le_bytes(std::array::from_fn(|offset| {
+            self.pages.read_byte(addr.wrapping_add(offset as u32))
+        })))
+    }
+
+    /// Checks that `width` bytes starting at `addr` are aligned, mapped and
+    /// allow `access`.
+    pub fn check(&self, addr: u32, width: u32, access: AccessKind) -> Result<(), MemoryFault> {
+        let fault = |cause| MemoryFault {
+            access,
+            cause,
+            addr,
+        };
+
+        if addr & (width - 1) != 0 {
+            return Err(fault(FaultCause::Unaligned));
+        }
+
+        (0..width).try_for_each(|offset| match self.region(addr.wrapping_add(offset)) {
+            Some(region) if region.permissions.allows(access) => Ok(()),
+            Some(_) => Err(fault(match access {
+                AccessKind::Load => FaultCause::NotReadable,
+                AccessKind::Store => FaultCause::ReadOnly,
+                AccessKind::Fetch => FaultCause::NotExecutable,
+            })),
+            None if addr < PAGE_SIZE as u32 => Err(fault(FaultCause::Null)),
+            None => Err(fault(FaultCause::Unmapped)),
+        })
+    }
+
+    pub fn static_data_size(&self) -> u32 {
+        self.static_data_size
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.page_count()
+    }
+
+    pub fn heap_break(&self) -> u32 {
+        self.heap_break
+    }
+
+    /// Grows the heap by `increment` bytes rounded up to a word and returns
+    /// the previous break, or `None` if the heap would run into the stack.
+    pub fn sbrk(&mut self, increment: u32) -> Option<u32> {
+        let old_break = self.heap_break;
+        let new_break = old_break.checked_add(increment.checked_add(3)? & !3)?;
+        if new_break > STACK_LIMIT {
+            return None;
+        }
+
+        if let Some(heap) = self
+            .regions
+            .iter_mut()
+            .find(|region| region.segment == Segment::Heap)
+        {
+            heap.end = new_break;
+        }
+        self.heap_break = new_break;
+        Some(old_break)
+    }
+
+    /// Watchpoints checked by every load and store.
+    pub fn watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    pub fn watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.watchpoints
+    }
+
+    /// Address and width of the last store since the record was cleared.
+    pub fn last_store(&self) -> Option<(u32, u32)> {
+        self.last_store
+    }
+
+    pub fn clear_last_store(&mut self) {
+        self.last_store = None;
+    }
+
+    /// Returns the bytes written since the last call and starts over.
+    pub fn take_dirty(&mut self) -> DirtyRegions {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Reads `width` little endian bytes without checking permissions.
+    fn peek(&self, addr: u32, width: u32) -> u32 {
+        (0..width).fold(0, |acc, offset| {
+            acc | (self.pages.read_byte(addr.wrapping_add(offset)) as u32) << (offset * 8)
+        })
+    }
+
+    fn region(&self, addr: u32) -> Option<&Region> {
+        self.regions.iter().find(|region| region.contains(addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault(access: AccessKind, cause: FaultCause, addr: u32) -> Result<(), MemoryFault> {
+        Err(MemoryFault {
+            access,
+            cause,
+            addr,
+        })
+    }
+
+    #[test]
+    fn static_data_is_read_write_but_not_executable() {
+        let mut memory = DataMemory::new(vec![1, 2, 3, 4]);
+        assert_eq!(memory.load(DATA_MEM_START, Mem::Word), Ok(0x04030201));
+        assert_eq!(memory.store(7, DATA_MEM_START + 4, Mem::Byte), Ok(()));
+        assert_eq!(memory.load(DATA_MEM_START + 4, Mem::Byte), Ok(7));
+        assert_eq!(
+            memory.fetch(DATA_MEM_START).map(|_| ()),
+            fault(AccessKind::Fetch, FaultCause::NotExecutable, DATA_MEM_START)
+        );
+    }
+
+    #[test]
+    fn text_permissions_follow_map_text() {
+        let mut memory = DataMemory::default();
+        assert_eq!(
+            memory.check(INST_MEM_START, 4, AccessKind::Load),
+            fault(AccessKind::Load, FaultCause::Unmapped, INST_MEM_START)
+        );
+
+        memory.map_text(&[0x12345678], false);
+        assert_eq!(memory.fetch(INST_MEM_START), Ok(0x12345678));
+        assert_eq!(memory.load(INST_MEM_START, Mem::Half), Ok(0x5678));
+        assert_eq!(
+            memory.store(0, INST_MEM_START, Mem::Word),
+            fault(AccessKind::Store, FaultCause::ReadOnly, INST_MEM_START)
+        );
+
+        memory.map_text(&[0x12345678], true);
+        assert_eq!(memory.store(0, INST_MEM_START, Mem::Word), Ok(()));
+        assert_eq!(memory.fetch(INST_MEM_START), Ok(0));
+
+        memory.unmap_text();
+        assert!(memory.fetch(INST_MEM_START).is_err());
+    }
+
+    #[test]
+    fn faults_name_the_cause() {
+        let mut memory = DataMemory::default();
+        assert_eq!(
+            memory.check(0x8, 4, AccessKind::Load),
+            fault(AccessKind::Load, FaultCause::Null, 0x8)
+        );
+        assert_eq!(
+            memory.check(DATA_MEM_START + 2, 4, AccessKind::Store),
+            fault(AccessKind::Store, FaultCause::Unaligned, DATA_MEM_START + 2)
+        );
+        assert_eq!(
+            memory.check(HEAP_START, 1, AccessKind::Store),
+            fault(AccessKind::Store, FaultCause::Unmapped, HEAP_START)
+        );
+        assert_eq!(memory.sbrk(5), Some(HEAP_START));
+        assert_eq!(memory.check(HEAP_START + 4, 4, AccessKind::Store), Ok(()));
+        assert!(memory.check(HEAP_START + 8, 1, AccessKind::Load).is_err());
+    }
+
+    #[test]
+    fn fault_text() {
+        let text = |access, cause, addr| {
+            MemoryFault {
+                access,
+                cause,
+                addr,
+            }
+            .to_string()
+        };
+        assert_eq!(
+            text(AccessKind::Load, FaultCause::Null, 0),
+            "load from null address 0x00000000"
+        );
+        assert_eq!(
+            text(AccessKind::Store, FaultCause::ReadOnly, INST_MEM_START),
+            "store to read-only address 0x00400000"
+        );
+        assert_eq!(
+            text(AccessKind::Fetch, FaultCause::NotExecutable, DATA_MEM_START),
+            "instruction fetch from non-executable address 0x10010000"
+        );
+        assert_eq!(
+            text(AccessKind::Load, FaultCause::Unaligned, 0x10010002),
+            "load from unaligned address 0x10010002"
+        );
+        assert_eq!(
+            text(AccessKind::Store, FaultCause::Unmapped, 0x7EFFFFFC),
+            "store to unmapped address 0x7efffffc"
+        );
+    }
 }
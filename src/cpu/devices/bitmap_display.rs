@@ -0,0 +1,155 @@
+use std::any::Any;
+
+use super::Device;
+use crate::cpu::DATA_MEM_START;
+
+/// Geometry of a [`BitmapDisplay`], matching the options of the MARS Bitmap
+/// Display tool. Each unit is one word of the framebuffer holding 0x00RRGGBB
+/// and covers `unit_size` by `unit_size` screen pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapConfig {
+    pub width: u32,
+    pub height: u32,
+    pub unit_size: u32,
+    pub base: u32,
+}
+
+impl Default for BitmapConfig {
+    fn default() -> Self {
+        Self {
+            width: 512,
+            height: 256,
+            unit_size: 8,
+            base: DATA_MEM_START,
+        }
+    }
+}
+
+impl BitmapConfig {
+    pub fn units_wide(&self) -> u32 {
+        (self.width / self.unit_size.max(1)).max(1)
+    }
+
+    pub fn units_high(&self) -> u32 {
+        (self.height / self.unit_size.max(1)).max(1)
+    }
+
+    /// Address of the word holding the unit at column `x` and row `y`.
+    pub fn unit_addr(&self, x: u32, y: u32) -> u32 {
+        self.base.wrapping_add((y * self.units_wide() + x) * 4)
+    }
+}
+
+/// Framebuffer laid out row by row from `base`. As in MARS it is a window
+/// onto ordinary memory, so a program can draw into an array in `.data` and
+/// whatever else the window covers stays readable.
+#[derive(Debug, Clone, Default)]
+pub struct BitmapDisplay {
+    config: BitmapConfig,
+}
+
+impl BitmapDisplay {
+    pub fn new(config: BitmapConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> BitmapConfig {
+        self.config
+    }
+}
+
+impl Device for BitmapDisplay {
+    fn base(&self) -> u32 {
+        self.config.base
+    }
+
+    fn size(&self) -> u32 {
+        self.config.units_wide() * self.config.units_high() * 4
+    }
+
+    fn read_word(&mut self, _: u32) -> u32 {
+        0
+    }
+
+    fn write_word(&mut self, _: u32, _: u32) {}
+
+    fn peek_word(&self, _: u32) -> u32 {
+        0
+    }
+
+    fn memory_backed(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{
+        control_unit::Mem, cpu_interface::CPUInterface, data_memory::DataMem,
+        multicore::MulticoreCPU, piplined_cpu::PipelinedCPU, single_cycle_cpu::SingleCycleCPU,
+        HEAP_START,
+    };
+
+    fn cpu(static_data: Vec<u8>, config: BitmapConfig) -> SingleCycleCPU {
+        let mut cpu = SingleCycleCPU::new_from_memory(Vec::new(), static_data.into());
+        cpu.attach_device(Box::new(BitmapDisplay::new(config)));
+        cpu
+    }
+
+    #[test]
+    fn framebuffer_is_the_memory_under_it() {
+        let config = BitmapConfig::default();
+        let mut cpu = cpu(vec![1, 2, 3, 4], config);
+        assert_eq!(
+            cpu.data_memory.load(DATA_MEM_START, Mem::Word),
+            Ok(0x04030201)
+        );
+
+        let addr = config.unit_addr(1, 1);
+        assert_eq!(addr, DATA_MEM_START + (config.units_wide() + 1) * 4);
+        cpu.data_memory.store(0x00FF8000, addr, Mem::Word).unwrap();
+        assert_eq!(cpu.get_memory_word(addr), Some(0x00FF8000));
+        let dirty = cpu.take_dirty_memory();
+        assert_eq!(dirty.ranges().len(), 1);
+        assert_eq!(dirty.ranges()[0], addr..addr + 4);
+    }
+
+    #[test]
+    fn window_outside_mapped_memory_is_mapped() {
+        let config = BitmapConfig {
+            base: HEAP_START,
+            ..Default::default()
+        };
+        let mut cpu = cpu(Vec::new(), config);
+        let last = config.unit_addr(config.units_wide() - 1, config.units_high() - 1);
+        assert_eq!(cpu.data_memory.store(0xFF, last, Mem::Word), Ok(()));
+        assert_eq!(cpu.get_memory_word(last), Some(0xFF));
+        assert!(cpu.data_memory.store(0, last + 4, Mem::Word).is_err());
+    }
+
+    #[test]
+    fn every_cpu_maps_the_window() {
+        fn check(mut cpu: impl CPUInterface) {
+            let config = BitmapConfig {
+                base: HEAP_START,
+                ..Default::default()
+            };
+            cpu.attach_device(Box::new(BitmapDisplay::new(config)));
+            let last = config.unit_addr(config.units_wide() - 1, config.units_high() - 1);
+            assert!(cpu.set_memory_byte(last - DATA_MEM_START, 0xFF));
+            assert_eq!(cpu.get_memory_word(last), Some(0xFF));
+        }
+        check(SingleCycleCPU::new());
+        check(PipelinedCPU::new());
+        check(MulticoreCPU::default());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+use super::Device;
+use crate::cpu::MMIO_START;
+
+const RECEIVER_CONTROL: u32 = 0x0;
+const RECEIVER_DATA: u32 = 0x4;
+const TRANSMITTER_CONTROL: u32 = 0x8;
+const TRANSMITTER_DATA: u32 = 0xC;
+
+const READY: u32 = 1 << 0;
+const INTERRUPT_ENABLE: u32 = 1 << 1;
+
+/// Interrupt lines, IP2 for the keyboard and IP3 for the display.
+const KEYBOARD_INTERRUPT: u32 = 1 << 0;
+const DISPLAY_INTERRUPT: u32 = 1 << 1;
+
+/// Instructions the display stays busy after a character is written.
+const DISPLAY_DELAY: u32 = 5;
+
+/// Every key typed by the user since the program was loaded. Clones share
+/// it, so input typed while a run is in progress reaches the running CPU.
+/// Keys are never removed: each device remembers how many it has read, so
+/// undoing a step gives back the keys that step read.
+#[derive(Debug, Clone, Default)]
+pub struct InputQueue(Arc<Mutex<Vec<u8>>>);
+
+impl InputQueue {
+    pub fn push_str(&self, text: &str) {
+        self.0.lock().unwrap().extend(text.bytes());
+    }
+
+    /// Number of keys typed so far.
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, idx: usize) -> Option<u8> {
+        self.0.lock().unwrap().get(idx).copied()
+    }
+}
+
+fn ready_bit(ready: bool) -> u32 {
+    if ready {
+        READY
+    } else {
+        0
+    }
+}
+
+/// The MARS "Keyboard and Display MMIO Simulator": receiver control and data
+/// at 0xFFFF0000 and 0xFFFF0004, transmitter control and data at 0xFFFF0008
+/// and 0xFFFF000C.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardDisplay {
+    input: InputQueue,
+    /// Keys of `input` the program has read.
+    keys_read: usize,
+    receiver_control: u32,
+    transmitter_control: u32,
+    transmitter_data: u32,
+    busy: u32,
+    output: String,
+}
+
+impl KeyboardDisplay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input(&self) -> InputQueue {
+        self.input.clone()
+    }
+
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Keys typed that the program has not read yet.
+    pub fn pending_keys(&self) -> usize {
+        self.input.len().saturating_sub(self.keys_read)
+    }
+
+    pub fn receiver_ready(&self) -> bool {
+        self.pending_keys() > 0
+    }
+
+    pub fn transmitter_ready(&self) -> bool {
+        self.busy == 0
+    }
+
+    pub fn receiver_interrupt_enabled(&self) -> bool {
+        self.receiver_control & INTERRUPT_ENABLE != 0
+    }
+
+    pub fn transmitter_interrupt_enabled(&self) -> bool {
+        self.transmitter_control & INTERRUPT_ENABLE != 0
+    }
+}
+
+impl Device for KeyboardDisplay {
+    fn base(&self) -> u32 {
+        MMIO_START
+    }
+
+    fn size(&self) -> u32 {
+        16
+    }
+
+    fn read_word(&mut self, offset: u32) -> u32 {
+        match offset {
+            RECEIVER_DATA => match self.input.get(self.keys_read) {
+                Some(key) => {
+                    self.keys_read += 1;
+                    key as u32
+                }
+                None => 0,
+            },
+            _ => self.peek_word(offset),
+        }
+    }
+
+    fn write_word(&mut self, offset: u32, value: u32) {
+        match offset {
+            RECEIVER_CONTROL => self.receiver_control = value & INTERRUPT_ENABLE,
+            TRANSMITTER_CONTROL => self.transmitter_control = value & INTERRUPT_ENABLE,
+            TRANSMITTER_DATA if self.transmitter_ready() => {
+                self.transmitter_data = value & 0xFF;
+                self.output.push(self.transmitter_data as u8 as char);
+                self.busy = DISPLAY_DELAY;
+            }
+            _ => {}
+        }
+    }
+
+    fn peek_word(&self, offset: u32) -> u32 {
+        match offset {
+            RECEIVER_CONTROL => self.receiver_control | ready_bit(self.receiver_ready()),
+            RECEIVER_DATA => self.input.get(self.keys_read).unwrap_or_default() as u32,
+            TRANSMITTER_CONTROL => self.transmitter_control | ready_bit(self.transmitter_ready()),
+            TRANSMITTER_DATA => self.transmitter_data,
+            _ => 0,
+        }
+    }
+
+    fn tick(&mut self) {
+        self.busy = self.busy.saturating_sub(1);
+    }
+
+    fn pending_interrupts(&self) -> u32 {
+        let keyboard = self.receiver_interrupt_enabled() && self.receiver_ready();
+        let display = self.transmitter_interrupt_enabled() && self.transmitter_ready();
+        (if keyboard { KEYBOARD_INTERRUPT } else { 0 })
+            | (if display { DISPLAY_INTERRUPT } else { 0 })
+    }
+
+    fn box_clone(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earlier_copies_keep_the_keys_a_later_one_read() {
+        let mut keyboard = KeyboardDisplay::new();
+        keyboard.input().push_str("ab");
+        let before = keyboard.clone();
+
+        assert_eq!(keyboard.read_word(RECEIVER_DATA), 'a' as u32);
+        assert_eq!(keyboard.pending_keys(), 1);
+        assert_eq!(before.pending_keys(), 2);
+        assert_eq!(before.peek_word(RECEIVER_DATA), 'a' as u32);
+
+        // Input typed later reaches every copy.
+        keyboard.input().push_str("c");
+        assert_eq!(keyboard.read_word(RECEIVER_DATA), 'b' as u32);
+        assert_eq!(keyboard.read_word(RECEIVER_DATA), 'c' as u32);
+        assert_eq!(keyboard.read_word(RECEIVER_CONTROL) & READY, 0);
+        assert_eq!(before.pending_keys(), 3);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    any::Any,
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+
+use super::{
+    control_unit::Mem,
+    data_memory::{access_width, AccessKind, DataMem, FaultCause, MemoryFault},
+};
+
+pub mod bitmap_display;
+pub mod keyboard_display;
+pub mod timer;
+
+/// A peripheral that owns a range of the address space. Devices are made of
+/// word sized registers; the bus turns byte and half accesses into reads and
+/// read-modify-writes of the containing word. Devices are `Sync` so one
+/// snapshot of the CPU can be shared by every view.
+pub trait Device: Debug + Send + Sync {
+    /// First address the device responds to.
+    fn base(&self) -> u32;
+    /// Number of bytes the device occupies.
+    fn size(&self) -> u32;
+
+    /// Reads the register at `offset`, which may have side effects such as
+    /// consuming input.
+    fn read_word(&mut self, offset: u32) -> u32;
+    fn write_word(&mut self, offset: u32, value: u32);
+    /// Reads the register at `offset` without side effects, for views.
+    fn peek_word(&self, offset: u32) -> u32;
+
+    /// Called once per executed instruction.
+    fn tick(&mut self) {}
+
+    /// Hardware interrupt lines the device is asserting, bit 0 being IP2.
+    fn pending_interrupts(&self) -> u32 {
+        0
+    }
+
+    /// Whether the device is a window onto ordinary memory rather than a set
+    /// of registers, like the MARS bitmap display. The bus leaves loads and
+    /// stores in the window to memory, which the CPU maps if it is not
+    /// already, and the register methods are never called.
+    fn memory_backed(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Device>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl Clone for Box<dyn Device> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Routes loads and stores that fall inside a device's range to the device
+/// and everything else to `memory`. The bus also tracks LL reservations,
+/// since it sees every store from every core.
+#[derive(Debug, Clone, Default)]
+pub struct Bus<M> {
+    memory: M,
+    devices: Vec<Box<dyn Device>>,
+    reservations: Vec<(usize, u32)>,
+}
+
+impl<M: DataMem> Bus<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            devices: Vec::new(),
+            reservations: Vec::new(),
+        }
+    }
+
+    /// Adds `device` to the bus. Devices attached later take precedence where
+    /// ranges overlap.
+    pub fn attach(&mut self, device: Box<dyn Device>) {
+        self.devices.insert(0, device);
+    }
+
+    pub fn devices(&self) -> &[Box<dyn Device>] {
+        &self.devices
+    }
+
+    pub fn tick(&mut self) {
+        self.devices.iter_mut().for_each(|device| device.tick());
+    }
+
+    pub fn pending_interrupts(&self) -> u32 {
+        self.devices
+            .iter()
+            .fold(0, |lines, device| lines | device.pending_interrupts())
+    }
+
+    /// Reserves the word at `addr` for `core`, replacing its previous
+    /// reservation.
+    pub fn load_linked(&mut self, core: usize, addr: u32) {
+        self.clear_reservation(core);
+        self.reservations.push((core, addr & !3));
+    }
+
+    /// Consumes the reservation of `core` and returns whether it still
+    /// covered `addr`, that is whether no store reached the word since LL.
+    pub fn store_conditional(&mut self, core: usize, addr: u32) -> bool {
+        let linked = self.reservations.contains(&(core, addr & !3));
+        self.clear_reservation(core);
+        linked
+    }
+
+    pub fn clear_reservation(&mut self, core: usize) {
+        self.reservations.retain(|(owner, _)| *owner != core);
+    }
+
+    fn device_at(&self, addr: u32) -> Option<usize> {
+        self.devices.iter().position(|device| {
+            !device.memory_backed()
+                && addr
+                    .checked_sub(device.base())
+                    .is_some_and(|offset| offset < device.size())
+        })
+    }
+}
+
+fn unaligned(addr: u32, access: AccessKind) -> MemoryFault {
+    MemoryFault {
+        access,
+        cause: FaultCause::Unaligned,
+        addr,
+    }
+}
+
+impl<M: DataMem> DataMem for Bus<M> {
+    fn load(&mut self, addr: u32, size: Mem) -> Result<u32, MemoryFault> {
+        let width = access_width(size);
+        let Some(idx) = self.device_at(addr).filter(|_| width != 0) else {
+            return self.memory.load(addr, size);
+        };
+        if addr & (width - 1) != 0 {
+            return Err(unaligned(addr, AccessKind::Load));
+        }
+
+        let device = &mut self.devices[idx];
+        let offset = addr - device.base();
+        let word = device.read_word(offset & !3);
+        Ok((word >> ((offset & 3) * 8)) & (u32::MAX >> (32 - width * 8)))
+    }
+
+    fn store(&mut self, data: u32, addr: u32, size: Mem) -> Result<(), MemoryFault> {
+        let width = access_width(size);
+        if width != 0 {
+            self.reservations.retain(|(_, linked)| *linked != addr & !3);
+        }
+        let Some(idx) = self.device_at(addr).filter(|_| width != 0) else {
+            return self.memory.store(data, addr, size);
+        };
+        if addr & (width - 1) != 0 {
+            return Err(unaligned(addr, AccessKind::Store));
+        }
+
+        let device = &mut self.devices[idx];
+        let offset = addr - device.base();
+        let shift = (offset & 3) * 8;
+        let mask = (u32::MAX >> (32 - width * 8)) << shift;
+        let word = if width == 4 {
+            data
+        } else {
+            (device.peek_word(offset & !3) & !mask) | ((data << shift) & mask)
+        };
+        device.write_word(offset & !3, word);
+        Ok(())
+    }
+
+    fn read_byte(&self, addr: u32) -> Option<u8> {
+        match self.device_at(addr) {
+            Some(idx) => {
+                let device = &self.devices[idx];
+                let offset = addr - device.base();
+                Some(device.peek_word(offset & !3).to_le_bytes()[(offset & 3) as usize])
+            }
+            None => self.memory.read_byte(addr),
+        }
+    }
+
+    /// Writes the byte to the device register under `addr`, as a store
+    /// would, or else to memory.
+    fn write_byte(&mut self, addr: u32, value: u8) -> bool {
+        self.reservations.retain(|(_, linked)| *linked != addr & !3);
+        let Some(idx) = self.device_at(addr) else {
+            return self.memory.write_byte(addr, value);
+        };
+        let device = &mut self.devices[idx];
+        let offset = addr - device.base();
+        let mut bytes = device.peek_word(offset & !3).to_le_bytes();
+        bytes[(offset & 3) as usize] = value;
+        device.write_word(offset & !3, u32::from_le_bytes(bytes));
+        true
+    }
+}
+
+impl<M> Deref for Bus<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.memory
+    }
+}
+
+impl<M> DerefMut for Bus<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{data_memory::DataMemory, DATA_MEM_START};
+    use super::timer::{Timer, TIMER_START};
+    use super::*;
+
+    #[test]
+    fn stores_break_reservations_on_their_word_only() {
+        let mut bus = Bus::new(DataMemory::default());
+        bus.load_linked(0, DATA_MEM_START);
+        bus.load_linked(1, DATA_MEM_START + 4);
+        bus.store(7, DATA_MEM_START + 2, Mem::Byte).unwrap();
+
+        assert!(!bus.store_conditional(0, DATA_MEM_START));
+        assert!(bus.store_conditional(1, DATA_MEM_START + 4));
+    }
+
+    #[test]
+    fn debugger_writes_reach_device_registers() {
+        let mut bus = Bus::new(DataMemory::default());
+        bus.attach(Box::new(Timer::new()));
+        assert!(bus.write_byte(TIMER_START + 5, 0x12));
+        assert_eq!(bus.read_byte(TIMER_START + 5), Some(0x12));
+        assert_eq!(bus.load(TIMER_START + 4, Mem::Word), Ok(0x1200));
+        assert!(bus.write_byte(DATA_MEM_START, 7));
+        assert_eq!(bus.read_byte(DATA_MEM_START), Some(7));
+    }
+
+    #[test]
+    fn sc_consumes_the_reservation() {
+        let mut bus = Bus::new(DataMemory::default());
+        bus.load_linked(0, DATA_MEM_START);
+        assert!(bus.store_conditional(0, DATA_MEM_START));
+        assert!(!bus.store_conditional(0, DATA_MEM_START));
+    }
+}
@@ -0,0 +1,169 @@
+use std::any::Any;
+
+use super::Device;
+use crate::cpu::MMIO_START;
+
+pub const TIMER_START: u32 = MMIO_START + 0x10;
+
+const CONTROL: u32 = 0x0;
+const PERIOD: u32 = 0x4;
+const COUNTER: u32 = 0x8;
+const STATUS: u32 = 0xC;
+
+const ENABLE: u32 = 1 << 0;
+const INTERRUPT_ENABLE: u32 = 1 << 1;
+const PERIODIC: u32 = 1 << 2;
+const EXPIRED: u32 = 1 << 0;
+
+/// Interrupt line of the timer, IP4.
+const TIMER_INTERRUPT: u32 = 1 << 2;
+
+/// Programmable interval timer at 0xFFFF0010.
+///
+/// Writing Control with the enable bit loads Counter from Period. Counter
+/// then counts down once per executed instruction, so timing is the same on
+/// every run. When it reaches zero Status reports expiry, the timer reloads
+/// if it is periodic or stops otherwise, and IP4 is raised while interrupts
+/// are enabled. Writing 1 to Status acknowledges expiry.
+///
+/// | Offset | Register                                      |
+/// |--------|-----------------------------------------------|
+/// | 0x0    | Control: enable, interrupt enable, periodic   |
+/// | 0x4    | Period in instructions                        |
+/// | 0x8    | Counter, instructions until expiry            |
+/// | 0xC    | Status: expired                               |
+#[derive(Debug, Clone, Default)]
+pub struct Timer {
+    control: u32,
+    period: u32,
+    counter: u32,
+    status: u32,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Device for Timer {
+    fn base(&self) -> u32 {
+        TIMER_START
+    }
+
+    fn size(&self) -> u32 {
+        16
+    }
+
+    fn read_word(&mut self, offset: u32) -> u32 {
+        self.peek_word(offset)
+    }
+
+    fn write_word(&mut self, offset: u32, value: u32) {
+        match offset {
+            CONTROL => {
+                if value & ENABLE != 0 && self.control & ENABLE == 0 {
+                    self.counter = self.period;
+                }
+                self.control = value & (ENABLE | INTERRUPT_ENABLE | PERIODIC);
+            }
+            PERIOD => self.period = value,
+            COUNTER => self.counter = value,
+            STATUS => self.status &= !(value & EXPIRED),
+            _ => {}
+        }
+    }
+
+    fn peek_word(&self, offset: u32) -> u32 {
+        match offset {
+            CONTROL => self.control,
+            PERIOD => self.period,
+            COUNTER => self.counter,
+            STATUS => self.status,
+            _ => 0,
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.control & ENABLE == 0 || self.counter == 0 {
+            return;
+        }
+
+        self.counter -= 1;
+        if self.counter == 0 {
+            self.status |= EXPIRED;
+            if self.control & PERIODIC != 0 {
+                self.counter = self.period;
+            } else {
+                self.control &= !ENABLE;
+            }
+        }
+    }
+
+    fn pending_interrupts(&self) -> u32 {
+        if self.control & INTERRUPT_ENABLE != 0 && self.status & EXPIRED != 0 {
+            TIMER_INTERRUPT
+        } else {
+            0
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(period: u32, control: u32) -> Timer {
+        let mut timer = Timer::new();
+        timer.write_word(PERIOD, period);
+        timer.write_word(CONTROL, control);
+        timer
+    }
+
+    #[test]
+    fn periodic_timer_expires_every_period() {
+        let mut timer = timer(3, ENABLE | INTERRUPT_ENABLE | PERIODIC);
+        let expiries = (1..=9)
+            .filter(|_| {
+                timer.tick();
+                let expired = timer.peek_word(STATUS) & EXPIRED != 0;
+                timer.write_word(STATUS, EXPIRED);
+                expired
+            })
+            .count();
+        assert_eq!(expiries, 3);
+        assert_eq!(timer.peek_word(COUNTER), 3);
+        assert_eq!(timer.peek_word(CONTROL) & ENABLE, ENABLE);
+    }
+
+    #[test]
+    fn one_shot_timer_interrupts_until_acknowledged() {
+        let mut timer = timer(2, ENABLE | INTERRUPT_ENABLE);
+        timer.tick();
+        assert_eq!(timer.pending_interrupts(), 0);
+        timer.tick();
+        assert_eq!(timer.pending_interrupts(), TIMER_INTERRUPT);
+        assert_eq!(timer.peek_word(CONTROL) & ENABLE, 0);
+
+        timer.tick();
+        assert_eq!(timer.pending_interrupts(), TIMER_INTERRUPT);
+        timer.write_word(STATUS, EXPIRED);
+        assert_eq!(timer.pending_interrupts(), 0);
+    }
+
+    #[test]
+    fn masked_timer_does_not_interrupt() {
+        let mut timer = timer(1, ENABLE);
+        timer.tick();
+        assert_eq!(timer.peek_word(STATUS), EXPIRED);
+        assert_eq!(timer.pending_interrupts(), 0);
+    }
+}
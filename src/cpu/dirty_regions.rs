@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+/// Byte ranges of memory written since the tracker was last taken.
+///
+/// Ranges are kept sorted with overlapping and adjacent ones merged, so a
+/// loop filling an array stays a single range however many stores it makes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyRegions {
+    ranges: Vec<Range<u32>>,
+}
+
+impl DirtyRegions {
+    pub fn mark(&mut self, addr: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+        let end = addr.saturating_add(len);
+        let first = self.ranges.partition_point(|range| range.end < addr);
+        let last = self.ranges.partition_point(|range| range.start <= end);
+        let merged = if first < last {
+            self.ranges[first].start.min(addr)..self.ranges[last - 1].end.max(end)
+        } else {
+            addr..end
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    /// Adds the ranges of `other`.
+    pub fn merge(&mut self, other: DirtyRegions) {
+        other
+            .ranges
+            .into_iter()
+            .for_each(|range| self.mark(range.start, range.end - range.start));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[Range<u32>] {
+        &self.ranges
+    }
+
+    /// The dirty parts of `bounds`, in ascending order.
+    pub fn within(&self, bounds: Range<u32>) -> impl Iterator<Item = Range<u32>> + '_ {
+        self.ranges
+            .iter()
+            .map(move |range| range.start.max(bounds.start)..range.end.min(bounds.end))
+            .filter(|range| !range.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(marks: &[(u32, u32)]) -> DirtyRegions {
+        let mut dirty = DirtyRegions::default();
+        marks.iter().for_each(|(addr, len)| dirty.mark(*addr, *len));
+        dirty
+    }
+
+    #[test]
+    fn adjacent_and_overlapping_marks_merge() {
+        let dirty = marked(&[(0, 4), (4, 4), (6, 4)]);
+        assert_eq!(dirty.ranges().len(), 1);
+        assert_eq!(dirty.ranges()[0], 0..10);
+    }
+
+    #[test]
+    fn separate_marks_stay_sorted() {
+        let dirty = marked(&[(20, 4), (0, 1), (10, 2)]);
+        assert_eq!(dirty.ranges(), [0..1, 10..12, 20..24]);
+    }
+
+    #[test]
+    fn a_mark_bridging_ranges_joins_them() {
+        let dirty = marked(&[(0, 2), (10, 2), (20, 2), (1, 10)]);
+        assert_eq!(dirty.ranges(), [0..12, 20..22]);
+    }
+
+    #[test]
+    fn empty_marks_and_the_top_of_memory() {
+        let dirty = marked(&[(8, 0), (u32::MAX - 1, 4)]);
+        assert_eq!(dirty.ranges().len(), 1);
+        assert_eq!(dirty.ranges()[0], u32::MAX - 1..u32::MAX);
+    }
+
+    #[test]
+    fn merge_and_within() {
+        let mut dirty = marked(&[(0, 4), (16, 4)]);
+        dirty.merge(marked(&[(4, 4), (30, 2)]));
+        assert_eq!(dirty.ranges(), [0..8, 16..20, 30..32]);
+        assert_eq!(dirty.within(6..18).collect::<Vec<_>>(), [6..8, 16..18]);
+    }
+}
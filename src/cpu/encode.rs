@@ -0,0 +1,110 @@
+//! Instruction encoders for tests that build programs without the assembler.
+
+use super::{
+    BEQ_OPCODE, BNE_OPCODE, BREAK_FUNCT, COP0_CO, COP0_MF, COP0_MT, COP0_OPCODE, ERET_FUNCT,
+    JAL_OPCODE, JR_FUNCT, J_OPCODE, LL_OPCODE, LUI_OPCODE, SC_OPCODE, SW_OPCODE, SYSCALL_FUNCT,
+    TLBWI_FUNCT,
+};
+
+pub const ZERO: u32 = 0;
+pub const V0: u32 = 2;
+pub const A0: u32 = 4;
+pub const T0: u32 = 8;
+pub const T1: u32 = 9;
+pub const T2: u32 = 10;
+pub const SP: u32 = 29;
+pub const RA: u32 = 31;
+
+const ADDIU_OPCODE: u32 = 0x09;
+const ORI_OPCODE: u32 = 0x0D;
+const LW_OPCODE: u32 = 0x23;
+
+pub fn r_type(funct: u32, rd: u32, rs: u32, rt: u32) -> u32 {
+    rs << 21 | rt << 16 | rd << 11 | funct
+}
+
+pub fn i_type(opcode: u32, rt: u32, rs: u32, imm: i16) -> u32 {
+    opcode << 26 | rs << 21 | rt << 16 | imm as u16 as u32
+}
+
+pub fn addiu(rt: u32, rs: u32, imm: i16) -> u32 {
+    i_type(ADDIU_OPCODE, rt, rs, imm)
+}
+
+pub fn ori(rt: u32, rs: u32, imm: u16) -> u32 {
+    i_type(ORI_OPCODE, rt, rs, imm as i16)
+}
+
+pub fn lui(rt: u32, imm: u16) -> u32 {
+    i_type(LUI_OPCODE, rt, ZERO, imm as i16)
+}
+
+/// `lui` and `ori` loading the 32-bit `value` into `rt`.
+pub fn li(rt: u32, value: u32) -> [u32; 2] {
+    [lui(rt, (value >> 16) as u16), ori(rt, rt, value as u16)]
+}
+
+pub fn lw(rt: u32, offset: i16, base: u32) -> u32 {
+    i_type(LW_OPCODE, rt, base, offset)
+}
+
+pub fn sw(rt: u32, offset: i16, base: u32) -> u32 {
+    i_type(SW_OPCODE, rt, base, offset)
+}
+
+pub fn ll(rt: u32, offset: i16, base: u32) -> u32 {
+    i_type(LL_OPCODE, rt, base, offset)
+}
+
+pub fn sc(rt: u32, offset: i16, base: u32) -> u32 {
+    i_type(SC_OPCODE, rt, base, offset)
+}
+
+/// Branches `offset` words from the branch itself, as the datapath does.
+pub fn beq(rs: u32, rt: u32, offset: i16) -> u32 {
+    i_type(BEQ_OPCODE, rt, rs, offset)
+}
+
+pub fn bne(rs: u32, rt: u32, offset: i16) -> u32 {
+    i_type(BNE_OPCODE, rt, rs, offset)
+}
+
+pub fn j(target: u32) -> u32 {
+    J_OPCODE << 26 | (target >> 2) & 0x03FF_FFFF
+}
+
+pub fn jal(target: u32) -> u32 {
+    JAL_OPCODE << 26 | (target >> 2) & 0x03FF_FFFF
+}
+
+pub fn jr(rs: u32) -> u32 {
+    r_type(JR_FUNCT, ZERO, rs, ZERO)
+}
+
+pub fn syscall() -> u32 {
+    SYSCALL_FUNCT
+}
+
+pub fn brk(code: u32) -> u32 {
+    code << 6 | BREAK_FUNCT
+}
+
+pub fn nop() -> u32 {
+    0
+}
+
+pub fn mfc0(rt: u32, rd: u32) -> u32 {
+    COP0_OPCODE << 26 | COP0_MF << 21 | rt << 16 | rd << 11
+}
+
+pub fn mtc0(rt: u32, rd: u32) -> u32 {
+    COP0_OPCODE << 26 | COP0_MT << 21 | rt << 16 | rd << 11
+}
+
+pub fn tlbwi() -> u32 {
+    COP0_OPCODE << 26 | COP0_CO << 21 | TLBWI_FUNCT
+}
+
+pub fn eret() -> u32 {
+    COP0_OPCODE << 26 | COP0_CO << 21 | ERET_FUNCT
+}
@@ -4,10 +4,17 @@ pub type InstructionMemory = Vec<u32>;
 
 pub trait InstructionMem {
     fn get_instruction(&self, pc: u32) -> Option<u32>;
+    fn end_address(&self) -> u32;
 }
 
 impl InstructionMem for InstructionMemory {
     fn get_instruction(&self, pc: u32) -> Option<u32> {
-        self.get(((pc - INST_MEM_START) as usize) / 4).copied()
+        pc.checked_sub(INST_MEM_START)
+            .and_then(|offset| self.get(offset as usize / 4))
+            .copied()
+    }
+
+    fn end_address(&self) -> u32 {
+        INST_MEM_START + self.len() as u32 * 4
     }
 }
@@ -0,0 +1,266 @@
+use super::{
+    cp0::{
+        Cp0, Exception, ExceptionCode, CP0_ENTRY_HI, CP0_ENTRY_LO0, CP0_ENTRY_LO1, CP0_INDEX,
+        CP0_PAGE_MASK, CP0_RANDOM, CP0_STATUS, CP0_WIRED, STATUS_ERL,
+    },
+    data_memory::AccessKind,
+};
+
+const KSEG2_START: u32 = 0xC0000000;
+const ENTRY_LO_VALID: u32 = 1 << 1;
+const ENTRY_LO_DIRTY: u32 = 1 << 2;
+const ENTRY_LO_GLOBAL: u32 = 1 << 0;
+const INDEX_PROBE_FAILED: u32 = 1 << 31;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlbConfig {
+    pub entries: u32,
+    pub associativity: u32,
+}
+
+impl Default for TlbConfig {
+    fn default() -> Self {
+        Self {
+            entries: 16,
+            associativity: 16,
+        }
+    }
+}
+
+impl TlbConfig {
+    pub fn ways(&self) -> u32 {
+        self.associativity.clamp(1, self.entries.max(1))
+    }
+
+    pub fn sets(&self) -> u32 {
+        (self.entries.max(1) / self.ways()).max(1)
+    }
+}
+
+/// How virtual addresses become physical ones. Identity mapping is the
+/// default and leaves every address untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressTranslation {
+    #[default]
+    Identity,
+    Tlb(TlbConfig),
+}
+
+/// One TLB entry mapping an even/odd pair of pages, laid out like EntryHi,
+/// EntryLo0, EntryLo1 and PageMask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TlbEntry {
+    pub entry_hi: u32,
+    pub entry_lo0: u32,
+    pub entry_lo1: u32,
+    pub page_mask: u32,
+}
+
+impl TlbEntry {
+    fn global(&self) -> bool {
+        self.entry_lo0 & self.entry_lo1 & ENTRY_LO_GLOBAL != 0
+    }
+
+    fn offset_mask(&self) -> u32 {
+        (self.page_mask | 0x1FFF) >> 1
+    }
+
+    fn matches(&self, entry_hi: u32) -> bool {
+        let vpn2_mask = !(self.page_mask | 0x1FFF);
+        (self.entry_hi & vpn2_mask) == (entry_hi & vpn2_mask)
+            && (self.global() || (self.entry_hi & 0xFF) == (entry_hi & 0xFF))
+    }
+}
+
+/// Software managed TLB in the style of the MIPS32 R4000 MMU.
+///
+/// Addresses below 0x80000000 (kuseg) and from 0xC0000000 up to MMIO
+/// (kseg2) are translated, while the kernel segments and MMIO are passed
+/// through unchanged so physical addresses keep the SPIM/MARS layout. While
+/// Status.ERL is set, as it is after reset, kuseg is also passed through so
+/// a program can fill the TLB before turning translation on.
+#[derive(Debug, Clone)]
+pub struct Tlb {
+    config: TlbConfig,
+    entries: Vec<TlbEntry>,
+}
+
+impl Tlb {
+    pub fn new(config: TlbConfig) -> Self {
+        Self {
+            config,
+            entries: vec![TlbEntry::default(); (config.sets() * config.ways()) as usize],
+        }
+    }
+
+    pub fn config(&self) -> TlbConfig {
+        self.config
+    }
+
+    pub fn entries(&self) -> &[TlbEntry] {
+        &self.entries
+    }
+
+    fn set_of(&self, entry_hi: u32) -> usize {
+        ((entry_hi >> 13) % self.config.sets()) as usize
+    }
+
+    fn set_range(&self, entry_hi: u32) -> std::ops::Range<usize> {
+        let ways = self.config.ways() as usize;
+        let start = self.set_of(entry_hi) * ways;
+        start..start + ways
+    }
+
+    fn lookup(&self, entry_hi: u32) -> Option<usize> {
+        self.set_range(entry_hi)
+            .find(|&idx| self.entries[idx].matches(entry_hi))
+    }
+
+    pub fn translate(&self, addr: u32, access: AccessKind, cp0: &Cp0) -> Result<u32, Exception> {
+        let mapped = match addr {
+            0..=0x7FFFFFFF => cp0.status() & STATUS_ERL == 0,
+            KSEG2_START..=0xFFFEFFFF => true,
+            _ => false,
+        };
+        if !mapped {
+            return Ok(addr);
+        }
+
+        let store = access == AccessKind::Store;
+        let exception = |code, refill| Exception {
+            code,
+            bad_vaddr: Some(addr),
+            refill,
+        };
+        let miss_code = if store {
+            ExceptionCode::TlbStore
+        } else {
+            ExceptionCode::TlbLoad
+        };
+
+        let asid = cp0.read(CP0_ENTRY_HI) & 0xFF;
+        let Some(idx) = self.lookup((addr & 0xFFFFE000) | asid) else {
+            return Err(exception(miss_code, true));
+        };
+
+        let entry = &self.entries[idx];
+        let offset_mask = entry.offset_mask();
+        let entry_lo = if addr & (offset_mask + 1) == 0 {
+            entry.entry_lo0
+        } else {
+            entry.entry_lo1
+        };
+
+        if entry_lo & ENTRY_LO_VALID == 0 {
+            return Err(exception(miss_code, false));
+        }
+        if store && entry_lo & ENTRY_LO_DIRTY == 0 {
+            return Err(exception(ExceptionCode::TlbModified, false));
+        }
+
+        let frame = ((entry_lo >> 6) & 0xFFFFF) << 12;
+        Ok((frame & !offset_mask) | (addr & offset_mask))
+    }
+
+    /// Puts CP0 in its reset state for this TLB, with Random at the last
+    /// entry and Status.ERL set.
+    pub fn reset(&self, cp0: &mut Cp0) {
+        cp0.set(CP0_RANDOM, self.entries.len() as u32 - 1);
+        cp0.set(CP0_STATUS, cp0.status() | STATUS_ERL);
+    }
+
+    /// TLBR: loads the entry selected by Index into EntryHi, EntryLo0,
+    /// EntryLo1 and PageMask.
+    pub fn read(&self, cp0: &mut Cp0) {
+        let idx = cp0.read(CP0_INDEX) as usize % self.entries.len();
+        let entry = self.entries[idx];
+        cp0.set(CP0_ENTRY_HI, entry.entry_hi);
+        cp0.set(CP0_ENTRY_LO0, entry.entry_lo0);
+        cp0.set(CP0_ENTRY_LO1, entry.entry_lo1);
+        cp0.set(CP0_PAGE_MASK, entry.page_mask);
+    }
+
+    /// TLBWI: writes the CP0 entry registers to the entry selected by Index.
+    pub fn write_indexed(&mut self, cp0: &Cp0) {
+        let idx = cp0.read(CP0_INDEX) as usize % self.entries.len();
+        self.entries[idx] = Self::entry_from(cp0);
+    }
+
+    /// TLBWR: writes the CP0 entry registers to the way chosen by Random
+    /// within the set EntryHi maps to.
+    pub fn write_random(&mut self, cp0: &Cp0) {
+        let entry = Self::entry_from(cp0);
+        let ways = self.config.ways() as usize;
+        let idx = if ways == self.entries.len() {
+            cp0.read(CP0_RANDOM) as usize % self.entries.len()
+        } else {
+            self.set_range(entry.entry_hi).start + cp0.read(CP0_RANDOM) as usize % ways
+        };
+        self.entries[idx] = entry;
+    }
+
+    /// TLBP: sets Index to the entry matching EntryHi, or sets its high bit
+    /// when there is none.
+    pub fn probe(&self, cp0: &mut Cp0) {
+        let index = self
+            .lookup(cp0.read(CP0_ENTRY_HI))
+            .map_or(INDEX_PROBE_FAILED, |idx| idx as u32);
+        cp0.set(CP0_INDEX, index);
+    }
+
+    /// Advances Random, which counts down from the last entry to Wired on
+    /// every instruction.
+    pub fn tick(&self, cp0: &mut Cp0) {
+        let last = self.entries.len() as u32 - 1;
+        let wired = cp0.read(CP0_WIRED).min(last);
+        let random = cp0.read(CP0_RANDOM);
+        let next = if random <= wired || random > last {
+            last
+        } else {
+            random - 1
+        };
+        cp0.set(CP0_RANDOM, next);
+    }
+
+    fn entry_from(cp0: &Cp0) -> TlbEntry {
+        let page_mask = cp0.read(CP0_PAGE_MASK) & 0x1FFFE000;
+        TlbEntry {
+            entry_hi: cp0.read(CP0_ENTRY_HI) & !(page_mask | 0x1F00),
+            entry_lo0: cp0.read(CP0_ENTRY_LO0) & 0x03FFFFFF,
+            entry_lo1: cp0.read(CP0_ENTRY_LO1) & 0x03FFFFFF,
+            page_mask,
+        }
+    }
+}
+
+/// Translates addresses according to the configured [`AddressTranslation`].
+#[derive(Debug, Clone, Default)]
+pub struct Mmu {
+    tlb: Option<Tlb>,
+}
+
+impl Mmu {
+    pub fn new(translation: AddressTranslation) -> Self {
+        Self {
+            tlb: match translation {
+                AddressTranslation::Identity => None,
+                AddressTranslation::Tlb(config) => Some(Tlb::new(config)),
+            },
+        }
+    }
+
+    pub fn tlb(&self) -> Option<&Tlb> {
+        self.tlb.as_ref()
+    }
+
+    pub fn tlb_mut(&mut self) -> Option<&mut Tlb> {
+        self.tlb.as_mut()
+    }
+
+    pub fn translate(&self, addr: u32, access: AccessKind, cp0: &Cp0) -> Result<u32, Exception> {
+        match &self.tlb {
+            Some(tlb) => tlb.translate(addr, access, cp0),
+            None => Ok(addr),
+        }
+    }
+}
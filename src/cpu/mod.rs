@@ -1,25 +1,58 @@
 #![allow(dead_code)]
 
 pub mod alu;
+pub mod cache;
+pub mod call_stack;
 pub mod control_unit;
+pub mod cp0;
 pub mod cpu_interface;
 pub mod data_memory;
+pub mod devices;
+pub mod dirty_regions;
+#[cfg(test)]
+mod encode;
 pub mod instruction_memory;
+pub mod mmu;
+pub mod multicore;
+pub mod paged_memory;
 pub mod piplined_cpu;
+pub mod profiler;
 pub mod registers;
 pub mod single_cycle_cpu;
+pub mod statistics;
 pub mod utils;
+pub mod watchpoints;
 
-const INST_MEM_START: u32 = 0x00400000;
-const DATA_MEM_START: u32 = 0x10010000;
+pub const INST_MEM_START: u32 = 0x00400000;
+const EXTERN_START: u32 = 0x10000000;
+pub const DATA_MEM_START: u32 = 0x10010000;
+const HEAP_START: u32 = 0x10040000;
+const STACK_LIMIT: u32 = 0x7F000000;
+const STACK_END: u32 = 0x80000000;
+const KERNEL_TEXT_START: u32 = 0x80000000;
+const KERNEL_DATA_START: u32 = 0x90000000;
+const MMIO_START: u32 = 0xFFFF0000;
+
+const GLOBAL_POINTER_START: u32 = 0x10008000;
+const STACK_POINTER_START: u32 = 0x7FFFEFFC;
+/// Stack space given to each core before the next core's stack begins.
+const CORE_STACK_SIZE: u32 = 0x00100000;
 
 const BEQ_OPCODE: u32 = 0x04;
 const BNE_OPCODE: u32 = 0x05;
+const J_OPCODE: u32 = 0x02;
+const JAL_OPCODE: u32 = 0x03;
+const LUI_OPCODE: u32 = 0x0F;
+const COP0_OPCODE: u32 = 0x10;
+const LL_OPCODE: u32 = 0x30;
+const SC_OPCODE: u32 = 0x38;
+const SW_OPCODE: u32 = 0x2B;
 
 const ADD_FUNCT: u32 = 0x20;
 const ADDU_FUNCT: u32 = 0x21;
 const AND_FUNCT: u32 = 0x24;
 const JR_FUNCT: u32 = 0x08;
+const JALR_FUNCT: u32 = 0x09;
 const NOR_FUNCT: u32 = 0x27;
 const OR_FUNCT: u32 = 0x25;
 const SLT_FUNCT: u32 = 0x2A;
@@ -28,3 +61,18 @@ const SLL_FUNCT: u32 = 0x00;
 const SRL_FUNCT: u32 = 0x02;
 const SUB_FUNCT: u32 = 0x22;
 const SUBU_FUNCT: u32 = 0x23;
+const SYSCALL_FUNCT: u32 = 0x0C;
+const BREAK_FUNCT: u32 = 0x0D;
+
+const COP0_MF: u32 = 0x00;
+const COP0_MT: u32 = 0x04;
+const COP0_CO: u32 = 0x10;
+const TLBR_FUNCT: u32 = 0x01;
+const TLBWI_FUNCT: u32 = 0x02;
+const TLBWR_FUNCT: u32 = 0x06;
+const TLBP_FUNCT: u32 = 0x08;
+const ERET_FUNCT: u32 = 0x18;
+
+const SYSCALL_SBRK: u32 = 9;
+const SYSCALL_EXIT: u32 = 10;
+const SYSCALL_EXIT2: u32 = 17;
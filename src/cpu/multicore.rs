@@ -0,0 +1,419 @@
+use super::{
+    alu::AluOperation,
+    cache::{Cache, CacheConfig, CachedMemory},
+    call_stack::CallStack,
+    control_unit::ControlUnitOutput,
+    cpu_interface::{CPUInterface, HaltReason, RegisterKind},
+    data_memory::{DataMem, DataMemory, MemoryArchitecture},
+    devices::{Bus, Device},
+    dirty_regions::DirtyRegions,
+    instruction_memory::InstructionMemory,
+    mmu::{AddressTranslation, Tlb},
+    profiler::Profile,
+    single_cycle_cpu::{SingleCycleCPU, SystemMemory},
+    statistics::Statistics,
+    watchpoints::{WatchHit, Watchpoint},
+    DATA_MEM_START,
+};
+
+/// Order in which cores take turns executing instructions. Both are
+/// deterministic, so a race shows up the same way on every run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interleaving {
+    /// Each core runs `quantum` instructions before the next one.
+    RoundRobin { quantum: u32 },
+    /// A core is picked at random for every instruction.
+    Random { seed: u64 },
+}
+
+impl Default for Interleaving {
+    fn default() -> Self {
+        Interleaving::RoundRobin { quantum: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticoreConfig {
+    pub cores: usize,
+    pub interleaving: Interleaving,
+}
+
+impl Default for MulticoreConfig {
+    fn default() -> Self {
+        Self {
+            cores: 1,
+            interleaving: Interleaving::default(),
+        }
+    }
+}
+
+/// Several cores running the same program against one shared memory.
+///
+/// The memory, its caches and devices live here and are moved into a core
+/// for the duration of its step, so every load and store goes through the
+/// same bus and LL/SC reservations see stores from all cores.
+#[derive(Debug, Clone)]
+pub struct MulticoreCPU {
+    cores: Vec<SingleCycleCPU>,
+    memory: SystemMemory,
+    interleaving: Interleaving,
+    current: usize,
+    quantum_used: u32,
+    rng_state: u64,
+}
+
+impl Default for MulticoreCPU {
+    fn default() -> Self {
+        Self::new_from_memory(
+            Vec::new(),
+            DataMemory::default(),
+            MulticoreConfig::default(),
+        )
+    }
+}
+
+impl MulticoreCPU {
+    pub fn new_from_memory(
+        instruction_memory: InstructionMemory,
+        data_memory: DataMemory,
+        config: MulticoreConfig,
+    ) -> Self {
+        let cores = (0..config.cores.max(1))
+            .map(|id| {
+                let mut core = SingleCycleCPU::new_from_memory(
+                    instruction_memory.clone(),
+                    DataMemory::default(),
+                );
+                core.set_core_id(id);
+                core
+            })
+            .collect();
+
+        Self {
+            cores,
+            memory: Bus::new(CachedMemory::new(data_memory)),
+            interleaving: config.interleaving,
+            current: 0,
+            quantum_used: 0,
+            rng_state: match config.interleaving {
+                Interleaving::Random { seed } => seed | 1,
+                Interleaving::RoundRobin { .. } => 1,
+            },
+        }
+    }
+
+    pub fn cores(&self) -> &[SingleCycleCPU] {
+        &self.cores
+    }
+
+    /// The program, which every core runs from its own copy.
+    pub fn instruction_memory(&self) -> &InstructionMemory {
+        &self.cores[0].instruction_memory
+    }
+
+    pub fn set_core_register(&mut self, core: usize, reg: RegisterKind, value: u32) {
+        self.cores[core].set_register(reg, value);
+    }
+
+    /// The core that executed the most recent instruction.
+    pub fn current_core(&self) -> usize {
+        self.current
+    }
+
+    /// Runs `f` on core `idx` with the shared memory attached.
+    fn with_core<R>(&mut self, idx: usize, f: impl FnOnce(&mut SingleCycleCPU) -> R) -> R {
+        let core = &mut self.cores[idx];
+        std::mem::swap(&mut core.data_memory, &mut self.memory);
+        let result = f(core);
+        std::mem::swap(&mut core.data_memory, &mut self.memory);
+        result
+    }
+
+    fn for_each_core(&mut self, mut f: impl FnMut(&mut SingleCycleCPU)) {
+        (0..self.cores.len()).for_each(|idx| self.with_core(idx, &mut f));
+    }
+
+    fn runnable(&self, idx: usize) -> bool {
+        !self.cores[idx].is_halted() && self.cores[idx].get_error().is_none()
+    }
+
+    /// The core the next step runs, `None` once every core has stopped.
+    pub fn scheduled_core(&self) -> Option<usize> {
+        self.schedule().map(|(core, _, _)| core)
+    }
+
+    fn next_core(&mut self) -> Option<usize> {
+        let (core, quantum_used, rng_state) = self.schedule()?;
+        self.current = core;
+        self.quantum_used = quantum_used;
+        self.rng_state = rng_state;
+        Some(core)
+    }
+
+    /// Picks the core to run next, with the quantum used and random state
+    /// after it runs.
+    fn schedule(&self) -> Option<(usize, u32, u64)> {
+        let runnable = (0..self.cores.len())
+            .filter(|idx| self.runnable(*idx))
+            .collect::<Vec<_>>();
+        if runnable.is_empty() {
+            return None;
+        }
+
+        match self.interleaving {
+            Interleaving::RoundRobin { quantum } => {
+                if !self.runnable(self.current) || self.quantum_used >= quantum.max(1) {
+                    let core = runnable
+                        .iter()
+                        .copied()
+                        .find(|idx| *idx > self.current)
+                        .unwrap_or(runnable[0]);
+                    Some((core, 1, self.rng_state))
+                } else {
+                    Some((self.current, self.quantum_used + 1, self.rng_state))
+                }
+            }
+            Interleaving::Random { .. } => {
+                let mut rng_state = self.rng_state;
+                rng_state ^= rng_state << 13;
+                rng_state ^= rng_state >> 7;
+                rng_state ^= rng_state << 17;
+                let core = runnable[(rng_state % runnable.len() as u64) as usize];
+                Some((core, self.quantum_used, rng_state))
+            }
+        }
+    }
+}
+
+impl CPUInterface for MulticoreCPU {
+    fn get_memory_size(&self) -> u32 {
+        self.memory.static_data_size()
+    }
+
+    fn get_instruction_size(&self) -> u32 {
+        self.cores[0].get_instruction_size()
+    }
+
+    fn get_register(&self, reg: RegisterKind) -> u32 {
+        self.cores[self.current].get_register(reg)
+    }
+
+    fn get_memory_byte(&self, address: u32) -> Option<u8> {
+        self.memory.read_byte(DATA_MEM_START.wrapping_add(address))
+    }
+
+    fn set_register(&mut self, reg: RegisterKind, value: u32) {
+        self.set_core_register(self.current, reg, value);
+    }
+
+    fn set_memory_byte(&mut self, address: u32, value: u8) -> bool {
+        self.memory
+            .write_byte(DATA_MEM_START.wrapping_add(address), value)
+    }
+
+    fn get_control_signals(&self) -> ControlUnitOutput {
+        self.cores[self.current].get_control_signals()
+    }
+
+    fn get_alu_signals(&self) -> AluOperation {
+        self.cores[self.current].get_alu_signals()
+    }
+
+    /// The first error raised by any core, naming the core when there are
+    /// several.
+    fn get_error(&self) -> Option<String> {
+        self.cores.iter().enumerate().find_map(|(idx, core)| {
+            core.get_error().map(|error| match self.cores.len() {
+                1 => error,
+                _ => format!("Core {idx}: {error}"),
+            })
+        })
+    }
+
+    /// An exit or break on any core stops the whole machine, other halts
+    /// only stop their core until every core has halted.
+    fn get_halt_reason(&self) -> Option<HaltReason> {
+        let reasons = self
+            .cores
+            .iter()
+            .map(|core| core.get_halt_reason())
+            .collect::<Vec<_>>();
+        reasons
+            .iter()
+            .flatten()
+            .find(|reason| matches!(reason, HaltReason::Exit(_) | HaltReason::Break(_)))
+            .copied()
+            .or_else(|| {
+                reasons
+                    .iter()
+                    .all(Option::is_some)
+                    .then(|| reasons[0])
+                    .flatten()
+            })
+    }
+
+    fn set_halt_address(&mut self, address: Option<u32>) {
+        self.cores
+            .iter_mut()
+            .for_each(|core| core.set_halt_address(address));
+    }
+
+    fn set_memory_architecture(&mut self, architecture: MemoryArchitecture) {
+        self.for_each_core(|core| core.set_memory_architecture(architecture));
+    }
+
+    fn configure_caches(&mut self, data: Option<CacheConfig>, instruction: Option<CacheConfig>) {
+        self.memory.configure(data, instruction);
+    }
+
+    fn get_data_cache(&self) -> Option<&Cache> {
+        self.memory.data_cache()
+    }
+
+    fn get_instruction_cache(&self) -> Option<&Cache> {
+        self.memory.instruction_cache()
+    }
+
+    fn set_address_translation(&mut self, translation: AddressTranslation) {
+        self.cores
+            .iter_mut()
+            .for_each(|core| core.set_address_translation(translation));
+    }
+
+    fn get_cp0_register(&self, reg: u32) -> u32 {
+        self.cores[self.current].get_cp0_register(reg)
+    }
+
+    fn get_tlb(&self) -> Option<&Tlb> {
+        self.cores[self.current].get_tlb()
+    }
+
+    fn attach_device(&mut self, device: Box<dyn Device>) {
+        if device.memory_backed() {
+            self.memory.map_window(device.base(), device.size());
+        }
+        self.memory.attach(device);
+    }
+
+    fn get_devices(&self) -> &[Box<dyn Device>] {
+        self.memory.devices()
+    }
+
+    /// Counters summed over all cores. Cores take turns, so the summed cycles
+    /// are the cycles of the whole machine.
+    fn get_statistics(&self) -> Statistics {
+        self.cores
+            .iter()
+            .map(|core| core.get_statistics())
+            .fold(Statistics::default(), |total, core| total + core)
+    }
+
+    fn get_profile(&self) -> Profile {
+        self.cores
+            .iter()
+            .map(|core| core.get_profile())
+            .fold(Profile::default(), |total, core| total + core)
+    }
+
+    fn set_watchpoints(&mut self, watchpoints: &[Watchpoint]) {
+        self.for_each_core(|core| core.set_watchpoints(watchpoints));
+    }
+
+    /// Memory is attached to a core only while it steps, so the shared
+    /// memory's hit is checked here along with the last core's registers.
+    fn get_watch_hit(&self) -> Option<WatchHit> {
+        self.memory
+            .watchpoints()
+            .hit()
+            .or(self.cores[self.current].get_watch_hit())
+    }
+
+    fn get_call_stack(&self) -> &CallStack {
+        self.cores[self.current].get_call_stack()
+    }
+
+    fn get_last_store(&self) -> Option<(u32, u32)> {
+        self.memory.last_store()
+    }
+
+    fn take_dirty_memory(&mut self) -> DirtyRegions {
+        self.memory.take_dirty()
+    }
+
+    fn step(&mut self) {
+        if self.is_halted() {
+            return;
+        }
+        if let Some(idx) = self.next_core() {
+            self.with_core(idx, |core| core.step());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cp0::CP0_EBASE;
+    use super::super::encode::*;
+    use super::*;
+
+    #[test]
+    fn sc_fails_after_another_core_stores_to_the_word() {
+        let mut program = li(T0, DATA_MEM_START).to_vec();
+        program.extend([ll(T1, 0, T0), addiu(T1, T1, 1), sc(T1, 0, T0)]);
+        let mut cpu = MulticoreCPU::new_from_memory(
+            program.clone(),
+            DataMemory::default(),
+            MulticoreConfig {
+                cores: 2,
+                interleaving: Interleaving::RoundRobin { quantum: 1 },
+            },
+        );
+        // Both cores link the word before either stores, so only the first
+        // SC may succeed.
+        (0..program.len() * 2).for_each(|_| cpu.step());
+
+        assert_eq!(cpu.cores()[0].get_register(RegisterKind::Reg09), 1);
+        assert_eq!(cpu.cores()[1].get_register(RegisterKind::Reg09), 0);
+        assert_eq!(cpu.get_memory_word(DATA_MEM_START), Some(1));
+    }
+
+    #[test]
+    fn cores_keep_their_number_across_translation_changes() {
+        let mut cpu = MulticoreCPU::new_from_memory(
+            Vec::new(),
+            DataMemory::default(),
+            MulticoreConfig {
+                cores: 2,
+                interleaving: Interleaving::default(),
+            },
+        );
+        cpu.set_address_translation(AddressTranslation::Tlb(Default::default()));
+        let cpu_num = |core: usize| cpu.cores()[core].get_cp0_register(CP0_EBASE) & 0x3FF;
+        assert_eq!((cpu_num(0), cpu_num(1)), (0, 1));
+    }
+
+    #[test]
+    fn scheduled_core_is_the_one_the_step_runs() {
+        [
+            Interleaving::RoundRobin { quantum: 1 },
+            Interleaving::RoundRobin { quantum: 3 },
+            Interleaving::Random { seed: 7 },
+        ]
+        .into_iter()
+        .for_each(|interleaving| {
+            let mut cpu = MulticoreCPU::new_from_memory(
+                vec![nop(); 32],
+                DataMemory::default(),
+                MulticoreConfig {
+                    cores: 3,
+                    interleaving,
+                },
+            );
+            (0..20).for_each(|_| {
+                let scheduled = cpu.scheduled_core();
+                cpu.step();
+                assert_eq!(scheduled, Some(cpu.current_core()), "{interleaving:?}");
+            });
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+const PAGE_BITS: u32 = 12;
+const TABLE_BITS: u32 = 10;
+
+pub const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const TABLE_SIZE: usize = 1 << TABLE_BITS;
+
+type Page = [u8; PAGE_SIZE];
+type PageTable = [Option<Arc<Page>>; TABLE_SIZE];
+type PageDirectory = [Option<Arc<PageTable>>; TABLE_SIZE];
+
+/// Sparse byte store covering the whole 32-bit address space.
+///
+/// Addresses are split 10/10/12 into a directory index, a table index and a
+/// page offset. Pages are allocated on first write and reads of untouched
+/// memory return zero. Every level is reference counted, so cloning is a
+/// single counter increment and a write only copies the path to the page it
+/// touches. This keeps `History`, which clones the CPU on every step, cheap
+/// no matter how much memory a program uses.
+#[derive(Clone)]
+pub struct PagedMemory {
+    directory: Arc<PageDirectory>,
+}
+
+fn split(addr: u32) -> (usize, usize, usize) {
+    (
+        (addr >> (PAGE_BITS + TABLE_BITS)) as usize,
+        ((addr >> PAGE_BITS) as usize) & (TABLE_SIZE - 1),
+        (addr as usize) & (PAGE_SIZE - 1),
+    )
+}
+
+impl Default for PagedMemory {
+    fn default() -> Self {
+        Self {
+            directory: Arc::new(std::array::from_fn(|_| None)),
+        }
+    }
+}
+
+impl Debug for PagedMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PagedMemory with {} allocated pages", self.page_count())
+    }
+}
+
+impl PagedMemory {
+    pub fn read_byte(&self, addr: u32) -> u8 {
+        let (dir, table, offset) = split(addr);
+        self.directory[dir]
+            .as_ref()
+            .and_then(|page_table| page_table[table].as_ref())
+            .map_or(0, |page| page[offset])
+    }
+
+    pub fn write_byte(&mut self, addr: u32, value: u8) {
+        let (dir, table, offset) = split(addr);
+        let page_table = Arc::make_mut(&mut self.directory)[dir]
+            .get_or_insert_with(|| Arc::new(std::array::from_fn(|_| None)));
+        let page = Arc::make_mut(page_table)[table].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Arc::make_mut(page)[offset] = value;
+    }
+
+    pub fn write_bytes(&mut self, addr: u32, bytes: &[u8]) {
+        bytes.iter().enumerate().for_each(|(offset, byte)| {
+            self.write_byte(addr.wrapping_add(offset as u32), *byte);
+        });
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.directory
+            .iter()
+            .flatten()
+            .map(|page_table| page_table.iter().flatten().count())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(memory: &PagedMemory, addr: u32) -> &Arc<Page> {
+        let (dir, table, _) = split(addr);
+        memory.directory[dir].as_ref().unwrap()[table]
+            .as_ref()
+            .unwrap()
+    }
+
+    #[test]
+    fn untouched_memory_reads_zero() {
+        let memory = PagedMemory::default();
+        assert_eq!(memory.read_byte(0x10010000), 0);
+        assert_eq!(memory.read_byte(u32::MAX), 0);
+        assert_eq!(memory.page_count(), 0);
+    }
+
+    #[test]
+    fn writes_allocate_pages() {
+        let mut memory = PagedMemory::default();
+        memory.write_bytes(0x10010ffe, &[1, 2, 3, 4]);
+        assert_eq!(memory.page_count(), 2);
+        assert_eq!(
+            (0x10010ffe..0x10011002)
+                .map(|addr| memory.read_byte(addr))
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn clones_do_not_see_each_others_writes() {
+        let mut original = PagedMemory::default();
+        original.write_byte(0x10010000, 1);
+        original.write_byte(0x7FFFEFFC, 2);
+
+        let mut clone = original.clone();
+        clone.write_byte(0x10010000, 10);
+        original.write_byte(0x7FFFEFFC, 20);
+        clone.write_byte(0x10040000, 30);
+
+        assert_eq!(original.read_byte(0x10010000), 1);
+        assert_eq!(original.read_byte(0x7FFFEFFC), 20);
+        assert_eq!(original.read_byte(0x10040000), 0);
+        assert_eq!(original.page_count(), 2);
+
+        assert_eq!(clone.read_byte(0x10010000), 10);
+        assert_eq!(clone.read_byte(0x7FFFEFFC), 2);
+        assert_eq!(clone.read_byte(0x10040000), 30);
+        assert_eq!(clone.page_count(), 3);
+    }
+
+    #[test]
+    fn writes_only_copy_the_page_they_touch() {
+        let mut original = PagedMemory::default();
+        original.write_byte(0x10010000, 1);
+        original.write_byte(0x10020000, 2);
+
+        let mut clone = original.clone();
+        assert!(Arc::ptr_eq(&original.directory, &clone.directory));
+
+        clone.write_byte(0x10010001, 3);
+        assert!(!Arc::ptr_eq(
+            page(&original, 0x10010000),
+            page(&clone, 0x10010000)
+        ));
+        assert!(Arc::ptr_eq(
+            page(&original, 0x10020000),
+            page(&clone, 0x10020000)
+        ));
+    }
+}
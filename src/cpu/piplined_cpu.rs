@@ -1,26 +1,46 @@
 use super::{
     alu::AluOperation,
+    cache::{Cache, CacheConfig, CachedMemory},
+    call_stack::CallStack,
     control_unit::ControlUnitOutput,
-    cpu_interface::{CPUInterface, RegisterKind},
-    data_memory::DataMemory,
+    cp0::Cp0,
+    cpu_interface::{CPUInterface, HaltReason, RegisterKind},
+    data_memory::{DataMem, DataMemory, MemoryArchitecture},
+    devices::{Bus, Device},
+    dirty_regions::DirtyRegions,
     instruction_memory::InstructionMemory,
-    registers::Registers,
-    INST_MEM_START,
+    mmu::{AddressTranslation, Mmu, Tlb},
+    profiler::Profile,
+    registers::{initial_registers, Register, Registers},
+    statistics::Statistics,
+    watchpoints::{WatchHit, Watchpoint},
+    DATA_MEM_START, INST_MEM_START,
 };
 
+/// The pipelined CPU always runs alone, as core 0.
+const CORE_ID: u32 = 0;
+
 #[derive(Debug, Clone)]
 pub struct PipelinedCPU {
     error_message: Option<String>,
+    halt_reason: Option<HaltReason>,
+    halt_address: Option<u32>,
+    architecture: MemoryArchitecture,
     registers: Registers,
     pc: u32,
+    cp0: Cp0,
+    mmu: Mmu,
+    statistics: Statistics,
+    profile: Profile,
+    call_stack: CallStack,
 
     instruction_memory: InstructionMemory,
-    data_memory: DataMemory,
+    data_memory: Bus<CachedMemory<DataMemory>>,
 }
 
 impl CPUInterface for PipelinedCPU {
     fn get_memory_size(&self) -> u32 {
-        self.data_memory.len() as u32
+        self.data_memory.static_data_size()
     }
 
     fn get_instruction_size(&self) -> u32 {
@@ -30,12 +50,60 @@ impl CPUInterface for PipelinedCPU {
     fn get_register(&self, reg: RegisterKind) -> u32 {
         match reg {
             RegisterKind::RegPC => self.pc,
-            _ => self.registers.get(reg as usize).copied().unwrap(),
+            _ => self.registers.read(reg as u32),
         }
     }
 
     fn get_memory_byte(&self, address: u32) -> Option<u8> {
-        self.data_memory.get(address as usize).copied()
+        self.data_memory
+            .read_byte(DATA_MEM_START.wrapping_add(address))
+    }
+
+    fn set_register(&mut self, reg: RegisterKind, value: u32) {
+        match reg {
+            RegisterKind::RegPC => self.pc = value,
+            _ => self.registers.set(reg as u32, value),
+        }
+    }
+
+    fn set_memory_byte(&mut self, address: u32, value: u8) -> bool {
+        self.data_memory
+            .write_byte(DATA_MEM_START.wrapping_add(address), value)
+    }
+
+    fn set_address_translation(&mut self, translation: AddressTranslation) {
+        self.mmu = Mmu::new(translation);
+        self.cp0 = Cp0::new(CORE_ID);
+        if let Some(tlb) = self.mmu.tlb() {
+            tlb.reset(&mut self.cp0);
+        }
+    }
+
+    fn get_cp0_register(&self, reg: u32) -> u32 {
+        self.cp0.read(reg)
+    }
+
+    fn get_tlb(&self) -> Option<&Tlb> {
+        self.mmu.tlb()
+    }
+
+    fn attach_device(&mut self, device: Box<dyn Device>) {
+        if device.memory_backed() {
+            self.data_memory.map_window(device.base(), device.size());
+        }
+        self.data_memory.attach(device);
+    }
+
+    fn get_devices(&self) -> &[Box<dyn Device>] {
+        self.data_memory.devices()
+    }
+
+    fn get_statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn get_profile(&self) -> Profile {
+        self.profile.clone()
     }
 
     fn get_control_signals(&self) -> ControlUnitOutput {
@@ -50,6 +118,60 @@ impl CPUInterface for PipelinedCPU {
         self.error_message.clone()
     }
 
+    fn get_halt_reason(&self) -> Option<HaltReason> {
+        self.halt_reason
+    }
+
+    fn set_halt_address(&mut self, address: Option<u32>) {
+        self.halt_address = address;
+    }
+
+    fn set_memory_architecture(&mut self, architecture: MemoryArchitecture) {
+        self.architecture = architecture;
+        match architecture {
+            MemoryArchitecture::Harvard => self.data_memory.unmap_text(),
+            MemoryArchitecture::VonNeumann { protect_text } => self
+                .data_memory
+                .map_text(&self.instruction_memory, !protect_text),
+        }
+    }
+
+    fn configure_caches(&mut self, data: Option<CacheConfig>, instruction: Option<CacheConfig>) {
+        self.data_memory.configure(data, instruction);
+    }
+
+    fn get_data_cache(&self) -> Option<&Cache> {
+        self.data_memory.data_cache()
+    }
+
+    fn get_instruction_cache(&self) -> Option<&Cache> {
+        self.data_memory.instruction_cache()
+    }
+
+    fn set_watchpoints(&mut self, watchpoints: &[Watchpoint]) {
+        self.registers.watchpoints.set(watchpoints);
+        self.data_memory.watchpoints_mut().set(watchpoints);
+    }
+
+    fn get_watch_hit(&self) -> Option<WatchHit> {
+        self.data_memory
+            .watchpoints()
+            .hit()
+            .or(self.registers.watchpoints.hit())
+    }
+
+    fn get_call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    fn get_last_store(&self) -> Option<(u32, u32)> {
+        self.data_memory.last_store()
+    }
+
+    fn take_dirty_memory(&mut self) -> DirtyRegions {
+        self.data_memory.take_dirty()
+    }
+
     fn step(&mut self) {
         todo!()
     }
@@ -59,20 +181,36 @@ impl PipelinedCPU {
     pub fn new() -> Self {
         Self {
             error_message: None,
-            registers: [0; 32],
+            halt_reason: None,
+            halt_address: None,
+            architecture: MemoryArchitecture::default(),
+            registers: initial_registers(),
             pc: INST_MEM_START,
+            cp0: Cp0::new(CORE_ID),
+            mmu: Mmu::default(),
+            statistics: Statistics::default(),
+            profile: Profile::default(),
+            call_stack: CallStack::default(),
             instruction_memory: Vec::new(),
-            data_memory: vec![0; 1024],
+            data_memory: Bus::default(),
         }
     }
 
     pub fn new_from_memory(instruction_memory: InstructionMemory, data_memory: DataMemory) -> Self {
         Self {
             error_message: None,
-            registers: [0; 32],
+            halt_reason: None,
+            halt_address: None,
+            architecture: MemoryArchitecture::default(),
+            registers: initial_registers(),
             pc: INST_MEM_START,
+            cp0: Cp0::new(CORE_ID),
+            mmu: Mmu::default(),
+            statistics: Statistics::default(),
+            profile: Profile::default(),
+            call_stack: CallStack::default(),
             instruction_memory,
-            data_memory,
+            data_memory: Bus::new(CachedMemory::new(data_memory)),
         }
     }
 }
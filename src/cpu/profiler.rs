@@ -0,0 +1,172 @@
+use std::{collections::BTreeMap, ops::Add};
+
+use super::{
+    ADDU_FUNCT, ADD_FUNCT, AND_FUNCT, BEQ_OPCODE, BNE_OPCODE, BREAK_FUNCT, COP0_MF, COP0_MT,
+    COP0_OPCODE, ERET_FUNCT, JALR_FUNCT, JAL_OPCODE, JR_FUNCT, LL_OPCODE, NOR_FUNCT, OR_FUNCT,
+    SC_OPCODE, SLL_FUNCT, SLTU_FUNCT, SLT_FUNCT, SRL_FUNCT, SUBU_FUNCT, SUB_FUNCT, SYSCALL_FUNCT,
+    TLBP_FUNCT, TLBR_FUNCT, TLBWI_FUNCT, TLBWR_FUNCT,
+};
+
+/// Basic block seen while running, named by its first instruction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Address of the last instruction executed in the block.
+    pub end: u32,
+    /// Number of times the block was entered.
+    pub entries: u64,
+}
+
+/// Execution counts gathered while a program runs.
+///
+/// Blocks are found dynamically: one starts at the first instruction, after
+/// every branch or jump whether taken or not, and after every exception.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pcs: BTreeMap<u32, u64>,
+    mnemonics: BTreeMap<&'static str, u64>,
+    blocks: BTreeMap<u32, BasicBlock>,
+    leader: Option<u32>,
+}
+
+impl Profile {
+    /// Counts a retired instruction. `ends_block` is set for instructions
+    /// that can transfer control.
+    pub fn record(&mut self, pc: u32, inst: u32, ends_block: bool) {
+        *self.pcs.entry(pc).or_default() += 1;
+        *self.mnemonics.entry(mnemonic(inst)).or_default() += 1;
+
+        let leader = *self.leader.get_or_insert_with(|| {
+            self.blocks.entry(pc).or_default().entries += 1;
+            pc
+        });
+        if let Some(block) = self.blocks.get_mut(&leader) {
+            block.end = pc;
+        }
+        if ends_block {
+            self.end_block();
+        }
+    }
+
+    /// Makes the next recorded instruction start a new block.
+    pub fn end_block(&mut self) {
+        self.leader = None;
+    }
+
+    pub fn count(&self, pc: u32) -> u64 {
+        self.pcs.get(&pc).copied().unwrap_or_default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.pcs.values().sum()
+    }
+
+    /// Execution count of every instruction that ran, by address.
+    pub fn pcs(&self) -> impl Iterator<Item = (u32, u64)> + '_ {
+        self.pcs.iter().map(|(pc, count)| (*pc, *count))
+    }
+
+    /// The `n` most executed instructions, hottest first.
+    pub fn hottest(&self, n: usize) -> Vec<(u32, u64)> {
+        let mut pcs = self.pcs().collect::<Vec<_>>();
+        pcs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        pcs.truncate(n);
+        pcs
+    }
+
+    /// Instruction mix, most executed mnemonic first.
+    pub fn mnemonics(&self) -> Vec<(&'static str, u64)> {
+        let mut mnemonics = self
+            .mnemonics
+            .iter()
+            .map(|(name, count)| (*name, *count))
+            .collect::<Vec<_>>();
+        mnemonics.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        mnemonics
+    }
+
+    /// Basic blocks by address of their first instruction.
+    pub fn blocks(&self) -> impl Iterator<Item = (u32, BasicBlock)> + '_ {
+        self.blocks.iter().map(|(leader, block)| (*leader, *block))
+    }
+}
+
+impl Add for Profile {
+    type Output = Profile;
+
+    fn add(mut self, other: Profile) -> Profile {
+        other
+            .pcs
+            .into_iter()
+            .for_each(|(pc, count)| *self.pcs.entry(pc).or_default() += count);
+        other
+            .mnemonics
+            .into_iter()
+            .for_each(|(name, count)| *self.mnemonics.entry(name).or_default() += count);
+        other.blocks.into_iter().for_each(|(leader, block)| {
+            let merged = self.blocks.entry(leader).or_default();
+            merged.end = merged.end.max(block.end);
+            merged.entries += block.entries;
+        });
+        self.leader = None;
+        self
+    }
+}
+
+/// Name of the instruction `inst` is an encoding of, for the instructions
+/// the simulator executes.
+pub fn mnemonic(inst: u32) -> &'static str {
+    let opcode = inst >> 26;
+    let funct = inst & 0b111111;
+    match opcode {
+        0x00 => match funct {
+            SLL_FUNCT => "sll",
+            SRL_FUNCT => "srl",
+            JR_FUNCT => "jr",
+            JALR_FUNCT => "jalr",
+            SYSCALL_FUNCT => "syscall",
+            BREAK_FUNCT => "break",
+            ADD_FUNCT => "add",
+            ADDU_FUNCT => "addu",
+            SUB_FUNCT => "sub",
+            SUBU_FUNCT => "subu",
+            AND_FUNCT => "and",
+            OR_FUNCT => "or",
+            NOR_FUNCT => "nor",
+            SLT_FUNCT => "slt",
+            SLTU_FUNCT => "sltu",
+            _ => "unknown",
+        },
+        0x02 => "j",
+        JAL_OPCODE => "jal",
+        BEQ_OPCODE => "beq",
+        BNE_OPCODE => "bne",
+        0x08 => "addi",
+        0x09 => "addiu",
+        0x0A => "slti",
+        0x0B => "sltiu",
+        0x0C => "andi",
+        0x0D => "ori",
+        0x0F => "lui",
+        COP0_OPCODE => match (inst >> 21) & 0b11111 {
+            COP0_MF => "mfc0",
+            COP0_MT => "mtc0",
+            _ => match funct {
+                TLBR_FUNCT => "tlbr",
+                TLBWI_FUNCT => "tlbwi",
+                TLBWR_FUNCT => "tlbwr",
+                TLBP_FUNCT => "tlbp",
+                ERET_FUNCT => "eret",
+                _ => "unknown",
+            },
+        },
+        0x23 => "lw",
+        0x24 => "lbu",
+        0x25 => "lhu",
+        0x28 => "sb",
+        0x29 => "sh",
+        0x2B => "sw",
+        LL_OPCODE => "ll",
+        SC_OPCODE => "sc",
+        _ => "unknown",
+    }
+}
@@ -1,4 +1,18 @@
-pub type Registers = [u32; 32];
+use super::{watchpoints::Watchpoints, GLOBAL_POINTER_START, STACK_POINTER_START};
+
+/// The general purpose register file, with the watchpoints set on it.
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    values: [u32; 32],
+    pub watchpoints: Watchpoints,
+}
+
+/// Conventional names of the general purpose registers, by number.
+pub const REGISTER_NAMES: [&str; 32] = [
+    "$zero", "$at", "$v0", "$v1", "$a0", "$a1", "$a2", "$a3", "$t0", "$t1", "$t2", "$t3", "$t4",
+    "$t5", "$t6", "$t7", "$s0", "$s1", "$s2", "$s3", "$s4", "$s5", "$s6", "$s7", "$t8", "$t9",
+    "$k0", "$k1", "$gp", "$sp", "$fp", "$ra",
+];
 
 pub trait Register {
     fn read(&self, addr: u32) -> u32;
@@ -7,12 +21,43 @@ pub trait Register {
 
 impl Register for Registers {
     fn read(&self, addr: u32) -> u32 {
-        self[addr as usize]
+        self.values[addr as usize]
     }
 
     fn write(&mut self, data: u32, addr: u32, write: bool) {
         if write {
-            self[addr as usize] = data;
+            let old = std::mem::replace(&mut self.values[addr as usize], data);
+            self.watchpoints.write_register(addr, old, data);
+        }
+    }
+}
+
+impl Registers {
+    /// Lets the watchpoints see an instruction reading `reg`.
+    pub fn record_read(&mut self, reg: u32) {
+        self.watchpoints
+            .read_register(reg, self.values[reg as usize]);
+    }
+
+    /// Writes a register without setting off watchpoints, for debuggers.
+    /// `$zero` stays zero.
+    pub fn set(&mut self, reg: u32, value: u32) {
+        if reg != 0 {
+            self.values[reg as usize] = value;
         }
     }
 }
+
+/// Register file as SPIM and MARS leave it before the first instruction:
+/// `$gp` in the middle of the extern segment, `$sp` just under the top of
+/// the stack and `$fp` cleared.
+pub fn initial_registers() -> Registers {
+    let mut values = [0; 32];
+    values[28] = GLOBAL_POINTER_START;
+    values[29] = STACK_POINTER_START;
+    values[30] = 0;
+    Registers {
+        values,
+        watchpoints: Watchpoints::default(),
+    }
+}
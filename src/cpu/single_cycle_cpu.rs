@@ -1,32 +1,60 @@
 use super::alu::AluOperation;
 use super::control_unit::ControlUnitOutput;
-use super::{BEQ_OPCODE, BNE_OPCODE, INST_MEM_START};
+use super::{
+    BEQ_OPCODE, BNE_OPCODE, BREAK_FUNCT, COP0_CO, COP0_MF, COP0_MT, COP0_OPCODE, CORE_STACK_SIZE,
+    DATA_MEM_START, ERET_FUNCT, INST_MEM_START, JALR_FUNCT, JAL_OPCODE, JR_FUNCT, J_OPCODE,
+    LL_OPCODE, LUI_OPCODE, SC_OPCODE, SLL_FUNCT, SRL_FUNCT, STACK_POINTER_START, SW_OPCODE,
+    SYSCALL_EXIT, SYSCALL_EXIT2, SYSCALL_FUNCT, SYSCALL_SBRK, TLBP_FUNCT, TLBR_FUNCT, TLBWI_FUNCT,
+    TLBWR_FUNCT,
+};
 
 use super::{
     alu::{alu, alu_control},
-    control_unit::{control_unit, MemToReg, PCSrc, RegDst},
+    cache::{Cache, CacheConfig, CachedMemory},
+    call_stack::CallStack,
+    control_unit::{control_unit, Mem, MemToReg, PCSrc, RegDst},
+    cp0::{Cp0, Exception, ExceptionCode},
     cpu_interface::*,
-    data_memory::{DataMem, DataMemory},
+    data_memory::{AccessKind, DataMem, DataMemory, FaultCause, MemoryArchitecture, MemoryFault},
+    devices::{Bus, Device},
+    dirty_regions::DirtyRegions,
     instruction_memory::{InstructionMem, InstructionMemory},
-    registers::{Register, Registers},
+    mmu::{AddressTranslation, Mmu, Tlb},
+    profiler::Profile,
+    registers::{initial_registers, Register, Registers},
+    statistics::{InstructionClass, Statistics},
     utils::*,
+    watchpoints::{WatchHit, Watchpoint},
 };
 
+/// Memory as seen by a core: RAM behind optional caches, with devices on
+/// the bus in front of it.
+pub type SystemMemory = Bus<CachedMemory<DataMemory>>;
+
 #[derive(Debug, Clone)]
 pub struct SingleCycleCPU {
+    core_id: usize,
     error_message: Option<String>,
+    halt_reason: Option<HaltReason>,
+    halt_address: Option<u32>,
+    architecture: MemoryArchitecture,
     registers: Registers,
     pc: u32,
+    cp0: Cp0,
+    mmu: Mmu,
+    statistics: Statistics,
+    profile: Profile,
+    call_stack: CallStack,
 
     pub instruction_memory: InstructionMemory,
-    pub data_memory: DataMemory,
+    pub data_memory: SystemMemory,
     control_signals: ControlUnitOutput,
     alu_control_signals: AluOperation,
 }
 
 impl CPUInterface for SingleCycleCPU {
     fn get_memory_size(&self) -> u32 {
-        self.data_memory.len() as u32
+        self.data_memory.static_data_size()
     }
 
     fn get_instruction_size(&self) -> u32 {
@@ -34,13 +62,26 @@ impl CPUInterface for SingleCycleCPU {
     }
 
     fn get_memory_byte(&self, address: u32) -> Option<u8> {
-        self.data_memory.get(address as usize).copied()
+        self.data_memory
+            .read_byte(DATA_MEM_START.wrapping_add(address))
+    }
+
+    fn set_register(&mut self, reg: RegisterKind, value: u32) {
+        match reg {
+            RegisterKind::RegPC => self.pc = value,
+            _ => self.registers.set(reg as u32, value),
+        }
+    }
+
+    fn set_memory_byte(&mut self, address: u32, value: u8) -> bool {
+        self.data_memory
+            .write_byte(DATA_MEM_START.wrapping_add(address), value)
     }
 
     fn get_register(&self, reg: RegisterKind) -> u32 {
         match reg {
             RegisterKind::RegPC => self.pc,
-            _ => self.registers.get(reg as usize).copied().unwrap(),
+            _ => self.registers.read(reg as u32),
         }
     }
 
@@ -48,6 +89,95 @@ impl CPUInterface for SingleCycleCPU {
         self.error_message.clone()
     }
 
+    fn get_halt_reason(&self) -> Option<HaltReason> {
+        self.halt_reason
+    }
+
+    fn set_halt_address(&mut self, address: Option<u32>) {
+        self.halt_address = address;
+    }
+
+    fn set_memory_architecture(&mut self, architecture: MemoryArchitecture) {
+        self.architecture = architecture;
+        match architecture {
+            MemoryArchitecture::Harvard => self.data_memory.unmap_text(),
+            MemoryArchitecture::VonNeumann { protect_text } => self
+                .data_memory
+                .map_text(&self.instruction_memory, !protect_text),
+        }
+    }
+
+    fn configure_caches(&mut self, data: Option<CacheConfig>, instruction: Option<CacheConfig>) {
+        self.data_memory.configure(data, instruction);
+    }
+
+    fn get_data_cache(&self) -> Option<&Cache> {
+        self.data_memory.data_cache()
+    }
+
+    fn get_instruction_cache(&self) -> Option<&Cache> {
+        self.data_memory.instruction_cache()
+    }
+
+    fn set_address_translation(&mut self, translation: AddressTranslation) {
+        self.mmu = Mmu::new(translation);
+        self.cp0 = Cp0::new(self.core_id as u32);
+        if let Some(tlb) = self.mmu.tlb() {
+            tlb.reset(&mut self.cp0);
+        }
+    }
+
+    fn get_cp0_register(&self, reg: u32) -> u32 {
+        self.cp0.read(reg)
+    }
+
+    fn get_tlb(&self) -> Option<&Tlb> {
+        self.mmu.tlb()
+    }
+
+    fn attach_device(&mut self, device: Box<dyn Device>) {
+        if device.memory_backed() {
+            self.data_memory.map_window(device.base(), device.size());
+        }
+        self.data_memory.attach(device);
+    }
+
+    fn get_devices(&self) -> &[Box<dyn Device>] {
+        self.data_memory.devices()
+    }
+
+    fn get_statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn get_profile(&self) -> Profile {
+        self.profile.clone()
+    }
+
+    fn set_watchpoints(&mut self, watchpoints: &[Watchpoint]) {
+        self.registers.watchpoints.set(watchpoints);
+        self.data_memory.watchpoints_mut().set(watchpoints);
+    }
+
+    fn get_watch_hit(&self) -> Option<WatchHit> {
+        self.data_memory
+            .watchpoints()
+            .hit()
+            .or(self.registers.watchpoints.hit())
+    }
+
+    fn get_call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    fn get_last_store(&self) -> Option<(u32, u32)> {
+        self.data_memory.last_store()
+    }
+
+    fn take_dirty_memory(&mut self) -> DirtyRegions {
+        self.data_memory.take_dirty()
+    }
+
     fn get_control_signals(&self) -> ControlUnitOutput {
         self.control_signals.clone()
     }
@@ -57,84 +187,46 @@ impl CPUInterface for SingleCycleCPU {
     }
 
     fn step(&mut self) {
-        if let Some(inst) = self.instruction_memory.get_instruction(self.pc) {
-            let inc_pc = self.pc + 4;
-            let opcode = inst >> 26;
-
-            let rs = (inst >> 21) & 0b11111;
-            let rt = (inst >> 16) & 0b11111;
-            let rd = (inst >> 11) & 0b11111;
-            let shamt = (inst >> 6) & 0b11111;
-            let funct = inst & 0b111111;
-
-            let jump_addr =
-                ((inst & 0b11_1111_1111_1111_1111_1111_1111) << 2) + (inc_pc & !0xFFFFFFF);
-
-            let immediate = inst & 0xFFFF;
-            let imm_sign_extended = sign_extend(immediate as i32, 16) as u32;
-            let branch_addr = (imm_sign_extended << 2).wrapping_add(self.pc);
-
-            let data_1 = self.registers.read(rs);
-            let data_2 = self.registers.read(rt);
-
-            self.control_signals = control_unit(opcode, funct);
-            self.alu_control_signals = alu_control(self.control_signals.alu_op, funct);
-            let alu_result = alu(
-                data_1,
-                if self.control_signals.alu_src {
-                    imm_sign_extended
-                } else {
-                    data_2
-                },
-                shamt,
-                self.alu_control_signals,
-            );
-
-            let write_register = match self.control_signals.reg_dst {
-                RegDst::RT => rt,
-                RegDst::RD => rd,
-                RegDst::RA => 31,
-            };
-
-            self.pc = match self.control_signals.pc_src {
-                PCSrc::PCBranch if alu_result == 0 && opcode == BEQ_OPCODE => branch_addr,
-                PCSrc::PCBranch if alu_result != 0 && opcode == BNE_OPCODE => branch_addr,
-                PCSrc::PCBranch => inc_pc,
-                PCSrc::PC => inc_pc,
-                PCSrc::Jump => jump_addr,
-                PCSrc::RegJump => rs,
-            };
-
-            let read_data = self
-                .data_memory
-                .load(alu_result, self.control_signals.mem_read);
-            let reg_write_data = match self.control_signals.mem_to_reg {
-                MemToReg::MemoryRead => match read_data {
-                    Some(data) => data,
-                    None => {
-                        self.error_message = Some("Invalid memory read".to_string());
-                        0
-                    }
-                },
-                MemToReg::PCInc => inc_pc,
-                MemToReg::ALUResult => alu_result,
-                MemToReg::ImmLeftShift16 => immediate << 16,
-            };
-
-            self.registers.write(
-                reg_write_data,
-                write_register,
-                self.control_signals.reg_write,
-            );
-            if matches!(
-                self.data_memory
-                    .store(data_2, alu_result, self.control_signals.mem_write),
-                Err(())
-            ) {
-                self.error_message = Some(format!("Invalid memory write address: {}", rt));
-            }
-        } else {
-            self.error_message = Some("Out of range instruction".to_string());
+        if self.halt_reason.is_some() {
+            return;
+        }
+        if self.halt_address == Some(self.pc) {
+            self.halt_reason = Some(HaltReason::HaltAddress(self.pc));
+            return;
+        }
+
+        if self.pc == self.instruction_memory.end_address() {
+            self.halt_reason = Some(HaltReason::EndOfText);
+            return;
+        }
+        self.registers.watchpoints.clear_hit();
+        self.data_memory.watchpoints_mut().clear_hit();
+        self.data_memory.clear_last_store();
+        self.data_memory.clear_last_access();
+
+        if let Some(tlb) = self.mmu.tlb() {
+            tlb.tick(&mut self.cp0);
+        }
+        self.cp0.tick();
+        self.data_memory.tick();
+        self.statistics.tick();
+
+        self.cp0
+            .set_hardware_interrupts(self.data_memory.pending_interrupts());
+        if self.cp0.interrupt_pending() {
+            return self.take_exception(Exception {
+                code: ExceptionCode::Interrupt,
+                bad_vaddr: None,
+                refill: false,
+            });
+        }
+
+        let Some(addr) = self.translate(self.pc, AccessKind::Fetch) else {
+            return;
+        };
+        match self.fetch(addr) {
+            Ok(inst) => self.execute(inst),
+            Err(fault) => self.fault(fault),
         }
     }
 }
@@ -146,27 +238,575 @@ impl Default for SingleCycleCPU {
 }
 
 impl SingleCycleCPU {
+    fn execute(&mut self, inst: u32) {
+        let pc = self.pc;
+        let inc_pc = self.pc + 4;
+        let opcode = inst >> 26;
+
+        let rs = (inst >> 21) & 0b11111;
+        let rt = (inst >> 16) & 0b11111;
+        let rd = (inst >> 11) & 0b11111;
+        let shamt = (inst >> 6) & 0b11111;
+        let funct = inst & 0b111111;
+
+        let immediate = inst & 0xFFFF;
+        let imm_sign_extended = sign_extend(immediate as i32, 16) as u32;
+        // Only branches and jumps, which always have a target, select it.
+        let target = jump_target(inst, pc).unwrap_or(inc_pc);
+
+        let data_1 = self.registers.read(rs);
+        let data_2 = self.registers.read(rt);
+
+        let Some(control_signals) = control_unit(opcode, funct) else {
+            return self.reserved_instruction();
+        };
+        let Some(alu_control_signals) = alu_control(control_signals.alu_op, funct) else {
+            return self.reserved_instruction();
+        };
+        self.control_signals = control_signals;
+        self.alu_control_signals = alu_control_signals;
+        let (reads_rs, reads_rt) = register_reads(opcode, funct, rs, &self.control_signals);
+        if reads_rs {
+            self.registers.record_read(rs);
+        }
+        if reads_rt {
+            self.registers.record_read(rt);
+        }
+        let alu_result = alu(
+            data_1,
+            if self.control_signals.alu_src {
+                imm_sign_extended
+            } else {
+                data_2
+            },
+            shamt,
+            self.alu_control_signals,
+        );
+
+        let write_register = match self.control_signals.reg_dst {
+            RegDst::RT => rt,
+            RegDst::RD => rd,
+            RegDst::RA => 31,
+        };
+
+        let branch_taken =
+            (alu_result == 0 && opcode == BEQ_OPCODE) || (alu_result != 0 && opcode == BNE_OPCODE);
+        let next_pc = match self.control_signals.pc_src {
+            PCSrc::PCBranch if branch_taken => target,
+            PCSrc::PCBranch => inc_pc,
+            PCSrc::PC => inc_pc,
+            PCSrc::Jump => target,
+            PCSrc::RegJump => data_1,
+        };
+
+        let data_access = match (
+            self.control_signals.mem_read,
+            self.control_signals.mem_write,
+        ) {
+            (Mem::None, Mem::None) => None,
+            (Mem::None, _) => Some(AccessKind::Store),
+            _ => Some(AccessKind::Load),
+        };
+        let data_addr = match data_access {
+            Some(access) => match self.translate(alu_result, access) {
+                Some(addr) => addr,
+                None => return,
+            },
+            None => alu_result,
+        };
+
+        let read_data = match self
+            .data_memory
+            .load(data_addr, self.control_signals.mem_read)
+        {
+            Ok(data) => data,
+            Err(fault) => return self.fault(fault),
+        };
+        if opcode == LL_OPCODE {
+            self.data_memory.load_linked(self.core_id, data_addr);
+        }
+        let linked =
+            opcode == SC_OPCODE && self.data_memory.store_conditional(self.core_id, data_addr);
+        let mem_write = if opcode == SC_OPCODE && !linked {
+            Mem::None
+        } else {
+            self.control_signals.mem_write
+        };
+        if let Err(fault) = self.data_memory.store(data_2, data_addr, mem_write) {
+            return self.fault(fault);
+        }
+
+        let reg_write_data = match self.control_signals.mem_to_reg {
+            MemToReg::MemoryRead => read_data,
+            MemToReg::PCInc => inc_pc,
+            MemToReg::ALUResult => alu_result,
+            MemToReg::ImmLeftShift16 => immediate << 16,
+        };
+
+        self.registers.write(
+            reg_write_data,
+            write_register,
+            self.control_signals.reg_write,
+        );
+        if opcode == SC_OPCODE {
+            self.registers.write(linked as u32, rt, true);
+        }
+        self.pc = next_pc;
+        match (opcode, funct) {
+            _ if is_call(inst) => self.call_stack.call(pc, next_pc, self.registers.read(29)),
+            (0, JR_FUNCT) if rs == 31 => self.call_stack.ret(pc, next_pc),
+            (SW_OPCODE, _) => self.call_stack.save(data_addr, rt),
+            _ => {}
+        }
+
+        if opcode == 0 && funct == SYSCALL_FUNCT {
+            self.syscall();
+        } else if opcode == 0 && funct == BREAK_FUNCT {
+            self.halt_reason = Some(HaltReason::Break((inst >> 6) & 0xFFFFF));
+        } else if opcode == COP0_OPCODE {
+            self.coprocessor0(inst, rs, rt, rd);
+        }
+
+        let class = InstructionClass::classify(opcode, funct, &self.control_signals, branch_taken);
+        self.statistics.retire(class);
+        self.profile.record(
+            pc,
+            inst,
+            matches!(
+                class,
+                InstructionClass::BranchTaken
+                    | InstructionClass::BranchNotTaken
+                    | InstructionClass::Jump
+            ) || self.pc != inc_pc,
+        );
+    }
+
+    fn coprocessor0(&mut self, inst: u32, rs: u32, rt: u32, rd: u32) {
+        let funct = inst & 0b111111;
+        match rs {
+            COP0_MF => self.registers.write(self.cp0.read(rd), rt, true),
+            COP0_MT => self.cp0.write(rd, self.registers.read(rt)),
+            _ if rs & COP0_CO != 0 && funct == ERET_FUNCT => {
+                self.pc = self.cp0.exception_return();
+                self.data_memory.clear_reservation(self.core_id);
+            }
+            _ if rs & COP0_CO != 0 => {
+                let Some(tlb) = self.mmu.tlb_mut() else {
+                    self.error_message = Some(format!(
+                        "TLB instruction at pc 0x{:08x} without a TLB, enable one in Preferences",
+                        self.pc.wrapping_sub(4)
+                    ));
+                    return;
+                };
+                match funct {
+                    TLBR_FUNCT => tlb.read(&mut self.cp0),
+                    TLBWI_FUNCT => tlb.write_indexed(&self.cp0),
+                    TLBWR_FUNCT => tlb.write_random(&self.cp0),
+                    TLBP_FUNCT => tlb.probe(&mut self.cp0),
+                    _ => {
+                        self.error_message = Some(format!(
+                            "Unsupported coprocessor 0 instruction 0x{inst:08x}"
+                        ))
+                    }
+                }
+            }
+            _ => {
+                self.error_message = Some(format!(
+                    "Unsupported coprocessor 0 instruction 0x{inst:08x}"
+                ))
+            }
+        }
+    }
+
+    /// Translates `addr` through the MMU, taking the exception and returning
+    /// `None` if the access misses or is not allowed.
+    fn translate(&mut self, addr: u32, access: AccessKind) -> Option<u32> {
+        match self.mmu.translate(addr, access, &self.cp0) {
+            Ok(addr) => Some(addr),
+            Err(exception) => {
+                self.take_exception(exception);
+                None
+            }
+        }
+    }
+
+    /// Enters the handler for `exception`, or stops with an error when the
+    /// program has none. Nothing is loaded into the kernel text segment, so a
+    /// handler has to be in `.text` with EBase pointing at it.
+    fn take_exception(&mut self, exception: Exception) {
+        let vector = self.cp0.exception_vector(exception);
+        if self.instruction_memory.get_instruction(vector).is_none() {
+            self.error_message = Some(format!(
+                "No exception handler installed at 0x{vector:08x} for the {exception} at pc 0x{:08x}",
+                self.pc
+            ));
+            return;
+        }
+        self.pc = self.cp0.enter_exception(exception, self.pc);
+        self.profile.end_block();
+    }
+
+    /// Raised by opcodes and functions the datapath does not implement.
+    fn reserved_instruction(&mut self) {
+        self.take_exception(Exception {
+            code: ExceptionCode::ReservedInstruction,
+            bad_vaddr: None,
+            refill: false,
+        });
+    }
+
     pub fn new() -> Self {
         Self {
+            core_id: 0,
             error_message: None,
-            registers: [0; 32],
+            halt_reason: None,
+            halt_address: None,
+            architecture: MemoryArchitecture::default(),
+            registers: initial_registers(),
             pc: INST_MEM_START,
-            data_memory: vec![0; 16],
+            cp0: Cp0::default(),
+            mmu: Mmu::default(),
+            statistics: Statistics::default(),
+            profile: Profile::default(),
+            call_stack: CallStack::default(),
+            data_memory: Bus::default(),
             instruction_memory: Vec::new(),
             control_signals: ControlUnitOutput::default(),
             alu_control_signals: AluOperation::default(),
         }
     }
 
+    /// Makes this core number `id` of a multicore system. The id is readable
+    /// from EBase.CPUNum and each core gets its own stack below the previous
+    /// core's.
+    pub fn set_core_id(&mut self, id: usize) {
+        self.core_id = id;
+        self.cp0 = Cp0::new(id as u32);
+        if let Some(tlb) = self.mmu.tlb() {
+            tlb.reset(&mut self.cp0);
+        }
+        self.registers
+            .write(STACK_POINTER_START - id as u32 * CORE_STACK_SIZE, 29, true);
+    }
+
+    pub fn core_id(&self) -> usize {
+        self.core_id
+    }
+
     pub fn new_from_memory(instruction_memory: InstructionMemory, data_memory: DataMemory) -> Self {
         Self {
+            core_id: 0,
             error_message: None,
-            registers: [0; 32],
+            halt_reason: None,
+            halt_address: None,
+            architecture: MemoryArchitecture::default(),
+            registers: initial_registers(),
             pc: INST_MEM_START,
+            cp0: Cp0::default(),
+            mmu: Mmu::default(),
+            statistics: Statistics::default(),
+            profile: Profile::default(),
+            call_stack: CallStack::default(),
             instruction_memory,
-            data_memory,
+            data_memory: Bus::new(CachedMemory::new(data_memory)),
             control_signals: ControlUnitOutput::default(),
             alu_control_signals: AluOperation::default(),
         }
     }
+
+    fn fetch(&mut self, addr: u32) -> Result<u32, MemoryFault> {
+        let inst = match self.architecture {
+            MemoryArchitecture::Harvard => match self.instruction_memory.get_instruction(addr) {
+                Some(inst) if addr & 3 == 0 => Ok(inst),
+                _ => self
+                    .data_memory
+                    .check(addr, 4, AccessKind::Fetch)
+                    .and(Err(MemoryFault {
+                        access: AccessKind::Fetch,
+                        cause: FaultCause::Unmapped,
+                        addr,
+                    })),
+            },
+            MemoryArchitecture::VonNeumann { .. } => self.data_memory.fetch(addr),
+        }?;
+
+        self.data_memory.record_fetch(addr);
+        Ok(inst)
+    }
+
+    fn fault(&mut self, fault: MemoryFault) {
+        self.error_message = Some(format!("{fault} at pc 0x{:08x}", self.pc));
+    }
+
+    fn syscall(&mut self) {
+        self.registers.record_read(2);
+        match self.registers.read(2) {
+            SYSCALL_SBRK => {
+                self.registers.record_read(4);
+                match self.data_memory.sbrk(self.registers.read(4)) {
+                    Some(addr) => self.registers.write(addr, 2, true),
+                    None => self.error_message = Some("Heap exhausted".to_string()),
+                }
+            }
+            SYSCALL_EXIT => self.halt_reason = Some(HaltReason::Exit(0)),
+            SYSCALL_EXIT2 => {
+                self.registers.record_read(4);
+                self.halt_reason = Some(HaltReason::Exit(self.registers.read(4) as i32))
+            }
+            // Other services are not simulated and do nothing.
+            _ => {}
+        }
+    }
+}
+
+/// Whether the instruction reads its `rs` and `rt` registers, which is what
+/// register read watchpoints see. Fields an instruction ignores are not
+/// reads even if they name a register.
+fn register_reads(opcode: u32, funct: u32, rs: u32, signals: &ControlUnitOutput) -> (bool, bool) {
+    match (opcode, funct) {
+        (0, SLL_FUNCT | SRL_FUNCT) => (false, true),
+        (0, JR_FUNCT | JALR_FUNCT) => (true, false),
+        (0, SYSCALL_FUNCT | BREAK_FUNCT) => (false, false),
+        (0, _) | (BEQ_OPCODE | BNE_OPCODE, _) => (true, true),
+        (J_OPCODE | JAL_OPCODE | LUI_OPCODE, _) => (false, false),
+        (COP0_OPCODE, _) => (false, rs == COP0_MT),
+        _ => (true, !matches!(signals.mem_write, Mem::None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cp0::{
+        CP0_BAD_VADDR, CP0_CAUSE, CP0_EBASE, CP0_ENTRY_HI, CP0_ENTRY_LO0, CP0_ENTRY_LO1, CP0_EPC,
+        CP0_INDEX, CP0_STATUS,
+    };
+    use super::super::devices::timer::{Timer, TIMER_START};
+    use super::super::encode::*;
+    use super::super::watchpoints::{Trigger, WatchTarget};
+    use super::*;
+
+    fn cpu(program: &[u32]) -> SingleCycleCPU {
+        SingleCycleCPU::new_from_memory(program.to_vec(), DataMemory::default())
+    }
+
+    fn run(cpu: &mut SingleCycleCPU) {
+        (0..100).for_each(|_| cpu.step());
+    }
+
+    #[test]
+    fn exit_halts_with_code_zero() {
+        let mut cpu = cpu(&[addiu(V0, ZERO, 10), syscall(), addiu(T0, ZERO, 1)]);
+        run(&mut cpu);
+        assert_eq!(cpu.get_halt_reason(), Some(HaltReason::Exit(0)));
+        assert_eq!(cpu.get_register(RegisterKind::RegPC), INST_MEM_START + 8);
+        assert_eq!(cpu.get_register(RegisterKind::Reg08), 0);
+        assert_eq!(cpu.get_error(), None);
+    }
+
+    #[test]
+    fn exit2_halts_with_a0() {
+        let mut cpu = cpu(&[addiu(V0, ZERO, 17), addiu(A0, ZERO, -3), syscall()]);
+        run(&mut cpu);
+        assert_eq!(cpu.get_halt_reason(), Some(HaltReason::Exit(-3)));
+    }
+
+    #[test]
+    fn break_halts_with_its_code() {
+        let mut cpu = cpu(&[brk(7), addiu(T0, ZERO, 1)]);
+        run(&mut cpu);
+        assert_eq!(cpu.get_halt_reason(), Some(HaltReason::Break(7)));
+        assert_eq!(cpu.get_register(RegisterKind::Reg08), 0);
+    }
+
+    #[test]
+    fn end_of_text_halts() {
+        let mut cpu = cpu(&[addiu(T0, ZERO, 1), addiu(T0, T0, 1)]);
+        run(&mut cpu);
+        assert_eq!(cpu.get_halt_reason(), Some(HaltReason::EndOfText));
+        assert_eq!(cpu.get_register(RegisterKind::Reg08), 2);
+        assert_eq!(cpu.get_error(), None);
+    }
+
+    #[test]
+    fn halt_address_stops_before_executing_it() {
+        let mut cpu = cpu(&[addiu(T0, ZERO, 1), addiu(T0, T0, 1)]);
+        cpu.set_halt_address(Some(INST_MEM_START + 4));
+        run(&mut cpu);
+        assert_eq!(
+            cpu.get_halt_reason(),
+            Some(HaltReason::HaltAddress(INST_MEM_START + 4))
+        );
+        assert_eq!(cpu.get_register(RegisterKind::Reg08), 1);
+    }
+
+    /// `program` followed by `handler` at the general exception vector, with
+    /// EBase pointing at the start of `.text`.
+    fn cpu_with_handler(program: &[u32], handler: &[u32]) -> SingleCycleCPU {
+        let mut text = program.to_vec();
+        text.resize(0x180 / 4, nop());
+        text.extend_from_slice(handler);
+        let mut cpu = cpu(&text);
+        cpu.cp0.write(CP0_EBASE, INST_MEM_START);
+        cpu
+    }
+
+    #[test]
+    fn unknown_instructions_raise_reserved_instruction() {
+        // An opcode and an R-type function the datapath does not implement.
+        [0x3F << 26, r_type(0x18, T0, T1, T2)]
+            .into_iter()
+            .for_each(|inst| {
+                let mut cpu = cpu_with_handler(&[nop(), inst], &[nop()]);
+                cpu.step();
+                cpu.step();
+                assert_eq!(cpu.get_error(), None);
+                assert_eq!(cpu.get_cp0_register(CP0_CAUSE) >> 2 & 0x1F, 10);
+                assert_eq!(cpu.get_cp0_register(CP0_EPC), INST_MEM_START + 4);
+                assert_eq!(
+                    cpu.get_register(RegisterKind::RegPC),
+                    INST_MEM_START + 0x180
+                );
+            });
+    }
+
+    #[test]
+    fn exceptions_without_a_handler_stop_with_the_cause() {
+        let mut cpu = cpu(&[nop(), 0x3F << 26]);
+        cpu.step();
+        cpu.step();
+        assert_eq!(
+            cpu.get_error().as_deref(),
+            Some(
+                "No exception handler installed at 0x80000180 for the reserved instruction \
+                 exception at pc 0x00400004"
+            )
+        );
+        assert_eq!(cpu.get_register(RegisterKind::RegPC), INST_MEM_START + 4);
+    }
+
+    #[test]
+    fn tlb_misses_refill_and_clean_pages_raise_mod() {
+        let data = DATA_MEM_START;
+        // Map the page holding .text, then the one holding .data read only,
+        // and leave ERL to turn translation on.
+        let program = [(INST_MEM_START, 0), (data, 1)]
+            .into_iter()
+            .flat_map(|(page, index)| {
+                let mut setup = li(T0, page).to_vec();
+                setup.extend(li(T1, page >> 12 << 6 | 0b010));
+                setup.extend([
+                    mtc0(T0, CP0_ENTRY_HI),
+                    mtc0(T1, CP0_ENTRY_LO0),
+                    mtc0(ZERO, CP0_ENTRY_LO1),
+                    addiu(T0, ZERO, index),
+                    mtc0(T0, CP0_INDEX),
+                    tlbwi(),
+                ]);
+                setup
+            })
+            .chain([mtc0(ZERO, CP0_STATUS)])
+            .chain(li(T2, data));
+        let mut program = program.collect::<Vec<_>>();
+        let setup = program.len();
+        program.extend([lw(T0, 0, T2), sw(T0, 0, T2), lw(T0, 0x2000, T2)]);
+
+        let mut cpu = cpu_with_handler(&program, &[eret()]);
+        cpu.set_address_translation(AddressTranslation::Tlb(Default::default()));
+        cpu.cp0.write(CP0_EBASE, INST_MEM_START);
+        (0..setup + 1).for_each(|_| cpu.step());
+        assert_eq!(cpu.get_error(), None);
+
+        cpu.step();
+        let store = INST_MEM_START + (setup as u32 + 1) * 4;
+        assert_eq!(cpu.get_cp0_register(CP0_CAUSE) >> 2 & 0x1F, 1);
+        assert_eq!(cpu.get_cp0_register(CP0_EPC), store);
+        assert_eq!(cpu.get_cp0_register(CP0_BAD_VADDR), data);
+        assert_eq!(
+            cpu.get_register(RegisterKind::RegPC),
+            INST_MEM_START + 0x180
+        );
+
+        // Skip the store, the handler returns to it otherwise.
+        cpu.cp0.write(CP0_EPC, store + 4);
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.get_cp0_register(CP0_CAUSE) >> 2 & 0x1F, 2);
+        assert_eq!(cpu.get_cp0_register(CP0_BAD_VADDR), data + 0x2000);
+        assert_eq!(cpu.get_cp0_register(CP0_ENTRY_HI), data + 0x2000);
+        assert_eq!(cpu.get_register(RegisterKind::RegPC), INST_MEM_START);
+    }
+
+    #[test]
+    fn timer_interrupt_arrives_on_ip4_at_the_same_instruction() {
+        let mut program = li(T0, TIMER_START).to_vec();
+        program.extend([
+            addiu(T1, ZERO, 2),
+            sw(T1, 4, T0),
+            // Enable the timer and its interrupt, then IE and IM4.
+            addiu(T1, ZERO, 0b11),
+            sw(T1, 0, T0),
+        ]);
+        program.extend(li(T1, 1 << 12 | 1));
+        program.push(mtc0(T1, CP0_STATUS));
+        let interrupted = INST_MEM_START + program.len() as u32 * 4;
+
+        let mut cpu = cpu_with_handler(&program, &[nop()]);
+        cpu.attach_device(Box::new(Timer::new()));
+        (0..program.len()).for_each(|_| cpu.step());
+        assert_eq!(cpu.get_register(RegisterKind::RegPC), interrupted);
+
+        cpu.step();
+        assert_eq!(cpu.get_error(), None);
+        assert_eq!(
+            cpu.get_register(RegisterKind::RegPC),
+            INST_MEM_START + 0x180
+        );
+        assert_eq!(cpu.get_cp0_register(CP0_EPC), interrupted);
+        let cause = cpu.get_cp0_register(CP0_CAUSE);
+        assert_eq!(cause >> 2 & 0x1F, 0);
+        assert_eq!(cause & 0xFF00, 1 << 12);
+    }
+
+    #[test]
+    fn register_read_watchpoints_see_only_operands_read() {
+        let watch_t1 = Watchpoint {
+            target: WatchTarget::Register(T1),
+            trigger: Trigger::Read,
+        };
+        let mut cpu = cpu(&[
+            lui(T0, (DATA_MEM_START >> 16) as u16),
+            lui(T1, 1),
+            // Shifts read rt only, so the rs field naming $t1 is ignored.
+            r_type(SLL_FUNCT, T2, T1, ZERO),
+            sw(T1, 0, T0),
+        ]);
+        cpu.set_watchpoints(&[watch_t1]);
+        (0..3).for_each(|_| cpu.step());
+        assert_eq!(cpu.get_watch_hit(), None);
+
+        cpu.step();
+        let hit = cpu.get_watch_hit().unwrap();
+        assert_eq!(hit.location, WatchTarget::Register(T1));
+        assert_eq!(hit.old, None);
+        assert_eq!(hit.value, 0x10000);
+    }
+
+    #[test]
+    fn last_cache_access_only_lasts_a_step() {
+        let mut cpu = cpu(&[lw(T0, 0, 28), nop()]);
+        cpu.configure_caches(Some(CacheConfig::default()), None);
+        cpu.step();
+        assert!(cpu.get_data_cache().unwrap().last_access().is_some());
+        cpu.step();
+        assert!(cpu.get_data_cache().unwrap().last_access().is_none());
+    }
+
+    #[test]
+    fn other_syscalls_do_nothing() {
+        let mut cpu = cpu(&[addiu(V0, ZERO, 1), syscall(), addiu(T0, ZERO, 1)]);
+        run(&mut cpu);
+        assert_eq!(cpu.get_error(), None);
+        assert_eq!(cpu.get_halt_reason(), Some(HaltReason::EndOfText));
+        assert_eq!(cpu.get_register(RegisterKind::Reg08), 1);
+    }
 }
@@ -0,0 +1,124 @@
+use std::{fmt::Display, ops::Add};
+
+use super::{
+    control_unit::{ControlUnitOutput, Mem, PCSrc},
+    BREAK_FUNCT, COP0_OPCODE, SYSCALL_FUNCT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionClass {
+    Alu,
+    Load,
+    Store,
+    BranchTaken,
+    BranchNotTaken,
+    Jump,
+    /// Syscalls, breaks and coprocessor 0 instructions.
+    System,
+}
+
+impl InstructionClass {
+    pub const ALL: [InstructionClass; 7] = [
+        InstructionClass::Alu,
+        InstructionClass::Load,
+        InstructionClass::Store,
+        InstructionClass::BranchTaken,
+        InstructionClass::BranchNotTaken,
+        InstructionClass::Jump,
+        InstructionClass::System,
+    ];
+
+    pub fn classify(
+        opcode: u32,
+        funct: u32,
+        signals: &ControlUnitOutput,
+        branch_taken: bool,
+    ) -> Self {
+        match signals.pc_src {
+            PCSrc::PCBranch if branch_taken => InstructionClass::BranchTaken,
+            PCSrc::PCBranch => InstructionClass::BranchNotTaken,
+            PCSrc::Jump | PCSrc::RegJump => InstructionClass::Jump,
+            PCSrc::PC if !matches!(signals.mem_read, Mem::None) => InstructionClass::Load,
+            PCSrc::PC if !matches!(signals.mem_write, Mem::None) => InstructionClass::Store,
+            PCSrc::PC
+                if opcode == COP0_OPCODE
+                    || (opcode == 0 && (funct == SYSCALL_FUNCT || funct == BREAK_FUNCT)) =>
+            {
+                InstructionClass::System
+            }
+            PCSrc::PC => InstructionClass::Alu,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InstructionClass::Alu => "ALU",
+            InstructionClass::Load => "Load",
+            InstructionClass::Store => "Store",
+            InstructionClass::BranchTaken => "Branch taken",
+            InstructionClass::BranchNotTaken => "Branch not taken",
+            InstructionClass::Jump => "Jump",
+            InstructionClass::System => "System",
+        }
+    }
+}
+
+/// Performance counters of a CPU. A cycle is spent on every instruction
+/// fetched and on every exception or interrupt taken, while only
+/// instructions that complete are retired and counted by class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub cycles: u64,
+    pub instret: u64,
+    classes: [u64; InstructionClass::ALL.len()],
+}
+
+impl Statistics {
+    pub fn count(&self, class: InstructionClass) -> u64 {
+        self.classes[class as usize]
+    }
+
+    /// Cycles per retired instruction, 0 before anything has retired.
+    pub fn cpi(&self) -> f64 {
+        match self.instret {
+            0 => 0.0,
+            instret => self.cycles as f64 / instret as f64,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.cycles += 1;
+    }
+
+    pub fn retire(&mut self, class: InstructionClass) {
+        self.instret += 1;
+        self.classes[class as usize] += 1;
+    }
+}
+
+impl Add for Statistics {
+    type Output = Statistics;
+
+    fn add(self, other: Statistics) -> Statistics {
+        Statistics {
+            cycles: self.cycles + other.cycles,
+            instret: self.instret + other.instret,
+            classes: std::array::from_fn(|idx| self.classes[idx] + other.classes[idx]),
+        }
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cycles: {}\nInstructions: {}\nCPI: {:.2}",
+            self.cycles,
+            self.instret,
+            self.cpi()
+        )?;
+        InstructionClass::ALL
+            .iter()
+            .try_for_each(|class| write!(f, "\n{}: {}", class.name(), self.count(*class)))
+    }
+}
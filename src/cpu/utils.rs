@@ -1,6 +1,62 @@
 use std::mem::size_of_val;
 
+use super::{BEQ_OPCODE, BNE_OPCODE, JALR_FUNCT, JAL_OPCODE, J_OPCODE};
+
 pub fn sign_extend(v: i32, n_bits: u32) -> i32 {
     let other_bits = size_of_val(&v) as u32 * 8 - n_bits;
     v.wrapping_shl(other_bits).wrapping_shr(other_bits)
 }
+
+/// Where the branch or jump `inst` at `pc` goes when taken, worked out as
+/// the datapath does. `None` for other instructions and register jumps.
+pub fn jump_target(inst: u32, pc: u32) -> Option<u32> {
+    match inst >> 26 {
+        J_OPCODE | JAL_OPCODE => {
+            Some(((inst & 0x03FF_FFFF) << 2) + (pc.wrapping_add(4) & !0x0FFF_FFFF))
+        }
+        BEQ_OPCODE | BNE_OPCODE => {
+            Some(((sign_extend((inst & 0xFFFF) as i32, 16) as u32) << 2).wrapping_add(pc))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `inst` is a `jal` or `jalr`, which return to the instruction
+/// after them.
+pub fn is_call(inst: u32) -> bool {
+    matches!((inst >> 26, inst & 0x3F), (JAL_OPCODE, _) | (0, JALR_FUNCT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode::*, INST_MEM_START};
+    use super::*;
+
+    #[test]
+    fn jumps_stay_in_the_current_256mb_region() {
+        assert_eq!(
+            jump_target(j(0x0040_0010), INST_MEM_START),
+            Some(0x0040_0010)
+        );
+        assert_eq!(
+            jump_target(jal(0x0000_0100), 0x1000_0000),
+            Some(0x1000_0100)
+        );
+    }
+
+    #[test]
+    fn branches_are_relative_to_the_branch() {
+        assert_eq!(jump_target(beq(T0, T1, 3), 0x100), Some(0x10C));
+        assert_eq!(jump_target(bne(T0, ZERO, -1), 0x100), Some(0xFC));
+        assert_eq!(jump_target(jr(RA), 0x100), None);
+        assert_eq!(jump_target(addiu(T0, T0, 1), 0x100), None);
+    }
+
+    #[test]
+    fn calls_are_jal_and_jalr() {
+        assert!(is_call(jal(0x0040_0000)));
+        assert!(is_call(r_type(JALR_FUNCT, RA, T0, ZERO)));
+        assert!(!is_call(jr(RA)));
+        assert!(!is_call(j(0x0040_0000)));
+    }
+}
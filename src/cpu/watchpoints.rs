@@ -0,0 +1,325 @@
+use std::fmt::Display;
+
+use super::registers::REGISTER_NAMES;
+
+/// How a value is compared with another. Ordering compares values as
+/// signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// Spellings of each comparison, two character operators first so `<=`
+    /// is not read as `<`.
+    pub const ALL: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+    ];
+
+    pub fn holds(self, left: u32, right: u32) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => (left as i32) < (right as i32),
+            Comparison::Le => (left as i32) <= (right as i32),
+            Comparison::Gt => (left as i32) > (right as i32),
+            Comparison::Ge => (left as i32) >= (right as i32),
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        Comparison::ALL
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map_or("", |(symbol, _)| symbol)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// `len` bytes of memory from `start`.
+    Memory { start: u32, len: u32 },
+    /// A general purpose register by number.
+    Register(u32),
+}
+
+impl WatchTarget {
+    fn overlaps(&self, other: &WatchTarget) -> bool {
+        match (self, other) {
+            (
+                WatchTarget::Memory { start, len },
+                WatchTarget::Memory {
+                    start: other_start,
+                    len: other_len,
+                },
+            ) => {
+                (*start as u64) < *other_start as u64 + *other_len as u64
+                    && (*other_start as u64) < *start as u64 + *len as u64
+            }
+            (WatchTarget::Register(reg), WatchTarget::Register(other)) => reg == other,
+            _ => false,
+        }
+    }
+}
+
+impl Display for WatchTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchTarget::Memory { start, len: 1 } => write!(f, "0x{start:08x}"),
+            WatchTarget::Memory { start, len } => write!(f, "0x{start:08x} ({len} bytes)"),
+            WatchTarget::Register(reg) => {
+                write!(f, "{}", REGISTER_NAMES.get(*reg as usize).unwrap_or(&"$?"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Read,
+    Write,
+    Access,
+    /// A write that changes the value, optionally only to one the comparison
+    /// with the given value holds for.
+    Change(Option<(Comparison, u32)>),
+}
+
+/// Stops a running program when the target is accessed as the trigger says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub target: WatchTarget,
+    pub trigger: Trigger,
+}
+
+impl Watchpoint {
+    /// Whether an access that read `value`, or wrote `value` over `old`,
+    /// sets this watchpoint off.
+    fn triggered(&self, old: Option<u32>, value: u32) -> bool {
+        match (self.trigger, old) {
+            (Trigger::Read | Trigger::Access, None) => true,
+            (Trigger::Write | Trigger::Access, Some(_)) => true,
+            (Trigger::Change(condition), Some(old)) => {
+                old != value
+                    && condition.is_none_or(|(comparison, right)| comparison.holds(value, right))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.trigger {
+            Trigger::Read => write!(f, "{} read", self.target),
+            Trigger::Write => write!(f, "{} written", self.target),
+            Trigger::Access => write!(f, "{} read or written", self.target),
+            Trigger::Change(None) => write!(f, "{} changed", self.target),
+            Trigger::Change(Some((comparison, value))) => write!(
+                f,
+                "{} changed to a value {} {}",
+                self.target,
+                comparison.symbol(),
+                value as i32
+            ),
+        }
+    }
+}
+
+/// An access that set a watchpoint off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Position of the watchpoint in the list the watchpoints were set from.
+    pub index: usize,
+    pub watchpoint: Watchpoint,
+    /// The bytes accessed or the register written.
+    pub location: WatchTarget,
+    /// Value before a write, `None` for reads.
+    pub old: Option<u32>,
+    pub value: u32,
+}
+
+impl Display for WatchHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Watchpoint {} ({}): ", self.index + 1, self.watchpoint)?;
+        match self.old {
+            None => write!(f, "read 0x{:x} from {}", self.value, self.location),
+            Some(old) => write!(
+                f,
+                "{} written with 0x{:x}, was 0x{old:x}",
+                self.location, self.value
+            ),
+        }
+    }
+}
+
+/// Watchpoints enforced by one memory or register file, and the first of
+/// them hit since the hit was last cleared.
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    watchpoints: Vec<Watchpoint>,
+    hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn set(&mut self, watchpoints: &[Watchpoint]) {
+        self.watchpoints = watchpoints.to_vec();
+        self.hit = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    pub fn hit(&self) -> Option<WatchHit> {
+        self.hit
+    }
+
+    pub fn clear_hit(&mut self) {
+        self.hit = None;
+    }
+
+    pub fn load(&mut self, addr: u32, width: u32, value: u32) {
+        self.record(
+            WatchTarget::Memory {
+                start: addr,
+                len: width,
+            },
+            None,
+            value,
+        );
+    }
+
+    pub fn store(&mut self, addr: u32, width: u32, old: u32, value: u32) {
+        self.record(
+            WatchTarget::Memory {
+                start: addr,
+                len: width,
+            },
+            Some(old),
+            value,
+        );
+    }
+
+    pub fn read_register(&mut self, reg: u32, value: u32) {
+        self.record(WatchTarget::Register(reg), None, value);
+    }
+
+    pub fn write_register(&mut self, reg: u32, old: u32, value: u32) {
+        self.record(WatchTarget::Register(reg), Some(old), value);
+    }
+
+    fn record(&mut self, location: WatchTarget, old: Option<u32>, value: u32) {
+        if self.hit.is_some() {
+            return;
+        }
+        self.hit = self
+            .watchpoints
+            .iter()
+            .enumerate()
+            .find(|(_, watchpoint)| {
+                watchpoint.target.overlaps(&location) && watchpoint.triggered(old, value)
+            })
+            .map(|(index, watchpoint)| WatchHit {
+                index,
+                watchpoint: *watchpoint,
+                location,
+                old,
+                value,
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watching(target: WatchTarget, trigger: Trigger) -> Watchpoints {
+        let mut watchpoints = Watchpoints::default();
+        watchpoints.set(&[Watchpoint { target, trigger }]);
+        watchpoints
+    }
+
+    const WORD: WatchTarget = WatchTarget::Memory {
+        start: 0x1001_0004,
+        len: 4,
+    };
+
+    #[test]
+    fn reads_and_writes_trigger_their_watchpoints() {
+        let mut reads = watching(WORD, Trigger::Read);
+        reads.store(0x1001_0004, 4, 0, 1);
+        assert_eq!(reads.hit(), None);
+        reads.load(0x1001_0006, 1, 7);
+        let hit = reads.hit().unwrap();
+        assert_eq!(hit.old, None);
+        assert_eq!(hit.value, 7);
+
+        let mut writes = watching(WORD, Trigger::Write);
+        writes.load(0x1001_0004, 4, 1);
+        assert_eq!(writes.hit(), None);
+        writes.store(0x1001_0004, 4, 3, 3);
+        assert_eq!(writes.hit().unwrap().old, Some(3));
+
+        let mut accesses = watching(WORD, Trigger::Access);
+        accesses.load(0x1001_0004, 4, 1);
+        assert!(accesses.hit().is_some());
+    }
+
+    #[test]
+    fn accesses_next_to_the_target_do_not_trigger() {
+        let mut watchpoints = watching(WORD, Trigger::Access);
+        watchpoints.store(0x1001_0000, 4, 0, 1);
+        watchpoints.load(0x1001_0008, 1, 0);
+        assert_eq!(watchpoints.hit(), None);
+    }
+
+    #[test]
+    fn changes_trigger_only_when_the_value_changes_as_asked() {
+        let mut changes = watching(WatchTarget::Register(8), Trigger::Change(None));
+        changes.write_register(8, 5, 5);
+        assert_eq!(changes.hit(), None);
+        changes.write_register(8, 5, 6);
+        assert!(changes.hit().is_some());
+
+        let negative = Trigger::Change(Some((Comparison::Lt, 0)));
+        let mut changes = watching(WatchTarget::Register(8), negative);
+        changes.write_register(8, 5, 1);
+        assert_eq!(changes.hit(), None);
+        changes.write_register(8, 1, -1i32 as u32);
+        assert_eq!(changes.hit().unwrap().value, u32::MAX);
+    }
+
+    #[test]
+    fn register_reads_trigger_read_watchpoints() {
+        let mut watchpoints = watching(WatchTarget::Register(9), Trigger::Read);
+        watchpoints.read_register(8, 1);
+        watchpoints.write_register(9, 0, 1);
+        assert_eq!(watchpoints.hit(), None);
+        watchpoints.read_register(9, 1);
+        assert_eq!(
+            watchpoints.hit().unwrap().to_string(),
+            "Watchpoint 1 ($t1 read): read 0x1 from $t1"
+        );
+    }
+
+    #[test]
+    fn the_first_hit_is_kept_until_cleared() {
+        let mut watchpoints = watching(WatchTarget::Register(9), Trigger::Access);
+        watchpoints.read_register(9, 1);
+        watchpoints.write_register(9, 1, 2);
+        assert_eq!(watchpoints.hit().unwrap().old, None);
+        watchpoints.clear_hit();
+        watchpoints.write_register(9, 1, 2);
+        assert_eq!(watchpoints.hit().unwrap().old, Some(1));
+    }
+}
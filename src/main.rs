@@ -1,4 +1,6 @@
+mod breakpoints;
 mod cpu;
+mod source_map;
 mod ui_components;
 
 use std::path::PathBuf;
@@ -19,6 +21,10 @@ use ui_components::{
     cpu_simulation::{CPUSimulation, SimulationMsg, SimulationOutput},
 };
 
+use crate::cpu::{
+    cache::CacheConfig, data_memory::MemoryArchitecture, devices::bitmap_display::BitmapConfig,
+    mmu::AddressTranslation, multicore::MulticoreConfig,
+};
 use crate::ui_components::preferences::{Preferences, UpdatePreferencesOutput};
 
 struct App {
@@ -41,6 +47,12 @@ pub enum Msg {
     ShowPreferences,
     ResizeHistory(usize),
     ChangeRadix(Radices),
+    SetHaltAddress(Option<u32>),
+    SetMemoryArchitecture(MemoryArchitecture),
+    ConfigureCaches(Option<CacheConfig>, Option<CacheConfig>),
+    SetAddressTranslation(AddressTranslation),
+    ConfigureBitmapDisplay(Option<BitmapConfig>),
+    ConfigureCores(MulticoreConfig),
     ChangeTheme,
     NewTab,
     OpenRequest(DynamicIndex),
@@ -127,6 +139,20 @@ impl Component for App {
                 .forward(sender.input_sender(), |msg| match msg {
                     UpdatePreferencesOutput::HistorySize(size) => Msg::ResizeHistory(size),
                     UpdatePreferencesOutput::Radix(radix) => Msg::ChangeRadix(radix),
+                    UpdatePreferencesOutput::HaltAddress(address) => Msg::SetHaltAddress(address),
+                    UpdatePreferencesOutput::MemoryArchitecture(architecture) => {
+                        Msg::SetMemoryArchitecture(architecture)
+                    }
+                    UpdatePreferencesOutput::Caches(data, instruction) => {
+                        Msg::ConfigureCaches(data, instruction)
+                    }
+                    UpdatePreferencesOutput::AddressTranslation(translation) => {
+                        Msg::SetAddressTranslation(translation)
+                    }
+                    UpdatePreferencesOutput::BitmapDisplay(config) => {
+                        Msg::ConfigureBitmapDisplay(config)
+                    }
+                    UpdatePreferencesOutput::Multicore(config) => Msg::ConfigureCores(config),
                     UpdatePreferencesOutput::Theme => Msg::ChangeTheme,
                 });
 
@@ -191,6 +217,24 @@ impl Component for App {
             Msg::ChangeRadix(radix) => self
                 .simulations
                 .broadcast(SimulationMsg::ChangeRadix(radix)),
+            Msg::SetHaltAddress(address) => self
+                .simulations
+                .broadcast(SimulationMsg::SetHaltAddress(address)),
+            Msg::SetMemoryArchitecture(architecture) => self
+                .simulations
+                .broadcast(SimulationMsg::SetMemoryArchitecture(architecture)),
+            Msg::ConfigureCaches(data, instruction) => self
+                .simulations
+                .broadcast(SimulationMsg::ConfigureCaches(data, instruction)),
+            Msg::SetAddressTranslation(translation) => self
+                .simulations
+                .broadcast(SimulationMsg::SetAddressTranslation(translation)),
+            Msg::ConfigureBitmapDisplay(config) => self
+                .simulations
+                .broadcast(SimulationMsg::ConfigureBitmapDisplay(config)),
+            Msg::ConfigureCores(config) => self
+                .simulations
+                .broadcast(SimulationMsg::ConfigureCores(config)),
             Msg::ChangeTheme => self.simulations.broadcast(SimulationMsg::UpdateViews),
             Msg::ResizeHistory(size) => self
                 .simulations
@@ -204,6 +248,25 @@ impl Component for App {
                 sender.input_sender().emit(Msg::ResizeHistory(
                     self.preferences_menu.model().history_size,
                 ));
+                sender.input_sender().emit(Msg::SetHaltAddress(
+                    self.preferences_menu.model().halt_address,
+                ));
+                sender.input_sender().emit(Msg::SetMemoryArchitecture(
+                    self.preferences_menu.model().memory_architecture,
+                ));
+                let (data_cache, instruction_cache) = self.preferences_menu.model().caches();
+                sender
+                    .input_sender()
+                    .emit(Msg::ConfigureCaches(data_cache, instruction_cache));
+                sender.input_sender().emit(Msg::SetAddressTranslation(
+                    self.preferences_menu.model().address_translation(),
+                ));
+                sender.input_sender().emit(Msg::ConfigureBitmapDisplay(
+                    self.preferences_menu.model().bitmap_display(),
+                ));
+                sender.input_sender().emit(Msg::ConfigureCores(
+                    self.preferences_menu.model().multicore(),
+                ));
             }
             Msg::OpenRequest(index) => {
                 self.file_tab = Some(index);
@@ -0,0 +1,640 @@
+use mips_assembler::parse;
+
+use crate::cpu::{DATA_MEM_START, INST_MEM_START};
+
+/// Opcode of `lui`, used with `$zero` as the target for line markers.
+const LUI_OPCODE: u32 = 0x0F;
+const REGIMM_OPCODE: u32 = 0x01;
+const J_OPCODE: u32 = 0x02;
+const JAL_OPCODE: u32 = 0x03;
+const BGTZ_OPCODE: u32 = 0x07;
+const ADDIU_OPCODE: u32 = 0x09;
+const ORI_OPCODE: u32 = 0x0D;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Text,
+    Data,
+}
+
+impl SymbolKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolKind::Text => "Text",
+            SymbolKind::Data => "Data",
+        }
+    }
+}
+
+/// A label with the memory it covers, which runs up to the next label at a
+/// higher address in its segment or the end of the segment.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub addr: u32,
+    pub size: u32,
+    /// Zero based source line defining the label.
+    pub line: usize,
+}
+
+/// Relates assembled instructions back to the source lines they came from.
+///
+/// The assembler only returns machine code, so the map is built by
+/// assembling the program a second time with a `lui $zero, n` marker after
+/// every instruction line. Whatever the assembler emits between markers
+/// `n - 1` and `n` belongs to line `n`, which keeps pseudo-instructions that
+/// expand to several words exact. Markers are numbered from past any
+/// `lui $zero` in the program itself, and the words between them must be
+/// the program's instructions, up to the label addresses the markers move,
+/// so a map that does not line up is left empty rather than wrong.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    /// Zero based source line of each instruction, by index from the start
+    /// of text.
+    lines: Vec<usize>,
+    /// Text labels with the address they name, in source order.
+    labels: Vec<(String, u32)>,
+    /// Labels in `.data` with the address they name, in source order.
+    data_labels: Vec<(String, u32)>,
+    /// Text and data labels by address.
+    symbols: Vec<Symbol>,
+}
+
+impl SourceMap {
+    /// Maps `source`, which assembled to `words` of text and `data_len`
+    /// bytes of data. The map is empty if the instrumented program does not
+    /// assemble to the same text.
+    pub fn new(source: &str, words: &[u32], data_len: usize) -> Self {
+        let Some(base) = marker_base(words, words.len()) else {
+            return Self::default();
+        };
+        let mut instrumented = String::new();
+        let mut marked_lines = Vec::new();
+        let mut pending_labels = Vec::new();
+        let mut text = true;
+        let mut data = DataLayout::default();
+        let mut definitions = Vec::new();
+
+        source.lines().enumerate().for_each(|(line, contents)| {
+            instrumented.push_str(contents);
+            instrumented.push('\n');
+
+            let (labels, statement) = split_labels(strip_comment(contents));
+            definitions.extend(labels.iter().map(|label| (label.clone(), line)));
+            let first = statement.split_whitespace().next();
+            match first {
+                Some(".text") => text = true,
+                Some(".data" | ".kdata" | ".ktext") => text = false,
+                _ => {}
+            }
+            if !text {
+                data.add(labels, statement);
+                return;
+            }
+
+            pending_labels.extend(labels.into_iter().map(|label| (label, marked_lines.len())));
+            match first {
+                Some(directive) if directive.starts_with('.') => {}
+                Some(_) => {
+                    let marker = base + marked_lines.len() as u32;
+                    instrumented.push_str(&format!("\tlui $0, {marker}\n"));
+                    marked_lines.push(line);
+                }
+                None => {}
+            }
+        });
+
+        let Ok((marked, _)) = parse(&instrumented) else {
+            return Self::default();
+        };
+
+        let mut lines = Vec::new();
+        let mut starts = Vec::with_capacity(marked_lines.len() + 1);
+        let mut unmarked = Vec::with_capacity(words.len());
+        let mut marker = 0;
+        let mut pending = 0;
+        starts.push(0);
+        for word in marked {
+            if marker < marked_lines.len() && word == marker_word(base + marker as u32) {
+                lines.extend(std::iter::repeat_n(marked_lines[marker], pending));
+                starts.push(lines.len());
+                marker += 1;
+                pending = 0;
+            } else {
+                unmarked.push(word);
+                pending += 1;
+            }
+        }
+        let same_program = unmarked.len() == words.len()
+            && unmarked
+                .iter()
+                .zip(words)
+                .all(|(marked, word)| same_instruction(*marked, *word));
+        if marker != marked_lines.len() || pending != 0 || !same_program {
+            return Self::default();
+        }
+
+        let labels: Vec<_> = pending_labels
+            .into_iter()
+            .map(|(label, marker)| (label, INST_MEM_START + starts[marker] as u32 * 4))
+            .collect();
+        let text_end = INST_MEM_START + lines.len() as u32 * 4;
+        let (data_labels, data_end) = data.finish(data_len);
+        let mut symbols = sized_symbols(&labels, SymbolKind::Text, text_end, &definitions);
+        symbols.extend(sized_symbols(
+            &data_labels,
+            SymbolKind::Data,
+            data_end,
+            &definitions,
+        ));
+        Self {
+            lines,
+            labels,
+            data_labels,
+            symbols,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Source line of the instruction at `pc`.
+    pub fn line(&self, pc: u32) -> Option<usize> {
+        let offset = pc.checked_sub(INST_MEM_START)?;
+        if offset & 3 != 0 {
+            return None;
+        }
+        self.lines.get((offset / 4) as usize).copied()
+    }
+
+    /// Addresses of the instructions assembled from `line`.
+    pub fn addresses(&self, line: usize) -> impl Iterator<Item = u32> + '_ {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(move |(_, source_line)| **source_line == line)
+            .map(|(idx, _)| INST_MEM_START + idx as u32 * 4)
+    }
+
+    pub fn labels(&self) -> &[(String, u32)] {
+        &self.labels
+    }
+
+    /// The first label naming `addr`.
+    pub fn label(&self, addr: u32) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(_, label_addr)| *label_addr == addr)
+            .map(|(label, _)| label.as_str())
+    }
+
+    pub fn data_labels(&self) -> &[(String, u32)] {
+        &self.data_labels
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+}
+
+/// `lui $zero, imm`, which is what the assembler makes of a marker.
+fn marker_word(imm: u32) -> u32 {
+    LUI_OPCODE << 26 | imm
+}
+
+/// Whether `marked`, from the program with markers, is the instruction
+/// `word`. Markers move every label after them, so the fields a label's
+/// address goes into are left out: branch offsets, jump targets and the
+/// halves of an address loaded with `la`.
+fn same_instruction(marked: u32, word: u32) -> bool {
+    let mask = match word >> 26 {
+        J_OPCODE | JAL_OPCODE => 0xFC00_0000,
+        REGIMM_OPCODE | 0x04..=BGTZ_OPCODE | ADDIU_OPCODE | ORI_OPCODE | LUI_OPCODE => 0xFFFF_0000,
+        _ => u32::MAX,
+    };
+    marked & mask == word & mask
+}
+
+/// The first of `count` consecutive marker immediates none of which the
+/// program's own `lui $zero` instructions in `words` use.
+fn marker_base(words: &[u32], count: usize) -> Option<u32> {
+    let mut taken: Vec<_> = words
+        .iter()
+        .filter(|word| *word & 0xFFFF_0000 == marker_word(0))
+        .map(|word| word & 0xFFFF)
+        .collect();
+    taken.sort_unstable();
+    let count = count as u32;
+    let mut base = 0;
+    for imm in taken {
+        if imm >= base + count {
+            break;
+        }
+        if imm >= base {
+            base = imm + 1;
+        }
+    }
+    (base + count <= 0x10000).then_some(base)
+}
+
+/// Sizes `labels` of one segment ending at `end`, sorted by address.
+fn sized_symbols(
+    labels: &[(String, u32)],
+    kind: SymbolKind,
+    end: u32,
+    definitions: &[(String, usize)],
+) -> Vec<Symbol> {
+    let mut symbols = labels
+        .iter()
+        .map(|(name, addr)| Symbol {
+            name: name.clone(),
+            kind,
+            addr: *addr,
+            size: labels
+                .iter()
+                .map(|(_, next)| *next)
+                .filter(|next| next > addr)
+                .min()
+                .unwrap_or(end)
+                .saturating_sub(*addr),
+            line: definitions
+                .iter()
+                .find(|(label, _)| label == name)
+                .map(|(_, line)| *line)
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    symbols.sort_by_key(|symbol| symbol.addr);
+    symbols
+}
+
+/// Works out where labels in `.data` point by laying the data directives
+/// out as MARS does, since the assembler does not report them. `.half`,
+/// `.word`, `.float` and `.double` are aligned to their size, along with
+/// the labels just before them. Labels after a directive the layout does
+/// not know the size of are left out until `.data` gives an address again.
+#[derive(Debug)]
+struct DataLayout {
+    /// Whether the current segment is `.data`, rather than `.kdata`.
+    data: bool,
+    addr: u32,
+    /// Whether `addr` is unknown since an unmodelled directive.
+    lost: bool,
+    /// Whether `.data` moved `addr` away from where the assembled data is.
+    moved: bool,
+    pending: Vec<String>,
+    labels: Vec<(String, u32)>,
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self {
+            data: true,
+            addr: DATA_MEM_START,
+            lost: false,
+            moved: false,
+            pending: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+}
+
+/// Directives that take no room in `.data`.
+const EMPTY_DIRECTIVES: [&str; 8] = [
+    ".data",
+    ".kdata",
+    ".ktext",
+    ".globl",
+    ".extern",
+    ".eqv",
+    ".set",
+    ".end_macro",
+];
+
+impl DataLayout {
+    /// Lays out a statement outside of `.text`.
+    fn add(&mut self, labels: Vec<String>, statement: &str) {
+        let (directive, args) = statement
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((statement.trim(), ""));
+        match directive {
+            ".data" => {
+                self.data = true;
+                if let Some(addr) = parse_address(args) {
+                    self.addr = addr;
+                    self.lost = false;
+                    self.moved = true;
+                }
+            }
+            ".kdata" | ".ktext" => self.data = false,
+            _ => {}
+        }
+        if !self.data || self.lost {
+            return;
+        }
+        self.pending.extend(labels);
+
+        let args = split_args(args);
+        let (align, size) = match directive {
+            ".byte" => (1, count(&args)),
+            ".half" => (2, count(&args) * 2),
+            ".word" | ".float" => (4, count(&args) * 4),
+            ".double" => (8, count(&args) * 8),
+            ".space" => (1, args.first().and_then(|n| parse_address(n)).unwrap_or(0)),
+            ".ascii" => (1, args.iter().map(|s| string_len(s)).sum()),
+            ".asciiz" => (1, args.iter().map(|s| string_len(s) + 1).sum()),
+            ".align" => {
+                let power = args.first().and_then(|n| parse_address(n)).unwrap_or(0);
+                (1 << power.min(31), 0)
+            }
+            _ if directive.is_empty() || EMPTY_DIRECTIVES.contains(&directive) => return,
+            _ => {
+                self.lost = true;
+                self.pending.clear();
+                return;
+            }
+        };
+        self.addr = self.addr.next_multiple_of(align);
+        let addr = self.addr;
+        self.labels
+            .extend(self.pending.drain(..).map(|label| (label, addr)));
+        self.addr = self.addr.wrapping_add(size);
+    }
+
+    /// The labels and the address just past the data laid out, given the
+    /// assembler emitted `data_len` bytes. A layout longer than that has
+    /// gone wrong somewhere, so none of its labels are kept.
+    fn finish(mut self, data_len: usize) -> (Vec<(String, u32)>, u32) {
+        let addr = self.addr;
+        if !self.moved && !self.lost && addr.wrapping_sub(DATA_MEM_START) as usize > data_len {
+            return (Vec::new(), addr);
+        }
+        if !self.lost {
+            self.labels
+                .extend(self.pending.drain(..).map(|label| (label, addr)));
+        }
+        (self.labels, addr)
+    }
+}
+
+/// A decimal or `0x` hexadecimal address or count.
+fn parse_address(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Splits directive arguments at commas outside string literals.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, c) in args.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(args[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Values given to a numeric directive, where `value:n` repeats `n` times.
+fn count(args: &[&str]) -> u32 {
+    args.iter()
+        .map(|arg| match arg.split_once(':') {
+            Some((_, repeat)) => parse_address(repeat).unwrap_or(1),
+            None => 1,
+        })
+        .sum()
+}
+
+/// Bytes in a string literal once escapes are resolved.
+fn string_len(literal: &str) -> u32 {
+    let Some(contents) = literal
+        .strip_prefix('"')
+        .and_then(|literal| literal.strip_suffix('"'))
+    else {
+        return 0;
+    };
+    let mut len = 0;
+    let mut escaped = false;
+    for c in contents.chars() {
+        if escaped || c != '\\' {
+            len += c.len_utf8() as u32;
+            escaped = false;
+        } else {
+            escaped = true;
+        }
+    }
+    len
+}
+
+/// Removes a `#` comment, ignoring `#` inside string and character literals.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (idx, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), c) if c == open => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits leading `label:` definitions off a line.
+fn split_labels(mut line: &str) -> (Vec<String>, &str) {
+    let mut labels = Vec::new();
+    loop {
+        line = line.trim_start();
+        let Some((label, rest)) = line.split_once(':') else {
+            return (labels, line);
+        };
+        let is_label = !label.is_empty()
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'));
+        if !is_label {
+            return (labels, line);
+        }
+        labels.push(label.to_string());
+        line = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(source: &str) -> SourceMap {
+        let (words, data) = parse(source).unwrap();
+        SourceMap::new(source, &words, data.len())
+    }
+
+    fn symbol<'a>(map: &'a SourceMap, name: &str) -> &'a Symbol {
+        map.symbols()
+            .iter()
+            .find(|symbol| symbol.name == name)
+            .unwrap()
+    }
+
+    const PROGRAM: &str = "\
+.text
+main:
+    li $t0, 0x12345678 # expands to more than one word
+    addi $t1, $t0, 1
+
+loop: j loop
+";
+
+    #[test]
+    fn instructions_map_to_their_lines() {
+        let (words, _) = parse(PROGRAM).unwrap();
+        let map = map(PROGRAM);
+        let expanded = words.len() - 2;
+        assert_eq!(map.addresses(2).count(), expanded);
+        assert_eq!(map.line(INST_MEM_START), Some(2));
+        assert_eq!(map.line(INST_MEM_START + expanded as u32 * 4), Some(3));
+        assert_eq!(map.line(INST_MEM_START + 2), None);
+        assert_eq!(map.addresses(4).count(), 0);
+    }
+
+    #[test]
+    fn text_labels_name_the_next_instruction() {
+        let map = map(PROGRAM);
+        let loop_addr = INST_MEM_START + (map.lines.len() as u32 - 1) * 4;
+        assert_eq!(map.label(INST_MEM_START), Some("main"));
+        assert_eq!(map.label(loop_addr), Some("loop"));
+        assert_eq!(symbol(&map, "main").size, loop_addr - INST_MEM_START);
+        assert_eq!(symbol(&map, "loop").size, 4);
+        assert_eq!(symbol(&map, "loop").line, 5);
+    }
+
+    #[test]
+    fn branches_and_jumps_across_lines_keep_the_map() {
+        let source = "\
+.text
+main:   li $t0, 3
+        beq $t0, $zero, done
+loop:   addi $t0, $t0, -1
+        bne $t0, $zero, loop
+        j main
+done:   li $v0, 10
+        syscall
+";
+        let (words, _) = parse(source).unwrap();
+        let map = map(source);
+        assert_eq!(map.lines.len(), words.len());
+        assert_eq!(map.line(INST_MEM_START + 4), Some(2));
+        let done = map.addresses(6).next().unwrap();
+        assert_eq!(map.label(done), Some("done"));
+        assert_eq!(map.line(done + 4), Some(7));
+        let loop_addr = map.addresses(3).next().unwrap();
+        assert_eq!(map.label(loop_addr), Some("loop"));
+        assert_eq!(map.addresses(5).next(), Some(done - 4));
+    }
+
+    #[test]
+    fn symbols_cover_programs_with_calls_and_jumps() {
+        let map = map("\
+.text
+main:   jal func
+        j end
+func:   addi $v0, $zero, 1
+        jr $ra
+end:    addi $v0, $zero, 10
+        syscall
+.data
+value:  .word 5
+");
+        let symbols: Vec<_> = map
+            .symbols()
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.addr, symbol.size))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("main", SymbolKind::Text, INST_MEM_START, 8),
+                ("func", SymbolKind::Text, INST_MEM_START + 8, 8),
+                ("end", SymbolKind::Text, INST_MEM_START + 16, 8),
+                ("value", SymbolKind::Data, DATA_MEM_START, 4),
+            ]
+        );
+        assert_eq!(map.label(INST_MEM_START + 8), Some("func"));
+    }
+
+    #[test]
+    fn writes_to_zero_in_the_program_are_not_markers() {
+        let source = ".text\nlui $0, 0\nlui $0, 1\naddi $t0, $0, 1\nlui $0, 3\n";
+        let map = map(source);
+        assert_eq!(map.lines, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn marker_base_skips_immediates_in_use() {
+        let words = [marker_word(0), marker_word(2), marker_word(7)];
+        assert_eq!(marker_base(&words, 1), Some(1));
+        assert_eq!(marker_base(&words, 2), Some(3));
+        assert_eq!(marker_base(&words, 5), Some(8));
+        assert_eq!(marker_base(&[marker_word(0xFFFF)], 0x10000), None);
+    }
+
+    #[test]
+    fn data_labels_follow_directive_sizes_and_alignment() {
+        let map = map(".data\nmsg: .asciiz \"hi\"\nnums: .word 1, 2\nflag: .byte 1\n");
+        let msg = symbol(&map, "msg");
+        assert_eq!((msg.addr, msg.size), (DATA_MEM_START, 4));
+        let nums = symbol(&map, "nums");
+        assert_eq!((nums.addr, nums.size), (DATA_MEM_START + 4, 8));
+        let flag = symbol(&map, "flag");
+        assert_eq!(
+            (flag.addr, flag.size, flag.line),
+            (DATA_MEM_START + 12, 1, 3)
+        );
+        assert_eq!(flag.kind, SymbolKind::Data);
+    }
+
+    #[test]
+    fn labels_after_unknown_directives_are_dropped() {
+        let mut layout = DataLayout::default();
+        layout.add(vec!["before".to_string()], ".word 1");
+        layout.add(vec!["unknown".to_string()], ".mystery 3");
+        layout.add(vec!["after".to_string()], ".word 2");
+        layout.add(Vec::new(), ".data 0x10020000");
+        layout.add(vec!["moved".to_string()], ".half 1");
+        let (labels, _) = layout.finish(0);
+        assert_eq!(
+            labels,
+            [
+                ("before".to_string(), DATA_MEM_START),
+                ("moved".to_string(), 0x10020000)
+            ]
+        );
+    }
+
+    #[test]
+    fn layouts_longer_than_the_data_are_dropped() {
+        let mut layout = DataLayout::default();
+        layout.add(vec!["word".to_string()], ".word 1, 2");
+        assert!(layout.finish(4).0.is_empty());
+    }
+}
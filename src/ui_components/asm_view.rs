@@ -4,19 +4,43 @@ use relm4::prelude::*;
 use relm4_icons::icon_name;
 use sourceview5::prelude::*;
 
+use crate::cpu::{profiler::Profile, utils::jump_target, INST_MEM_START};
+use crate::source_map::SourceMap;
+
+/// Gutter colours from coldest to hottest.
+const HEAT_COLORS: [u32; 4] = [0xf6d32dff, 0xffa348ff, 0xff7800ff, 0xe01b24ff];
+/// Mark categories of breakpoints with the colours of their dots.
+const BREAKPOINT: (&str, u32) = ("breakpoint", 0xc01c28ff);
+const CONDITIONAL_BREAKPOINT: (&str, u32) = ("conditional-breakpoint", 0x9141acff);
+
 #[derive(Debug)]
 pub struct AsmView {
     asm_buffer: sourceview5::Buffer,
     assembled_buffer: sourceview5::Buffer,
-    curr_line: u32,
+    source_view: sourceview5::View,
+    assembled_view: sourceview5::View,
+    pc: u32,
+    source_map: SourceMap,
+    condition_popover: gtk::Popover,
+    condition_entry: gtk::Entry,
+    condition_line: usize,
+    breakpoints: Vec<(usize, Option<String>)>,
     dirty: bool,
     can_save: bool,
 }
 
 #[derive(Debug)]
 pub enum AsmViewMsg {
-    LoadFile(String, Vec<u32>),
+    LoadFile(String, Vec<u32>, SourceMap),
     SetLine(u32),
+    SetProfile(Profile),
+    SetBreakpoints(Vec<(usize, Option<String>)>),
+    EditCondition(usize),
+    ConditionEntered(String),
+    RunToCursor,
+    ShowLine(usize),
+    /// Selects the word at an address in the machine code.
+    ShowAddress(u32),
     UpdateTheme,
     SaveFile,
     SetDirty(bool),
@@ -26,6 +50,9 @@ pub enum AsmViewMsg {
 #[derive(Debug)]
 pub enum AsmViewOutput {
     SaveFile(String),
+    ToggleBreakpoint(usize),
+    SetBreakpointCondition(usize, String),
+    RunToCursor(usize),
 }
 
 #[relm4::component(pub)]
@@ -42,18 +69,50 @@ impl SimpleComponent for AsmView {
             gtk::Overlay {
                 gtk::ScrolledWindow {
                     set_width_request: 500,
-                    sourceview5::View {
+                    #[local_ref]
+                    source_view -> sourceview5::View {
                         set_show_line_numbers: true,
+                        set_show_line_marks: true,
                         set_margin_all: 5,
                         set_vexpand: true,
                         set_monospace: true,
                         set_buffer: Some(&model.asm_buffer),
+                        connect_line_mark_activated[sender, popover = model.condition_popover.clone()] => move |view, iter, button, _, _| {
+                            let line = iter.line() as usize;
+                            if button == gtk::gdk::BUTTON_SECONDARY {
+                                let location = view.iter_location(iter);
+                                let (x, y) = view.buffer_to_window_coords(
+                                    gtk::TextWindowType::Widget,
+                                    location.x(),
+                                    location.y(),
+                                );
+                                popover.set_pointing_to(Some(&gtk::gdk::Rectangle::new(
+                                    x,
+                                    y,
+                                    1,
+                                    location.height(),
+                                )));
+                                sender.input(AsmViewMsg::EditCondition(line));
+                            } else {
+                                sender.output(AsmViewOutput::ToggleBreakpoint(line)).unwrap();
+                            }
+                        },
                     },
                 },
                 add_overlay = &gtk::Box {
                     set_halign: gtk::Align::End,
                     set_valign: gtk::Align::End,
                     set_margin_all: 10,
+                    set_spacing: 5,
+                    gtk::Button {
+                        set_icon_name: "go-jump-symbolic",
+                        set_tooltip_text: Some("Run to Cursor"),
+                        #[watch]
+                        set_visible: !model.dirty,
+                        #[watch]
+                        set_sensitive: model.can_save,
+                        connect_clicked => AsmViewMsg::RunToCursor,
+                    },
                     gtk::Button {
                         set_icon_name: icon_name::FLOPPY,
                         set_tooltip_text: Some("Save"),
@@ -67,8 +126,10 @@ impl SimpleComponent for AsmView {
             },
             gtk::ScrolledWindow {
                 set_width_request: 500,
-                sourceview5::View {
+                #[local_ref]
+                assembled_view -> sourceview5::View {
                     set_show_line_numbers: true,
+                    set_show_line_marks: true,
                     set_margin_all: 5,
                     set_vexpand: true,
                     set_editable: false,
@@ -85,17 +146,8 @@ impl SimpleComponent for AsmView {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let tag_table = gtk::TextTagTable::new();
-        tag_table.add(
-            &gtk::TextTag::builder()
-                .name("line_highlight")
-                .paragraph_background("yellow")
-                .foreground("black")
-                .build(),
-        );
-
-        let asm_buffer = sourceview5::Buffer::new(None);
-        let assembled_buffer = sourceview5::Buffer::new(Some(&tag_table));
+        let asm_buffer = sourceview5::Buffer::new(Some(&highlight_tags()));
+        let assembled_buffer = sourceview5::Buffer::new(Some(&highlight_tags()));
 
         asm_buffer.set_highlight_syntax(true);
 
@@ -110,39 +162,146 @@ impl SimpleComponent for AsmView {
             sender.input(AsmViewMsg::SetDirty(val.is_modified()));
         }));
 
+        let condition_entry = gtk::Entry::builder()
+            .placeholder_text("Condition, e.g. $t0 == 10")
+            .width_chars(24)
+            .build();
+        condition_entry.connect_activate(clone!(@strong sender => move |entry| {
+            sender.input(AsmViewMsg::ConditionEntered(entry.text().to_string()));
+        }));
+        let condition_popover = gtk::Popover::builder()
+            .child(&condition_entry)
+            .position(gtk::PositionType::Right)
+            .build();
+
         let mut model = Self {
             asm_buffer,
             assembled_buffer,
-            curr_line: 0,
+            source_view: sourceview5::View::new(),
+            assembled_view: sourceview5::View::new(),
+            pc: INST_MEM_START,
+            source_map: SourceMap::default(),
+            condition_popover,
+            condition_entry,
+            condition_line: 0,
+            breakpoints: Vec::new(),
             dirty: false,
             can_save: true,
         };
 
         model.set_theme_dark(adw::StyleManager::default().is_dark());
 
+        let source_view = &model.source_view;
+        let assembled_view = &model.assembled_view;
         let widgets = view_output!();
+        HEAT_COLORS.iter().enumerate().for_each(|(level, color)| {
+            let attributes = sourceview5::MarkAttributes::new();
+            if let Some(pixbuf) =
+                gdk_pixbuf::Pixbuf::new(gdk_pixbuf::Colorspace::Rgb, true, 8, 8, 16)
+            {
+                pixbuf.fill(*color);
+                attributes.set_pixbuf(&pixbuf);
+            }
+            model
+                .source_view
+                .set_mark_attributes(&heat_category(level), &attributes, level as i32);
+            model.assembled_view.set_mark_attributes(
+                &heat_category(level),
+                &attributes,
+                level as i32,
+            );
+        });
+        [BREAKPOINT, CONDITIONAL_BREAKPOINT]
+            .iter()
+            .for_each(|(category, color)| {
+                let attributes = sourceview5::MarkAttributes::new();
+                attributes.set_pixbuf(&dot(*color));
+                model.source_view.set_mark_attributes(
+                    category,
+                    &attributes,
+                    HEAT_COLORS.len() as i32,
+                );
+            });
+        model.condition_popover.set_parent(&model.source_view);
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            AsmViewMsg::LoadFile(asm, binary) => {
+            AsmViewMsg::LoadFile(asm, binary, source_map) => {
+                self.source_map = source_map;
                 self.asm_buffer.set_text(&asm);
                 self.asm_buffer.set_modified(false);
-                if let Some(bin) = binary
+                let words = binary
                     .iter()
-                    .map(|e| format!("{e:08x}\n"))
-                    .reduce(|acc, e| acc + &e)
-                {
-                    self.assembled_buffer.set_text(bin.trim());
-                }
+                    .enumerate()
+                    .map(|(idx, word)| {
+                        annotate(&self.source_map, INST_MEM_START + idx as u32 * 4, *word)
+                    })
+                    .collect::<Vec<_>>();
+                self.assembled_buffer.set_text(&words.join("\n"));
             }
             AsmViewMsg::SetLine(pc) => {
-                self.curr_line = (pc - 0x00400000) >> 2;
-                self.highlight_assembly();
+                self.pc = pc;
+                self.highlight_line();
+            }
+            AsmViewMsg::SetProfile(profile) => self.show_heat(&profile),
+            AsmViewMsg::SetBreakpoints(breakpoints) => {
+                self.breakpoints = breakpoints;
+                self.show_breakpoints();
+            }
+            AsmViewMsg::EditCondition(line) => {
+                self.condition_line = line;
+                self.condition_entry.set_text(
+                    self.breakpoints
+                        .iter()
+                        .find(|(bp_line, _)| *bp_line == line)
+                        .and_then(|(_, condition)| condition.as_deref())
+                        .unwrap_or_default(),
+                );
+                self.condition_popover.popup();
+                self.condition_entry.grab_focus();
+            }
+            AsmViewMsg::ConditionEntered(condition) => {
+                self.condition_popover.popdown();
+                sender
+                    .output(AsmViewOutput::SetBreakpointCondition(
+                        self.condition_line,
+                        condition,
+                    ))
+                    .unwrap();
+            }
+            AsmViewMsg::RunToCursor => {
+                let cursor = self.asm_buffer.iter_at_mark(&self.asm_buffer.get_insert());
+                sender
+                    .output(AsmViewOutput::RunToCursor(cursor.line() as usize))
+                    .unwrap();
+            }
+            AsmViewMsg::ShowLine(line) => {
+                if let Some(iter) = self.asm_buffer.iter_at_line(line as i32) {
+                    self.asm_buffer.place_cursor(&iter);
+                    self.source_view
+                        .scroll_mark_onscreen(&self.asm_buffer.get_insert());
+                    self.source_view.grab_focus();
+                }
+            }
+            AsmViewMsg::ShowAddress(addr) => {
+                if let Some(start) = addr
+                    .checked_sub(INST_MEM_START)
+                    .and_then(|offset| self.assembled_buffer.iter_at_line((offset / 4) as i32))
+                {
+                    let mut end = start;
+                    end.forward_to_line_end();
+                    self.assembled_buffer.select_range(&start, &end);
+                    self.assembled_view
+                        .scroll_to_iter(&mut start.clone(), 0.0, true, 0.0, 0.5);
+                }
             }
             AsmViewMsg::UpdateTheme => self.set_theme_dark(adw::StyleManager::default().is_dark()),
-            AsmViewMsg::SetDirty(dirty) => self.dirty = dirty,
+            AsmViewMsg::SetDirty(dirty) => {
+                self.dirty = dirty;
+                self.highlight_line();
+            }
             AsmViewMsg::SetCanSave(can_save) => self.can_save = can_save,
             AsmViewMsg::SaveFile => sender
                 .output(AsmViewOutput::SaveFile(
@@ -168,17 +327,181 @@ impl AsmView {
         }
     }
 
-    fn highlight_assembly(&mut self) {
-        self.assembled_buffer.remove_all_tags(
-            &self.assembled_buffer.start_iter(),
-            &self.assembled_buffer.end_iter(),
+    /// Highlights the source line of the instruction at the PC, with every
+    /// word it assembled to, and scrolls both into view. The source is left
+    /// alone while it has edits the map does not know about.
+    fn highlight_line(&self) {
+        let source_line = self.source_map.line(self.pc);
+        let words = match source_line {
+            Some(line) => self
+                .source_map
+                .addresses(line)
+                .map(|addr| ((addr - INST_MEM_START) / 4) as usize)
+                .collect(),
+            None => self
+                .pc
+                .checked_sub(INST_MEM_START)
+                .map(|offset| vec![(offset / 4) as usize])
+                .unwrap_or_default(),
+        };
+
+        highlight_lines(&self.assembled_view, &words);
+        match source_line {
+            Some(line) if !self.dirty => highlight_lines(&self.source_view, &[line]),
+            _ => highlight_lines(&self.source_view, &[]),
+        }
+    }
+
+    /// Marks every executed line in the gutter, coloured by how often it ran
+    /// relative to the hottest line.
+    fn show_heat(&self, profile: &Profile) {
+        let instructions = profile
+            .pcs()
+            .filter_map(|(pc, count)| Some(((pc.checked_sub(INST_MEM_START)? / 4) as usize, count)))
+            .collect::<Vec<_>>();
+        let mut source_lines = std::collections::BTreeMap::<usize, u64>::new();
+        profile.pcs().for_each(|(pc, count)| {
+            if let Some(line) = self.source_map.line(pc) {
+                *source_lines.entry(line).or_default() += count;
+            }
+        });
+
+        mark_heat(&self.assembled_buffer, &instructions);
+        mark_heat(
+            &self.asm_buffer,
+            &source_lines.into_iter().collect::<Vec<_>>(),
         );
+    }
+
+    /// Replaces the breakpoint marks with the breakpoints last set.
+    fn show_breakpoints(&self) {
+        [BREAKPOINT, CONDITIONAL_BREAKPOINT]
+            .iter()
+            .for_each(|(category, _)| {
+                self.asm_buffer.remove_source_marks(
+                    &self.asm_buffer.start_iter(),
+                    &self.asm_buffer.end_iter(),
+                    Some(category),
+                )
+            });
+        self.breakpoints.iter().for_each(|(line, condition)| {
+            let category = match condition {
+                Some(_) => CONDITIONAL_BREAKPOINT.0,
+                None => BREAKPOINT.0,
+            };
+            if let Some(iter) = self.asm_buffer.iter_at_line(*line as i32) {
+                self.asm_buffer.create_source_mark(None, category, &iter);
+            }
+        });
+    }
+}
+
+/// A filled circle of `color`, given as 0xRRGGBBAA, for breakpoint marks.
+fn dot(color: u32) -> gdk_pixbuf::Pixbuf {
+    const SIZE: usize = 16;
+    let radius = SIZE as f64 / 2.0 - 2.0;
+    let center = SIZE as f64 / 2.0 - 0.5;
+    let pixels = (0..SIZE * SIZE)
+        .flat_map(|idx| {
+            let (x, y) = ((idx % SIZE) as f64, (idx / SIZE) as f64);
+            let inside = (x - center).hypot(y - center) <= radius;
+            let rgba = color.to_be_bytes();
+            [rgba[0], rgba[1], rgba[2], if inside { rgba[3] } else { 0 }]
+        })
+        .collect::<Vec<_>>();
+    gdk_pixbuf::Pixbuf::from_bytes(
+        &gdk_pixbuf::glib::Bytes::from_owned(pixels),
+        gdk_pixbuf::Colorspace::Rgb,
+        true,
+        8,
+        SIZE as i32,
+        SIZE as i32,
+        SIZE as i32 * 4,
+    )
+}
 
-        if let Some(start) = self.assembled_buffer.iter_at_line(self.curr_line as i32) {
+/// A word of machine code with the labels naming its address and, for a
+/// branch or jump, where it goes.
+fn annotate(source_map: &SourceMap, addr: u32, word: u32) -> String {
+    let mut line = format!("{word:08x}");
+    if let Some(label) = source_map.label(addr) {
+        line.push_str(&format!("  {label}:"));
+    }
+    if let Some(target) = jump_target(word, addr) {
+        match source_map.label(target) {
+            Some(label) => line.push_str(&format!("  -> {label}")),
+            None => line.push_str(&format!("  -> 0x{target:08x}")),
+        }
+    }
+    line
+}
+
+/// Replaces the highlighted lines of `view`, scrolling to the first of them
+/// if it is off screen.
+fn highlight_lines(view: &sourceview5::View, lines: &[usize]) {
+    let buffer = view.buffer();
+    buffer.remove_tag_by_name("line_highlight", &buffer.start_iter(), &buffer.end_iter());
+
+    lines.iter().for_each(|line| {
+        if let Some(start) = buffer.iter_at_line(*line as i32) {
             let mut end = start;
             end.forward_to_line_end();
-            self.assembled_buffer
-                .apply_tag_by_name("line_highlight", &start, &end);
+            buffer.apply_tag_by_name("line_highlight", &start, &end);
         }
+    });
+
+    if let Some(start) = lines
+        .first()
+        .and_then(|line| buffer.iter_at_line(*line as i32))
+    {
+        let mark = match buffer.mark("current-line") {
+            Some(mark) => {
+                buffer.move_mark(&mark, &start);
+                mark
+            }
+            None => buffer.create_mark(Some("current-line"), &start, true),
+        };
+        view.scroll_mark_onscreen(&mark);
     }
 }
+
+/// A tag table with the tag marking the current line. Each buffer gets its
+/// own since sourceview adds tags of its own to it.
+fn highlight_tags() -> gtk::TextTagTable {
+    let tag_table = gtk::TextTagTable::new();
+    tag_table.add(
+        &gtk::TextTag::builder()
+            .name("line_highlight")
+            .paragraph_background("yellow")
+            .foreground("black")
+            .build(),
+    );
+    tag_table
+}
+
+fn heat_category(level: usize) -> String {
+    format!("heat{level}")
+}
+
+/// Replaces the heat marks of `buffer` with one per `(line, count)`.
+fn mark_heat(buffer: &sourceview5::Buffer, lines: &[(usize, u64)]) {
+    (0..HEAT_COLORS.len()).for_each(|level| {
+        buffer.remove_source_marks(
+            &buffer.start_iter(),
+            &buffer.end_iter(),
+            Some(&heat_category(level)),
+        )
+    });
+
+    let Some(hottest) = lines.iter().map(|(_, count)| *count).max() else {
+        return;
+    };
+    lines.iter().for_each(|(line, count)| {
+        let level = ((count * HEAT_COLORS.len() as u64).div_ceil(hottest) as usize)
+            .clamp(1, HEAT_COLORS.len())
+            - 1;
+        if let Some(iter) = buffer.iter_at_line(*line as i32) {
+            buffer.create_source_mark(None, &heat_category(level), &iter);
+        }
+    });
+}
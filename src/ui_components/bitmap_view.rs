@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::devices::bitmap_display::{BitmapConfig, BitmapDisplay};
+
+use super::CPUViewMessage;
+use gtk::cairo;
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+/// Copy of the framebuffer the draw function paints from, one 0x00RRGGBB
+/// word per unit.
+#[derive(Debug, Default)]
+struct Frame {
+    config: Option<BitmapConfig>,
+    pixels: Vec<u32>,
+}
+
+impl Frame {
+    fn draw(&self, cr: &cairo::Context) {
+        let Some(config) = self.config else {
+            return;
+        };
+        let (width, height) = (config.units_wide() as i32, config.units_high() as i32);
+
+        let data = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let Ok(surface) = cairo::ImageSurface::create_for_data(
+            data,
+            cairo::Format::Rgb24,
+            width,
+            height,
+            width * 4,
+        ) else {
+            return;
+        };
+
+        cr.scale(config.unit_size as f64, config.unit_size as f64);
+        if cr.set_source_surface(&surface, 0.0, 0.0).is_ok() {
+            cr.source().set_filter(cairo::Filter::Nearest);
+            let _ = cr.paint();
+        }
+    }
+}
+
+pub struct BitmapView {
+    frame: Rc<RefCell<Frame>>,
+    drawing_area: gtk::DrawingArea,
+    has_display: bool,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for BitmapView {
+    type Input = CPUViewMessage;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Stack {
+            add_named[Some("empty")] = &adw::StatusPage {
+                set_icon_name: Some("image-x-generic-symbolic"),
+                set_title: "No Bitmap Display",
+                set_description: Some("Enable the bitmap display in Preferences"),
+            },
+            add_named[Some("display")] = &gtk::ScrolledWindow {
+                set_hexpand: true,
+                set_vexpand: true,
+                #[local_ref]
+                drawing_area -> gtk::DrawingArea {
+                    set_halign: gtk::Align::Center,
+                    set_valign: gtk::Align::Center,
+                    set_margin_all: 10,
+                },
+            },
+            #[watch]
+            set_visible_child_name: if model.has_display { "display" } else { "empty" },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = BitmapView {
+            frame: Rc::new(RefCell::new(Frame::default())),
+            drawing_area: gtk::DrawingArea::new(),
+            has_display: false,
+        };
+
+        let frame = model.frame.clone();
+        model
+            .drawing_area
+            .set_draw_func(move |_, cr, _, _| frame.borrow().draw(cr));
+
+        let drawing_area = &model.drawing_area;
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            CPUViewMessage::Update(cpu) => {
+                let display = cpu
+                    .get_devices()
+                    .iter()
+                    .find_map(|device| device.as_any().downcast_ref::<BitmapDisplay>());
+                self.has_display = display.is_some();
+
+                let mut frame = self.frame.borrow_mut();
+                frame.config = display.map(|display| display.config());
+                frame.pixels = display
+                    .map(|display| {
+                        let config = display.config();
+                        (0..config.units_high())
+                            .flat_map(|y| (0..config.units_wide()).map(move |x| (x, y)))
+                            .map(|(x, y)| {
+                                cpu.get_memory_word(config.unit_addr(x, y))
+                                    .unwrap_or_default()
+                                    & 0x00FFFFFF
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                if let Some(config) = frame.config {
+                    self.drawing_area
+                        .set_content_width((config.units_wide() * config.unit_size) as i32);
+                    self.drawing_area
+                        .set_content_height((config.units_high() * config.unit_size) as i32);
+                }
+                self.drawing_area.queue_draw();
+            }
+            CPUViewMessage::Previous(_) => {}
+            CPUViewMessage::DirtyMemory(_) => {}
+            CPUViewMessage::ChangeRadix(_) => {}
+            CPUViewMessage::Resize(_) => {}
+            CPUViewMessage::SelectCore(_) => {}
+            CPUViewMessage::SetRegister(_, _) => {}
+            CPUViewMessage::SetMemory(_, _) => {}
+            CPUViewMessage::SetLabels(_) => {}
+            CPUViewMessage::ShowAddress(_) => {}
+            CPUViewMessage::None => {}
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::cpu::watchpoints::Trigger;
+
+use adw::prelude::*;
+use relm4::prelude::*;
+
+/// Choices of the "Stop When" row, in order.
+const TRIGGERS: [(&str, Trigger); 4] = [
+    ("Written", Trigger::Write),
+    ("Changed", Trigger::Change(None)),
+    ("Read", Trigger::Read),
+    ("Read or written", Trigger::Access),
+];
+
+#[derive(Debug)]
+pub enum BreakpointViewMsg {
+    /// Breakpoints by line, with the source text and condition of the line.
+    SetBreakpoints(Vec<(usize, String, Option<String>)>),
+    SetWatchpoints(Vec<String>),
+}
+
+#[derive(Debug)]
+pub enum BreakpointViewOutput {
+    RemoveBreakpoint(usize),
+    /// Register or address, bytes watched, trigger and new value condition.
+    AddWatchpoint(String, u32, Trigger, String),
+    RemoveWatchpoint(usize),
+}
+
+/// Lists the breakpoints set in the editor and the watchpoints, with a form
+/// to add watchpoints.
+pub struct BreakpointView {
+    breakpoints: gtk::ListBox,
+    watchpoints: gtk::ListBox,
+}
+
+impl BreakpointView {
+    fn list(placeholder: &str) -> gtk::ListBox {
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        list.set_placeholder(Some(
+            &gtk::Label::builder()
+                .label(placeholder)
+                .css_classes(["dim-label"])
+                .margin_top(10)
+                .margin_bottom(10)
+                .build(),
+        ));
+        list
+    }
+
+    /// Replaces the rows of `list`, each with a button sending `remove`.
+    fn fill(
+        list: &gtk::ListBox,
+        rows: impl Iterator<Item = (String, String)>,
+        remove: impl Fn(usize) -> BreakpointViewOutput + Clone + 'static,
+        sender: &ComponentSender<Self>,
+    ) {
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        rows.enumerate().for_each(|(idx, (title, subtitle))| {
+            let row = adw::ActionRow::builder()
+                .title(escape(&title))
+                .subtitle(escape(&subtitle))
+                .build();
+            let button = gtk::Button::builder()
+                .icon_name("user-trash-symbolic")
+                .tooltip_text("Remove")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            let sender = sender.clone();
+            let remove = remove.clone();
+            button.connect_clicked(move |_| sender.output(remove(idx)).unwrap());
+            row.add_suffix(&button);
+            list.append(&row);
+        });
+    }
+}
+
+/// Rows use markup, source text can contain `<` and `&`.
+fn escape(text: &str) -> String {
+    gtk::glib::markup_escape_text(text).to_string()
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for BreakpointView {
+    type Input = BreakpointViewMsg;
+    type Output = BreakpointViewOutput;
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_hexpand: true,
+            set_vexpand: true,
+            adw::Clamp {
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 20,
+                    set_margin_all: 10,
+                    adw::PreferencesGroup {
+                        set_title: "Breakpoints",
+                        set_description: Some("Click the editor's gutter to set one, right click it to add a condition"),
+                        #[local_ref]
+                        breakpoints -> gtk::ListBox {},
+                    },
+                    adw::PreferencesGroup {
+                        set_title: "Watchpoints",
+                        set_description: Some("Stop a running program when memory or a register is accessed"),
+                        #[local_ref]
+                        watchpoints -> gtk::ListBox {},
+                    },
+                    adw::PreferencesGroup {
+                        set_title: "Add Watchpoint",
+                        #[wrap(Some)]
+                        set_header_suffix = &gtk::Button {
+                            set_label: "Add",
+                            add_css_class: "suggested-action",
+                            connect_clicked[sender, target, bytes, trigger, condition] => move |_| {
+                                let (_, trigger) = TRIGGERS[(trigger.selected() as usize).min(TRIGGERS.len() - 1)];
+                                sender.output(BreakpointViewOutput::AddWatchpoint(
+                                    target.text().to_string(),
+                                    bytes.value() as u32,
+                                    trigger,
+                                    condition.text().to_string(),
+                                )).unwrap();
+                            },
+                        },
+                        #[name = "target"]
+                        adw::EntryRow {
+                            set_title: "Register or Address",
+                        },
+                        adw::ActionRow {
+                            set_title: "Bytes",
+                            set_subtitle: "Watched from the address, registers ignore this",
+                            #[name = "bytes"]
+                            add_suffix = &gtk::SpinButton::with_range(1.0, 4096.0, 1.0) {
+                                set_valign: gtk::Align::Center,
+                                set_value: 4.0,
+                            },
+                        },
+                        #[name = "trigger"]
+                        adw::ComboRow {
+                            set_title: "Stop When",
+                            set_model: Some(&gtk::StringList::new(&TRIGGERS.map(|(name, _)| name))),
+                        },
+                        #[name = "condition"]
+                        adw::EntryRow {
+                            set_title: "New Value (e.g. == 0, empty for any)",
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = BreakpointView {
+            breakpoints: Self::list("No breakpoints"),
+            watchpoints: Self::list("No watchpoints"),
+        };
+        let breakpoints = &model.breakpoints;
+        let watchpoints = &model.watchpoints;
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            BreakpointViewMsg::SetBreakpoints(breakpoints) => {
+                let lines = breakpoints
+                    .iter()
+                    .map(|(line, _, _)| *line)
+                    .collect::<Vec<_>>();
+                Self::fill(
+                    &self.breakpoints,
+                    breakpoints.into_iter().map(|(line, text, condition)| {
+                        (
+                            format!("Line {}: {}", line + 1, text.trim()),
+                            condition.unwrap_or_else(|| String::from("Always stops")),
+                        )
+                    }),
+                    move |idx| BreakpointViewOutput::RemoveBreakpoint(lines[idx]),
+                    &sender,
+                );
+            }
+            BreakpointViewMsg::SetWatchpoints(watchpoints) => Self::fill(
+                &self.watchpoints,
+                watchpoints
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, watchpoint)| (format!("Watchpoint {}", idx + 1), watchpoint)),
+                BreakpointViewOutput::RemoveWatchpoint,
+                &sender,
+            ),
+        }
+    }
+}
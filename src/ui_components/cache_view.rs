@@ -0,0 +1,258 @@
+use crate::cpu::cache::{Cache, CacheConfig, ReplacementPolicy, WritePolicy};
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::instruction_memory::InstructionMem;
+use crate::cpu::multicore::MulticoreCPU;
+use crate::cpu::DATA_MEM_START;
+
+use super::CPUViewMessage;
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+const COLUMN_NAMES: [&str; 6] = ["Set", "Way", "Valid", "Dirty", "Tag", "Data"];
+
+/// Sets and ways of one cache laid out as a grid. The grid is rebuilt only
+/// when the cache geometry changes, otherwise labels are updated in place.
+struct CacheGrid {
+    root: gtk::Box,
+    summary: gtk::Label,
+    grid: gtk::Grid,
+    rows: Vec<[gtk::Label; 6]>,
+    config: Option<CacheConfig>,
+    highlighted: Option<usize>,
+}
+
+impl CacheGrid {
+    fn new(title: &str) -> Self {
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let heading = gtk::Label::builder()
+            .label(title)
+            .xalign(0.0)
+            .css_classes(["title-4"])
+            .build();
+        let summary = gtk::Label::builder().xalign(0.0).selectable(true).build();
+        let grid = gtk::Grid::builder()
+            .row_spacing(2)
+            .column_spacing(15)
+            .build();
+
+        root.append(&heading);
+        root.append(&summary);
+        root.append(&grid);
+
+        Self {
+            root,
+            summary,
+            grid,
+            rows: Vec::new(),
+            config: None,
+            highlighted: None,
+        }
+    }
+
+    /// Shows `cache`, reading the blocks its lines hold with `read_byte`.
+    fn update(&mut self, cache: Option<&Cache>, read_byte: impl Fn(u32) -> u8) {
+        let Some(cache) = cache else {
+            self.root.set_visible(false);
+            return;
+        };
+        self.root.set_visible(true);
+
+        let config = cache.config();
+        if self.config != Some(config) {
+            self.rebuild(config);
+        }
+
+        self.summary
+            .set_label(&format!("{}\n{}", describe(config), cache.stats()));
+
+        let ways = config.ways() as usize;
+        cache.sets().enumerate().for_each(|(set, lines)| {
+            lines.iter().enumerate().for_each(|(way, line)| {
+                let labels = &self.rows[set * ways + way];
+                let block = cache.block_addr(set, line);
+                labels[2].set_label(if line.valid { "1" } else { "0" });
+                labels[3].set_label(if line.dirty { "1" } else { "0" });
+                labels[4].set_label(&format!("0x{:x}", line.tag));
+                labels[5].set_label(&if line.valid {
+                    (0..config.block_size)
+                        .step_by(4)
+                        .map(|word| {
+                            (word..(word + 4).min(config.block_size))
+                                .map(|offset| format!("{:02x}", read_byte(block + offset)))
+                                .collect::<String>()
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                } else {
+                    String::new()
+                });
+            })
+        });
+
+        if let Some(row) = self.highlighted.take() {
+            self.rows[row].iter().for_each(|label| {
+                label.remove_css_class("success");
+                label.remove_css_class("error");
+                label.remove_css_class("heading");
+            });
+        }
+        if let Some(access) = cache.last_access() {
+            if let Some(way) = access.way {
+                let row = access.set * ways + way;
+                let class = if access.miss.is_none() {
+                    "success"
+                } else {
+                    "error"
+                };
+                self.rows[row].iter().for_each(|label| {
+                    label.add_css_class(class);
+                    label.add_css_class("heading");
+                });
+                self.highlighted = Some(row);
+            }
+        }
+    }
+
+    fn rebuild(&mut self, config: CacheConfig) {
+        while let Some(child) = self.grid.first_child() {
+            self.grid.remove(&child);
+        }
+
+        COLUMN_NAMES.iter().enumerate().for_each(|(column, name)| {
+            let label = gtk::Label::builder()
+                .label(*name)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build();
+            self.grid.attach(&label, column as i32, 0, 1, 1);
+        });
+
+        self.rows = (0..config.sets())
+            .flat_map(|set| (0..config.ways()).map(move |way| (set, way)))
+            .enumerate()
+            .map(|(row, (set, way))| {
+                let labels: [gtk::Label; 6] = std::array::from_fn(|_| {
+                    gtk::Label::builder()
+                        .xalign(0.0)
+                        .css_classes(["monospace"])
+                        .build()
+                });
+                labels[0].set_label(&set.to_string());
+                labels[1].set_label(&way.to_string());
+                labels.iter().enumerate().for_each(|(column, label)| {
+                    self.grid.attach(label, column as i32, row as i32 + 1, 1, 1);
+                });
+                labels
+            })
+            .collect();
+
+        self.config = Some(config);
+        self.highlighted = None;
+    }
+}
+
+/// The byte at physical `addr`, from instruction memory when text is not in
+/// data memory.
+fn memory_byte(cpu: &MulticoreCPU, addr: u32) -> u8 {
+    cpu.get_memory_byte(addr.wrapping_sub(DATA_MEM_START))
+        .or_else(|| {
+            cpu.instruction_memory()
+                .get_instruction(addr & !3)
+                .map(|inst| inst.to_le_bytes()[(addr & 3) as usize])
+        })
+        .unwrap_or_default()
+}
+
+fn describe(config: CacheConfig) -> String {
+    format!(
+        "{} B, {} B blocks, {} sets of {} ways, {} replacement, {}",
+        config.size,
+        config.block_size,
+        config.sets(),
+        config.ways(),
+        match config.replacement {
+            ReplacementPolicy::Lru => "LRU",
+            ReplacementPolicy::Fifo => "FIFO",
+            ReplacementPolicy::Random => "random",
+        },
+        match config.write_policy {
+            WritePolicy::WriteBack => "write-back",
+            WritePolicy::WriteThrough => "write-through",
+        }
+    )
+}
+
+pub struct CacheView {
+    data_cache: CacheGrid,
+    instruction_cache: CacheGrid,
+    has_cache: bool,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for CacheView {
+    type Input = CPUViewMessage;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Stack {
+            add_named[Some("empty")] = &adw::StatusPage {
+                set_icon_name: Some("view-grid-symbolic"),
+                set_title: "No Cache Configured",
+                set_description: Some("Enable a data or instruction cache in Preferences"),
+            },
+            add_named[Some("caches")] = &gtk::ScrolledWindow {
+                set_hexpand: true,
+                set_vexpand: true,
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 20,
+                    set_margin_all: 10,
+                    append: &model.data_cache.root,
+                    append: &model.instruction_cache.root,
+                },
+            },
+            #[watch]
+            set_visible_child_name: if model.has_cache { "caches" } else { "empty" },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = CacheView {
+            data_cache: CacheGrid::new("Data Cache"),
+            instruction_cache: CacheGrid::new("Instruction Cache"),
+            has_cache: false,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            CPUViewMessage::Update(cpu) => {
+                self.has_cache =
+                    cpu.get_data_cache().is_some() || cpu.get_instruction_cache().is_some();
+                self.data_cache
+                    .update(cpu.get_data_cache(), |addr| memory_byte(&cpu, addr));
+                self.instruction_cache
+                    .update(cpu.get_instruction_cache(), |addr| memory_byte(&cpu, addr));
+            }
+            CPUViewMessage::Previous(_) => {}
+            CPUViewMessage::DirtyMemory(_) => {}
+            CPUViewMessage::ChangeRadix(_) => {}
+            CPUViewMessage::Resize(_) => {}
+            CPUViewMessage::SelectCore(_) => {}
+            CPUViewMessage::SetRegister(_, _) => {}
+            CPUViewMessage::SetMemory(_, _) => {}
+            CPUViewMessage::SetLabels(_) => {}
+            CPUViewMessage::ShowAddress(_) => {}
+            CPUViewMessage::None => {}
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::multicore::MulticoreCPU;
+use crate::source_map::SourceMap;
+
+use adw::prelude::*;
+use relm4::prelude::*;
+
+#[derive(Debug)]
+pub enum CallStackMsg {
+    Update(Arc<MulticoreCPU>),
+    LoadSource(SourceMap),
+}
+
+#[derive(Debug)]
+pub enum CallStackOutput {
+    /// Shows the source line in the editor.
+    ShowLine(usize),
+}
+
+/// The calls the current core is inside of, innermost first, and the
+/// returns that went somewhere other than where their call expected.
+pub struct CallStackView {
+    source_map: SourceMap,
+    frames: gtk::ListBox,
+    mismatches: gtk::ListBox,
+    has_mismatches: bool,
+    /// How many of the mismatched returns taken are listed.
+    mismatch_summary: String,
+}
+
+impl CallStackView {
+    fn list(placeholder: &str) -> gtk::ListBox {
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        list.set_placeholder(Some(
+            &gtk::Label::builder()
+                .label(placeholder)
+                .css_classes(["dim-label"])
+                .margin_top(10)
+                .margin_bottom(10)
+                .build(),
+        ));
+        list
+    }
+
+    /// The label naming `addr` with the address, or the address alone.
+    fn name(&self, addr: u32) -> String {
+        match self.source_map.label(addr) {
+            Some(label) => format!("{label} (0x{addr:08x})"),
+            None => format!("0x{addr:08x}"),
+        }
+    }
+
+    fn location(&self, addr: u32) -> String {
+        match self.source_map.line(addr) {
+            Some(line) => format!("line {} (0x{addr:08x})", line + 1),
+            None => format!("0x{addr:08x}"),
+        }
+    }
+
+    /// Replaces the rows of `list`. Rows of instructions with a source line
+    /// jump to it when activated.
+    fn fill(
+        &self,
+        list: &gtk::ListBox,
+        rows: Vec<(String, String, u32)>,
+        icon: Option<&str>,
+        sender: &ComponentSender<Self>,
+    ) {
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        rows.into_iter().for_each(|(title, subtitle, pc)| {
+            let row = adw::ActionRow::builder()
+                .title(title)
+                .subtitle(subtitle)
+                .build();
+            if let Some(icon) = icon {
+                row.add_prefix(&gtk::Image::from_icon_name(icon));
+            }
+            if let Some(line) = self.source_map.line(pc) {
+                row.set_activatable(true);
+                row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+                let sender = sender.clone();
+                row.connect_activated(move |_| {
+                    sender.output(CallStackOutput::ShowLine(line)).unwrap()
+                });
+            }
+            list.append(&row);
+        });
+    }
+
+    fn show(&mut self, cpu: &MulticoreCPU, sender: &ComponentSender<Self>) {
+        let call_stack = cpu.get_call_stack();
+        let frames = call_stack
+            .frames()
+            .enumerate()
+            .map(|(idx, frame)| {
+                (
+                    format!("#{idx} {}", self.name(frame.target)),
+                    format!(
+                        "Called from {}, $sp 0x{:08x}",
+                        self.location(frame.call_site),
+                        frame.sp
+                    ),
+                    frame.call_site,
+                )
+            })
+            .collect();
+        self.fill(&self.frames, frames, None, sender);
+
+        let mismatches = call_stack
+            .mismatches()
+            .iter()
+            .rev()
+            .map(|mismatch| {
+                (
+                    format!(
+                        "Return at {} went to 0x{:08x}",
+                        self.location(mismatch.pc),
+                        mismatch.target
+                    ),
+                    format!(
+                        "Expected 0x{:08x} for the call to {} from {}, check that $ra was saved",
+                        mismatch.frame.return_address(),
+                        self.name(mismatch.frame.target),
+                        self.location(mismatch.frame.call_site)
+                    ),
+                    mismatch.pc,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.has_mismatches = !mismatches.is_empty();
+        self.mismatch_summary = match call_stack.mismatch_count() {
+            count if count > mismatches.len() => {
+                format!("The last {} of {count} returns through $ra that did not go back after their call", mismatches.len())
+            }
+            _ => String::from("Returns through $ra that did not go back after their call"),
+        };
+        self.fill(
+            &self.mismatches,
+            mismatches,
+            Some("dialog-warning-symbolic"),
+            sender,
+        );
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for CallStackView {
+    type Input = CallStackMsg;
+    type Output = CallStackOutput;
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_hexpand: true,
+            set_vexpand: true,
+            adw::Clamp {
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 20,
+                    set_margin_all: 10,
+                    adw::PreferencesGroup {
+                        set_title: "Call Stack",
+                        set_description: Some("Calls made with jal or jalr that have not returned, click one to show its call site"),
+                        #[local_ref]
+                        frames -> gtk::ListBox {},
+                    },
+                    adw::PreferencesGroup {
+                        set_title: "Mismatched Returns",
+                        #[watch]
+                        set_description: Some(&model.mismatch_summary),
+                        #[watch]
+                        set_visible: model.has_mismatches,
+                        #[local_ref]
+                        mismatches -> gtk::ListBox {},
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = CallStackView {
+            source_map: SourceMap::default(),
+            frames: Self::list("Not inside a call"),
+            mismatches: Self::list(""),
+            has_mismatches: false,
+            mismatch_summary: String::new(),
+        };
+        let frames = &model.frames;
+        let mismatches = &model.mismatches;
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            CallStackMsg::Update(cpu) => self.show(&cpu, &sender),
+            CallStackMsg::LoadSource(source_map) => self.source_map = source_map,
+        }
+    }
+}
@@ -1,15 +1,64 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::cpu::DATA_MEM_START;
 use crate::ui_components::column_views::Radices;
-use relm4::{gtk::traits::WidgetExt, prelude::*};
+use relm4::gtk::{glib, prelude::*};
+use relm4::prelude::*;
 use relm4::{
-    typed_view::column::{LabelColumn, TypedColumnView},
+    typed_view::column::{LabelColumn, RelmColumn, TypedColumnView},
     ComponentParts, ComponentSender, SimpleComponent,
 };
 
-use super::RadixedValue;
+use super::{connect_edited, RadixedValue};
+
+/// How memory is grouped into rows.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MemoryMode {
+    Bytes,
+    Halfwords,
+    Words,
+    /// Sixteen bytes a row in hex with their ASCII characters.
+    HexDump,
+}
+
+impl MemoryMode {
+    pub const ALL: [MemoryMode; 4] = [
+        MemoryMode::Bytes,
+        MemoryMode::Halfwords,
+        MemoryMode::Words,
+        MemoryMode::HexDump,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MemoryMode::Bytes => "Bytes",
+            MemoryMode::Halfwords => "Halfwords",
+            MemoryMode::Words => "Words",
+            MemoryMode::HexDump => "Hex Dump",
+        }
+    }
+
+    fn bytes_per_row(self) -> usize {
+        match self {
+            MemoryMode::Bytes => 1,
+            MemoryMode::Halfwords => 2,
+            MemoryMode::Words => 4,
+            MemoryMode::HexDump => 16,
+        }
+    }
+}
 
 pub struct MemoryRow {
     addr: u32,
-    value: RadixedValue<u8>,
+    /// Little endian bytes of the row, as many as the mode groups.
+    bytes: Vec<u8>,
+    /// The bytes before the last step, when the step changed them.
+    old: Option<Vec<u8>>,
+    radix: Radices,
+    mode: MemoryMode,
+    /// Data labels pointing into the row.
+    labels: String,
+    sender: ComponentSender<MemoryView>,
 }
 
 pub struct AddressColumn;
@@ -32,85 +81,338 @@ impl LabelColumn for AddressColumn {
     }
 }
 
-pub struct MemoryColumn;
+pub struct LabelsColumn;
 
-impl LabelColumn for MemoryColumn {
+impl LabelColumn for LabelsColumn {
     type Item = MemoryRow;
 
-    type Value = RadixedValue<u8>;
+    type Value = String;
 
-    const COLUMN_NAME: &'static str = "Memory Contents";
+    const COLUMN_NAME: &'static str = "Label";
 
     const ENABLE_SORT: bool = false;
 
     fn get_cell_value(item: &Self::Item) -> Self::Value {
-        item.value
+        item.labels.clone()
     }
+}
 
-    fn format_cell_value(value: &Self::Value) -> String {
-        match value.radix {
-            Radices::Binary => format!("0b{:08b}", value.value),
-            Radices::Hex => format!("0x{:02x}", value.value),
-            Radices::Decimal => format!("{:010}", value.value),
+fn format_row(row: &MemoryRow, bytes: &[u8]) -> String {
+    if row.mode == MemoryMode::HexDump {
+        return bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    let digits = bytes.len();
+    let value = RadixedValue {
+        radix: row.radix,
+        value: bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | *byte as u32),
+    };
+    match value.radix {
+        Radices::Binary => format!("0b{:0width$b}", value.value, width = digits * 8),
+        Radices::Hex => format!("0x{:0width$x}", value.value, width = digits * 2),
+        Radices::Decimal => format!("{:010}", value.value),
+    }
+}
+
+/// Values of the row, which can be edited in any radix to write them back
+/// except in a hex dump.
+pub struct MemoryColumn;
+
+impl RelmColumn for MemoryColumn {
+    type Root = gtk::EditableLabel;
+
+    type Widgets = Option<glib::SignalHandlerId>;
+
+    type Item = MemoryRow;
+
+    const COLUMN_NAME: &'static str = "Memory Contents";
+
+    fn setup(_: &gtk::ListItem) -> (Self::Root, Self::Widgets) {
+        (gtk::EditableLabel::new(""), None)
+    }
+
+    fn bind(item: &mut Self::Item, handler: &mut Self::Widgets, label: &mut Self::Root) {
+        let text = format_row(item, &item.bytes);
+        label.set_text(&text);
+        label.set_class_active("accent", item.old.is_some());
+        label.set_tooltip_text(
+            item.old
+                .as_ref()
+                .map(|old| format!("Was {}", format_row(item, old)))
+                .as_deref(),
+        );
+        label.set_editable(item.mode != MemoryMode::HexDump);
+        if item.mode == MemoryMode::HexDump {
+            return;
+        }
+        let addr = item.addr;
+        let width = item.bytes.len();
+        let sender = item.sender.clone();
+        *handler = Some(connect_edited(label, text, item.radix, move |value| {
+            let bits = width as u32 * 8;
+            let fits = (-(1 << (bits - 1))..1 << bits).contains(&value);
+            if fits {
+                let bytes = value.to_le_bytes()[..width].to_vec();
+                sender.input(MemoryMsg::Edit(addr, bytes));
+            }
+            fits
+        }));
+    }
+
+    fn unbind(_: &mut Self::Item, handler: &mut Self::Widgets, label: &mut Self::Root) {
+        if let Some(handler) = handler.take() {
+            label.disconnect(handler);
         }
     }
 }
 
+pub struct AsciiColumn;
+
+impl LabelColumn for AsciiColumn {
+    type Item = MemoryRow;
+
+    type Value = String;
+
+    const COLUMN_NAME: &'static str = "ASCII";
+
+    const ENABLE_SORT: bool = false;
+
+    fn get_cell_value(item: &Self::Item) -> Self::Value {
+        item.bytes
+            .iter()
+            .map(|byte| match byte {
+                0x20..=0x7e => *byte as char,
+                _ => '.',
+            })
+            .collect()
+    }
+}
+
 pub struct MemoryView {
-    view_wrapper: TypedColumnView<MemoryRow, gtk::NoSelection>,
+    view_wrapper: TypedColumnView<MemoryRow, gtk::SingleSelection>,
+    scrolled: gtk::ScrolledWindow,
+    /// Memory from the start of `.data`.
+    memory: Vec<u8>,
+    /// Offsets of the bytes the last step changed, with their old values.
+    changed: BTreeMap<usize, u8>,
+    labels: Vec<(String, u32)>,
     curr_radix: Radices,
+    mode: MemoryMode,
+    goto_failed: bool,
 }
 
 #[derive(Debug)]
 pub enum MemoryMsg {
-    UpdateMemory(Vec<u8>),
+    /// Memory from the start of `.data`, and what it held before the last
+    /// step if known.
+    UpdateMemory(Vec<u8>, Option<Vec<u8>>),
+    /// Bytes the last step wrote, each run from an offset into `.data`. Only
+    /// their rows and those highlighted before are redrawn.
+    Write(Vec<(u32, Vec<u8>)>),
     UpdateRadix(Radices),
+    SetMode(MemoryMode),
+    SetLabels(Vec<(String, u32)>),
+    /// Shows the row of an address or data label.
+    Goto(String),
+    /// Shows the row of an absolute address.
+    Show(u32),
+    Edit(u32, Vec<u8>),
+}
+
+#[derive(Debug)]
+pub enum MemoryOutput {
+    /// Bytes written from an offset into `.data`.
+    SetBytes(u32, Vec<u8>),
+}
+
+impl MemoryView {
+    fn row(&self, row: usize, sender: &ComponentSender<Self>) -> MemoryRow {
+        let per_row = self.mode.bytes_per_row();
+        let start = row * per_row;
+        let bytes = &self.memory[start..(start + per_row).min(self.memory.len())];
+        let addr = DATA_MEM_START + start as u32;
+        let old = self
+            .changed
+            .range(start..start + bytes.len())
+            .next()
+            .map(|_| {
+                (start..start + bytes.len())
+                    .map(|offset| {
+                        self.changed
+                            .get(&offset)
+                            .copied()
+                            .unwrap_or(self.memory[offset])
+                    })
+                    .collect()
+            });
+        let labels = self
+            .labels
+            .iter()
+            .filter(|(_, label_addr)| (addr..addr + per_row as u32).contains(label_addr))
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        MemoryRow {
+            addr,
+            bytes: bytes.to_vec(),
+            old,
+            radix: self.curr_radix,
+            mode: self.mode,
+            labels,
+            sender: sender.clone(),
+        }
+    }
+
+    fn rebuild(&mut self, sender: &ComponentSender<Self>) {
+        let rows = self.memory.len().div_ceil(self.mode.bytes_per_row());
+        self.view_wrapper.clear();
+        let items = (0..rows)
+            .map(|row| self.row(row, sender))
+            .collect::<Vec<_>>();
+        self.view_wrapper.extend_from_iter(items);
+        if let Some(column) = self
+            .view_wrapper
+            .get_columns()
+            .get(<AsciiColumn as LabelColumn>::COLUMN_NAME)
+        {
+            column.set_visible(self.mode == MemoryMode::HexDump);
+        }
+    }
+
+    /// Replaces the rows holding the changed bytes and the bytes written,
+    /// leaving every other row as it is.
+    fn write(&mut self, writes: Vec<(u32, Vec<u8>)>, sender: &ComponentSender<Self>) {
+        let per_row = self.mode.bytes_per_row();
+        let mut rows = std::mem::take(&mut self.changed)
+            .into_keys()
+            .map(|offset| offset / per_row)
+            .collect::<BTreeSet<_>>();
+        for (offset, bytes) in writes {
+            let start = offset as usize;
+            for (offset, byte) in (start..).zip(bytes) {
+                let Some(current) = self.memory.get_mut(offset) else {
+                    break;
+                };
+                if *current != byte {
+                    self.changed.insert(offset, *current);
+                    *current = byte;
+                    rows.insert(offset / per_row);
+                }
+            }
+        }
+        for row in rows {
+            let item = self.row(row, sender);
+            self.view_wrapper.remove(row as u32);
+            self.view_wrapper.insert(row as u32, item);
+        }
+    }
+
+    /// The address a data label or number in the current radix names.
+    fn resolve(&self, target: &str) -> Option<u32> {
+        let target = target.trim();
+        match self.labels.iter().find(|(label, _)| label == target) {
+            Some((_, addr)) => Some(*addr),
+            None => u32::try_from(self.curr_radix.parse(target)?).ok(),
+        }
+    }
+
+    /// Selects the row holding `addr` and scrolls it to the middle.
+    fn goto(&self, addr: u32) -> bool {
+        let Some(offset) = addr
+            .checked_sub(DATA_MEM_START)
+            .filter(|offset| (*offset as usize) < self.memory.len())
+        else {
+            return false;
+        };
+        let row = offset as usize / self.mode.bytes_per_row();
+        self.view_wrapper.selection_model.set_selected(row as u32);
+
+        let adjustment = self.scrolled.vadjustment();
+        let rows = self.view_wrapper.len().max(1) as f64;
+        adjustment.set_value(adjustment.upper() * row as f64 / rows - adjustment.page_size() / 2.0);
+        true
+    }
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for MemoryView {
     type Input = MemoryMsg;
-    type Output = crate::Msg;
+    type Output = MemoryOutput;
     type Init = ();
 
     view! {
-        memory_view = gtk::ScrolledWindow {
+        memory_view = gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 5,
             set_hexpand: true,
             set_vexpand: true,
             set_margin_all: 5,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 5,
+                gtk::DropDown {
+                    set_model: Some(&gtk::StringList::new(&MemoryMode::ALL.map(|mode| mode.name()))),
+                    set_tooltip_text: Some("How memory is grouped into rows"),
+                    connect_selected_notify[sender] => move |dropdown| {
+                        let mode = MemoryMode::ALL[(dropdown.selected() as usize).min(MemoryMode::ALL.len() - 1)];
+                        sender.input(MemoryMsg::SetMode(mode))
+                    },
+                },
+                gtk::SearchEntry {
+                    set_hexpand: true,
+                    set_placeholder_text: Some("Go to address or label"),
+                    #[watch]
+                    set_class_active: ("error", model.goto_failed),
+                    connect_activate[sender] => move |entry| {
+                        sender.input(MemoryMsg::Goto(entry.text().to_string()))
+                    },
+                },
+            },
             #[local_ref]
-            my_view -> gtk::ColumnView {}
+            scrolled -> gtk::ScrolledWindow {
+                set_vexpand: true,
+                #[local_ref]
+                my_view -> gtk::ColumnView {}
+            },
         }
     }
 
     fn init(
         _: Self::Init,
         root: &Self::Root,
-        _sender: ComponentSender<Self>,
+        sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let mut view_wrapper = TypedColumnView::<MemoryRow, gtk::NoSelection>::new();
+        let mut view_wrapper = TypedColumnView::<MemoryRow, gtk::SingleSelection>::new();
         view_wrapper.append_column::<AddressColumn>();
+        view_wrapper.append_column::<LabelsColumn>();
         view_wrapper.append_column::<MemoryColumn>();
+        view_wrapper.append_column::<AsciiColumn>();
 
         view_wrapper.get_columns().iter().for_each(|(_, c)| {
             c.set_expand(true);
         });
+        view_wrapper.selection_model.set_autoselect(false);
+        view_wrapper.selection_model.set_can_unselect(true);
 
-        (0..1024).for_each(|idx| {
-            view_wrapper.append(MemoryRow {
-                addr: idx,
-                value: RadixedValue {
-                    radix: Radices::Hex,
-                    value: 0,
-                },
-            });
-        });
-
-        let model = MemoryView {
+        let mut model = MemoryView {
             view_wrapper,
+            scrolled: gtk::ScrolledWindow::new(),
+            memory: vec![0; 1024],
+            changed: BTreeMap::new(),
+            labels: Vec::new(),
             curr_radix: Radices::Hex,
+            mode: MemoryMode::Bytes,
+            goto_failed: false,
         };
+        model.rebuild(&sender);
 
+        let scrolled = &model.scrolled;
         let my_view = &model.view_wrapper.view;
         my_view.set_show_row_separators(true);
         my_view.set_show_column_separators(true);
@@ -118,35 +420,45 @@ impl SimpleComponent for MemoryView {
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         match msg {
-            MemoryMsg::UpdateMemory(new_mem) => {
-                let radix = self.curr_radix;
-                self.view_wrapper.clear();
-                new_mem.into_iter().enumerate().for_each(|(idx, val)| {
-                    self.view_wrapper.append(MemoryRow {
-                        addr: idx as u32,
-                        value: RadixedValue { radix, value: val },
-                    });
-                })
+            MemoryMsg::UpdateMemory(new_mem, old_mem) => {
+                self.changed = old_mem
+                    .map(|old_mem| {
+                        new_mem
+                            .iter()
+                            .zip(old_mem)
+                            .enumerate()
+                            .filter(|(_, (new, old))| *new != old)
+                            .map(|(offset, (_, old))| (offset, old))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                self.memory = new_mem;
+                self.rebuild(&sender);
             }
+            MemoryMsg::Write(writes) => self.write(writes, &sender),
             MemoryMsg::UpdateRadix(radix) => {
                 self.curr_radix = radix;
-                let mut new_list: Vec<u8> = Vec::new();
-
-                (0..self.view_wrapper.len()).for_each(|v| {
-                    self.view_wrapper.get(v).iter().for_each(|rv| {
-                        new_list.push(rv.borrow().value.value);
-                    });
-                });
-
-                self.view_wrapper.clear();
-                new_list.iter().enumerate().for_each(|(idx, val)| {
-                    self.view_wrapper.append(MemoryRow {
-                        addr: idx as u32,
-                        value: RadixedValue { radix, value: *val },
-                    });
-                });
+                self.rebuild(&sender);
+            }
+            MemoryMsg::SetMode(mode) => {
+                self.mode = mode;
+                self.rebuild(&sender);
+            }
+            MemoryMsg::SetLabels(labels) => {
+                self.labels = labels;
+                self.rebuild(&sender);
+            }
+            MemoryMsg::Goto(target) => {
+                self.goto_failed = !target.trim().is_empty()
+                    && !self.resolve(&target).is_some_and(|addr| self.goto(addr));
+            }
+            MemoryMsg::Show(addr) => self.goto_failed = !self.goto(addr),
+            MemoryMsg::Edit(addr, bytes) => {
+                sender
+                    .output(MemoryOutput::SetBytes(addr - DATA_MEM_START, bytes))
+                    .unwrap();
             }
         }
     }
@@ -1,4 +1,5 @@
 use num::PrimInt;
+use relm4::gtk::{self, glib, prelude::*};
 
 #[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
 pub enum Radices {
@@ -6,6 +7,55 @@ pub enum Radices {
     Hex,
     Decimal,
 }
+impl Radices {
+    /// Reads a value typed into a cell. A `0x` or `0b` prefix picks the
+    /// radix, otherwise the digits are read in this one. A leading `-`
+    /// negates the value.
+    pub fn parse(self, text: &str) -> Option<i64> {
+        let text = text.trim().replace('_', "");
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.as_str()),
+        };
+        let (radix, digits) = if let Some(hex) = digits.strip_prefix("0x") {
+            (16, hex)
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            (2, binary)
+        } else {
+            match self {
+                Radices::Binary => (2, digits),
+                Radices::Hex => (16, digits),
+                Radices::Decimal => (10, digits),
+            }
+        };
+        if digits.starts_with(['+', '-']) {
+            return None;
+        }
+        let value = i64::from_str_radix(digits, radix).ok()?;
+        Some(if negative { -value } else { value })
+    }
+}
+
+/// Calls `edited` with the value typed into `label` once editing finishes.
+/// When the text is not a value, or `edited` rejects it, `label` goes back
+/// to `text`.
+fn connect_edited(
+    label: &gtk::EditableLabel,
+    text: String,
+    radix: Radices,
+    edited: impl Fn(i64) -> bool + 'static,
+) -> glib::SignalHandlerId {
+    label.connect_editing_notify(move |label| {
+        if label.is_editing() || label.text() == text {
+            return;
+        }
+        match radix.parse(&label.text()) {
+            Some(value) if edited(value) => {}
+            _ => label.set_text(&text),
+        }
+    })
+}
+
 #[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
 pub struct RadixedValue<T: PrimInt> {
     radix: Radices,
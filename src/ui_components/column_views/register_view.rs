@@ -1,8 +1,12 @@
-use super::RadixedValue;
+use super::{connect_edited, RadixedValue};
+use crate::cpu::cp0::CP0_NAMES;
+use crate::cpu::cpu_interface::RegisterKind;
 use crate::ui_components::column_views::Radices;
-use relm4::{gtk::traits::WidgetExt, prelude::*};
+use num::FromPrimitive;
+use relm4::gtk::{glib, prelude::*};
+use relm4::prelude::*;
 use relm4::{
-    typed_view::column::{LabelColumn, TypedColumnView},
+    typed_view::column::{LabelColumn, RelmColumn, TypedColumnView},
     ComponentParts, ComponentSender, SimpleComponent,
 };
 
@@ -18,11 +22,16 @@ const REG_ALIAS: [&str; 33] = [
     "$t9", "$k0", "$k1", "$gp", "$sp", "$fp", "$ra",
 ];
 
-#[derive(Debug)]
 pub struct RegisterRow {
     reg_num: &'static str,
     reg_alias: &'static str,
     reg_val: RadixedValue<u32>,
+    /// The value before the last step, when the step changed it.
+    old: Option<u32>,
+    /// The register written when the value is edited, `None` for
+    /// coprocessor 0 registers which cannot be.
+    reg: Option<RegisterKind>,
+    sender: ComponentSender<RegisterView>,
 }
 
 pub struct RegNumColumn;
@@ -57,26 +66,66 @@ impl LabelColumn for RegAliasColumn {
     }
 }
 
+fn format_register(value: &RadixedValue<u32>) -> String {
+    match value.radix {
+        Radices::Binary => format!("0b{:032b}", value.value),
+        Radices::Hex => format!("0x{:08x}", value.value),
+        Radices::Decimal => format!("{:010}", value.value),
+    }
+}
+
+/// Register values, which can be edited in any radix to write the register.
 pub struct RegisterColumn;
 
-impl LabelColumn for RegisterColumn {
-    type Item = RegisterRow;
+impl RelmColumn for RegisterColumn {
+    type Root = gtk::EditableLabel;
+
+    type Widgets = Option<glib::SignalHandlerId>;
 
-    type Value = RadixedValue<u32>;
+    type Item = RegisterRow;
 
     const COLUMN_NAME: &'static str = "Register Contents";
 
-    const ENABLE_SORT: bool = false;
+    fn setup(_: &gtk::ListItem) -> (Self::Root, Self::Widgets) {
+        (gtk::EditableLabel::new(""), None)
+    }
 
-    fn get_cell_value(item: &Self::Item) -> Self::Value {
-        item.reg_val
+    fn bind(item: &mut Self::Item, handler: &mut Self::Widgets, label: &mut Self::Root) {
+        let text = format_register(&item.reg_val);
+        label.set_text(&text);
+        label.set_class_active("accent", item.old.is_some());
+        label.set_tooltip_text(
+            item.old
+                .map(|value| {
+                    let old = RadixedValue {
+                        radix: item.reg_val.radix,
+                        value,
+                    };
+                    format!("Was {}", format_register(&old))
+                })
+                .as_deref(),
+        );
+        label.set_editable(item.reg.is_some());
+        if let Some(reg) = item.reg {
+            let sender = item.sender.clone();
+            *handler = Some(connect_edited(
+                label,
+                text,
+                item.reg_val.radix,
+                move |value| {
+                    let fits = (i32::MIN as i64..=u32::MAX as i64).contains(&value);
+                    if fits {
+                        sender.input(RegMsg::Edit(reg, value as u32));
+                    }
+                    fits
+                },
+            ));
+        }
     }
 
-    fn format_cell_value(value: &Self::Value) -> String {
-        match value.radix {
-            Radices::Binary => format!("0b{:032b}", value.value),
-            Radices::Hex => format!("0x{:08x}", value.value),
-            Radices::Decimal => format!("{:010}", value.value),
+    fn unbind(_: &mut Self::Item, handler: &mut Self::Widgets, label: &mut Self::Root) {
+        if let Some(handler) = handler.take() {
+            label.disconnect(handler);
         }
     }
 }
@@ -84,18 +133,72 @@ impl LabelColumn for RegisterColumn {
 pub struct RegisterView {
     view_wrapper: TypedColumnView<RegisterRow, gtk::NoSelection>,
     curr_radix: Radices,
+    /// Values in the order of the rows, the program counter first.
+    registers: Vec<u32>,
+    /// Values before the last step in the same order, if known.
+    previous: Option<Vec<u32>>,
 }
 
 #[derive(Debug)]
 pub enum RegMsg {
-    UpdateRegisters(Vec<u32>),
+    /// General purpose registers, the program counter and coprocessor 0
+    /// registers, with their values before the last step if known.
+    UpdateRegisters(Vec<u32>, Option<Vec<u32>>),
     UpdateRadix(Radices),
+    Edit(RegisterKind, u32),
+}
+
+#[derive(Debug)]
+pub enum RegOutput {
+    SetRegister(RegisterKind, u32),
+}
+
+impl RegisterView {
+    fn rebuild(&mut self, sender: &ComponentSender<Self>) {
+        let radix = self.curr_radix;
+        self.view_wrapper.clear();
+        self.registers.iter().enumerate().for_each(|(idx, val)| {
+            let old = self
+                .previous
+                .as_ref()
+                .and_then(|previous| previous.get(idx))
+                .filter(|old| *old != val)
+                .copied();
+            let (reg_num, reg_alias, reg) = match idx.checked_sub(REG_NUMBERS.len()) {
+                None => (REG_NUMBERS[idx], REG_ALIAS[idx], register_kind(idx)),
+                // Coprocessor 0 registers follow the general purpose ones
+                Some(cp0) => {
+                    let (_, number, name) = CP0_NAMES[cp0];
+                    (number, name, None)
+                }
+            };
+            self.view_wrapper.append(RegisterRow {
+                reg_num,
+                reg_alias,
+                reg_val: RadixedValue { radix, value: *val },
+                old,
+                reg,
+                sender: sender.clone(),
+            });
+        });
+    }
+}
+
+/// Moves the program counter from after the general purpose registers to
+/// the front.
+fn display_order(registers: &[u32]) -> Vec<u32> {
+    registers[32..33]
+        .iter()
+        .chain(&registers[..32])
+        .chain(&registers[33..])
+        .copied()
+        .collect()
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for RegisterView {
     type Input = RegMsg;
-    type Output = crate::Msg;
+    type Output = RegOutput;
     type Init = ();
 
     view! {
@@ -111,7 +214,7 @@ impl SimpleComponent for RegisterView {
     fn init(
         _: Self::Init,
         root: &Self::Root,
-        _sender: ComponentSender<Self>,
+        sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let mut view_wrapper = TypedColumnView::<RegisterRow, gtk::NoSelection>::new();
         view_wrapper.append_column::<RegNumColumn>();
@@ -122,21 +225,13 @@ impl SimpleComponent for RegisterView {
             c.set_expand(true);
         });
 
-        (0..33).for_each(|idx| {
-            view_wrapper.append(RegisterRow {
-                reg_num: REG_NUMBERS[idx],
-                reg_alias: REG_ALIAS[idx],
-                reg_val: RadixedValue {
-                    radix: Radices::Hex,
-                    value: 0,
-                },
-            });
-        });
-
-        let model = RegisterView {
+        let mut model = RegisterView {
             view_wrapper,
             curr_radix: Radices::Hex,
+            registers: vec![0; 33],
+            previous: None,
         };
+        model.rebuild(&sender);
 
         let my_view = &model.view_wrapper.view;
         my_view.set_show_row_separators(true);
@@ -145,44 +240,30 @@ impl SimpleComponent for RegisterView {
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, msg: Self::Input, _: ComponentSender<Self>) {
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         match msg {
-            RegMsg::UpdateRegisters(new_registers) => {
-                let radix = self.curr_radix;
-
-                self.view_wrapper.clear();
-                let mut back = new_registers[..32].to_owned();
-                let mut front = new_registers[32..].to_owned();
-
-                front.reverse();
-                front.append(&mut back);
-
-                front.iter().enumerate().for_each(|(idx, val)| {
-                    self.view_wrapper.append(RegisterRow {
-                        reg_num: REG_NUMBERS[idx],
-                        reg_alias: REG_ALIAS[idx],
-                        reg_val: RadixedValue { radix, value: *val },
-                    });
-                });
+            RegMsg::UpdateRegisters(new_registers, old_registers) => {
+                self.registers = display_order(&new_registers);
+                self.previous = old_registers.as_deref().map(display_order);
+                self.rebuild(&sender);
             }
             RegMsg::UpdateRadix(radix) => {
-                let mut new_list: Vec<u32> = Vec::new();
-
-                (0..self.view_wrapper.len()).for_each(|v| {
-                    self.view_wrapper.get(v).iter().for_each(|rv| {
-                        new_list.push(rv.borrow().reg_val.value);
-                    });
-                });
-
-                self.view_wrapper.clear();
-                new_list.iter().enumerate().for_each(|(idx, val)| {
-                    self.view_wrapper.append(RegisterRow {
-                        reg_num: REG_NUMBERS[idx],
-                        reg_alias: REG_ALIAS[idx],
-                        reg_val: RadixedValue { radix, value: *val },
-                    });
-                });
+                self.curr_radix = radix;
+                self.rebuild(&sender);
+            }
+            RegMsg::Edit(reg, value) => {
+                sender.output(RegOutput::SetRegister(reg, value)).unwrap();
             }
         }
     }
 }
+
+/// The register shown on row `idx`, the program counter coming first.
+/// `$zero` always reads as zero so it is not editable.
+fn register_kind(idx: usize) -> Option<RegisterKind> {
+    match idx {
+        0 => Some(RegisterKind::RegPC),
+        1 => None,
+        _ => FromPrimitive::from_usize(idx - 1),
+    }
+}
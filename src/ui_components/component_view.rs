@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use crate::cpu::control_unit::{Mem, MemToReg, PCSrc, RegDst};
 use crate::cpu::cpu_interface::CPUInterface;
-use crate::cpu::single_cycle_cpu::SingleCycleCPU;
+use crate::cpu::multicore::MulticoreCPU;
 
 use super::CPUViewMessage;
 use crate::main_separator;
@@ -15,7 +17,7 @@ pub struct ComponentView {
     handler: DrawHandler,
     imgs: Vec<Vec<u8>>,
     size: (i32, i32),
-    cpu: SingleCycleCPU,
+    cpu: Arc<MulticoreCPU>,
 }
 
 #[relm4::component(pub)]
@@ -208,7 +210,7 @@ impl SimpleComponent for ComponentView {
             handler: DrawHandler::new(),
             imgs,
             size: (0, 0),
-            cpu: SingleCycleCPU::default(),
+            cpu: Arc::default(),
         };
 
         let area = model.handler.drawing_area();
@@ -219,14 +221,21 @@ impl SimpleComponent for ComponentView {
     fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
         match msg {
             CPUViewMessage::Update(cpu) => {
-                self.cpu = *cpu;
+                self.cpu = cpu;
                 self.draw();
             }
             CPUViewMessage::Resize(size) => {
                 self.size = size;
                 self.draw();
             }
+            CPUViewMessage::Previous(_) => {}
+            CPUViewMessage::DirtyMemory(_) => {}
             CPUViewMessage::ChangeRadix(_) => {}
+            CPUViewMessage::SelectCore(_) => {}
+            CPUViewMessage::SetRegister(_, _) => {}
+            CPUViewMessage::SetMemory(_, _) => {}
+            CPUViewMessage::SetLabels(_) => {}
+            CPUViewMessage::ShowAddress(_) => {}
             CPUViewMessage::None => {}
         }
     }
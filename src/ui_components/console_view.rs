@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::devices::keyboard_display::{InputQueue, KeyboardDisplay};
+use crate::cpu::multicore::MulticoreCPU;
+
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+#[derive(Debug)]
+pub enum ConsoleMsg {
+    Update(Arc<MulticoreCPU>),
+    SendInput(String),
+}
+
+/// Shows what the program wrote to the MMIO display and forwards typed text
+/// to the MMIO keyboard.
+pub struct ConsoleView {
+    buffer: gtk::TextBuffer,
+    input: Option<InputQueue>,
+    pending: usize,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ConsoleView {
+    type Input = ConsoleMsg;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 5,
+            set_margin_all: 10,
+            gtk::ScrolledWindow {
+                set_hexpand: true,
+                set_vexpand: true,
+                gtk::TextView {
+                    set_buffer: Some(&model.buffer),
+                    set_editable: false,
+                    set_cursor_visible: false,
+                    set_monospace: true,
+                    set_wrap_mode: gtk::WrapMode::Char,
+                },
+            },
+            gtk::Entry {
+                set_placeholder_text: Some("Keyboard input, press Enter to send"),
+                #[watch]
+                set_sensitive: model.input.is_some(),
+                connect_activate[sender] => move |entry| {
+                    sender.input(ConsoleMsg::SendInput(format!("{}\n", entry.text())));
+                    entry.set_text("");
+                },
+            },
+            gtk::Label {
+                set_xalign: 0.0,
+                add_css_class: "dim-label",
+                #[watch]
+                set_label: &format!("{} key(s) waiting to be read", model.pending),
+            },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ConsoleView {
+            buffer: gtk::TextBuffer::new(None),
+            input: None,
+            pending: 0,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            ConsoleMsg::Update(cpu) => {
+                let device = cpu
+                    .get_devices()
+                    .iter()
+                    .find_map(|device| device.as_any().downcast_ref::<KeyboardDisplay>());
+                self.buffer
+                    .set_text(device.map(|device| device.output()).unwrap_or_default());
+                self.input = device.map(|device| device.input());
+                self.pending = device.map_or(0, |device| device.pending_keys());
+            }
+            ConsoleMsg::SendInput(text) => {
+                if let Some(input) = &self.input {
+                    input.push_str(&text);
+                    self.pending += text.len();
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use adw::prelude::*;
 use mips_assembler::parse;
@@ -9,16 +11,39 @@ use relm4_icons::icon_name;
 
 use super::{
     asm_view::{AsmView, AsmViewMsg, AsmViewOutput},
+    bitmap_view::BitmapView,
+    breakpoint_view::{BreakpointView, BreakpointViewMsg, BreakpointViewOutput},
+    cache_view::CacheView,
+    call_stack_view::{CallStackMsg, CallStackOutput, CallStackView},
     column_views::Radices,
     component_view::ComponentView,
+    console_view::{ConsoleMsg, ConsoleView},
     history::History,
-    simple_view::SimpleView,
+    profile_view::{ProfileMsg, ProfileView},
+    simple_view::{SimpleView, SimpleViewOutput},
+    stack_view::{StackMsg, StackView},
+    statistics_view::StatisticsView,
+    symbol_view::{SymbolMsg, SymbolOutput, SymbolView},
     CPUViewMessage,
 };
+use crate::breakpoints::{parse_watchpoint, Breakpoints, Condition};
 use crate::cpu::{
+    cache::CacheConfig,
     cpu_interface::{CPUInterface, RegisterKind},
-    single_cycle_cpu::SingleCycleCPU,
+    data_memory::MemoryArchitecture,
+    devices::{
+        bitmap_display::{BitmapConfig, BitmapDisplay},
+        keyboard_display::KeyboardDisplay,
+        timer::Timer,
+    },
+    dirty_regions::DirtyRegions,
+    instruction_memory::InstructionMem,
+    mmu::AddressTranslation,
+    multicore::{MulticoreCPU, MulticoreConfig},
+    utils::is_call,
+    watchpoints::{Trigger, WatchHit, Watchpoint},
 };
+use crate::source_map::{SourceMap, Symbol, SymbolKind};
 
 #[derive(Debug, Clone)]
 pub enum SimulationMsg {
@@ -27,6 +52,12 @@ pub enum SimulationMsg {
     FileSaved,
     Ignore,
     Step,
+    StepOver,
+    StepOut,
+    RunToCursor(usize),
+    ShowLine(usize),
+    /// Shows a label in the editor and in the machine code or memory.
+    ShowSymbol(Symbol),
     Run,
     Break,
     Undo,
@@ -36,14 +67,62 @@ pub enum SimulationMsg {
     ShowMessage(String),
     ChangeRadix(Radices),
     ResizeHistory(usize),
+    SetHaltAddress(Option<u32>),
+    SetMemoryArchitecture(MemoryArchitecture),
+    ConfigureCaches(Option<CacheConfig>, Option<CacheConfig>),
+    SetAddressTranslation(AddressTranslation),
+    ConfigureBitmapDisplay(Option<BitmapConfig>),
+    ConfigureCores(MulticoreConfig),
+    ToggleBreakpoint(usize),
+    SetBreakpointCondition(usize, String),
+    AddWatchpoint(String, u32, Trigger, String),
+    RemoveWatchpoint(usize),
+    /// Core, register and the value to write into it.
+    SetRegister(usize, RegisterKind, u32),
+    /// Bytes to write from an offset into `.data`.
+    SetMemory(u32, Vec<u8>),
     ShowSidebar(bool),
 }
 
 #[derive(Debug)]
 pub enum SimulationCmd {
-    ThreadFinished(SingleCycleCPU),
+    RunProgress(Arc<MulticoreCPU>, DirtyRegions),
+    ThreadFinished(MulticoreCPU),
+    BreakpointHit(usize),
+    WatchpointHit(WatchHit),
 }
 
+/// Where a run stops besides breakpoints, watchpoints, errors and halts.
+#[derive(Debug, Clone, Copy)]
+enum RunUntil {
+    /// Runs until the user breaks.
+    Break,
+    /// Until `core` reaches `addr` with `$sp` at or above `sp`, that is
+    /// back in the caller of a call made at `addr - 4` however the callee
+    /// returned, and not in a recursive call of it.
+    Return { core: usize, addr: u32, sp: u32 },
+    /// Until the core that just executed reaches `pc`.
+    Address(u32),
+}
+
+impl RunUntil {
+    fn reached(&self, cpu: &MulticoreCPU) -> bool {
+        let current = &cpu.cores()[cpu.current_core()];
+        match *self {
+            RunUntil::Break => false,
+            RunUntil::Return { core, addr, sp } => {
+                cpu.current_core() == core
+                    && current.get_register(RegisterKind::RegPC) == addr
+                    && current.get_register(RegisterKind::Reg29) >= sp
+            }
+            RunUntil::Address(pc) => current.get_register(RegisterKind::RegPC) == pc,
+        }
+    }
+}
+
+/// How often a running program refreshes the views.
+const RUN_REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Debug)]
 pub enum SimulationOutput {
     ShowMessage(String),
@@ -56,12 +135,39 @@ pub enum SimulationOutput {
 pub struct CPUSimulation {
     simple_view: Controller<SimpleView>,
     component_view: Controller<ComponentView>,
+    cache_view: Controller<CacheView>,
+    console_view: Controller<ConsoleView>,
+    bitmap_view: Controller<BitmapView>,
+    statistics_view: Controller<StatisticsView>,
+    profile_view: Controller<ProfileView>,
+    breakpoint_view: Controller<BreakpointView>,
+    call_stack_view: Controller<CallStackView>,
+    stack_view: Controller<StackView>,
+    symbol_view: Controller<SymbolView>,
     asm_view: Controller<AsmView>,
-    history: History<SingleCycleCPU>,
+    history: History<Arc<MulticoreCPU>>,
+    /// Memory written by states appended since the views last updated.
+    dirty: DirtyRegions,
     curr_asm: String,
     curr_file: String,
+    /// Bytes of data the current program assembled to.
+    data_len: usize,
+    source_map: SourceMap,
+    breakpoints: Breakpoints,
+    watchpoints: Vec<Watchpoint>,
     app_to_thread: Option<Sender<()>>,
     cpu_running: bool,
+    /// Set when the current state's dirty memory does not describe what the
+    /// views show, after a reset, undo or redo, so they redraw all of it.
+    memory_stale: bool,
+    exit_status: Option<String>,
+    halt_address: Option<u32>,
+    memory_architecture: MemoryArchitecture,
+    data_cache: Option<CacheConfig>,
+    instruction_cache: Option<CacheConfig>,
+    address_translation: AddressTranslation,
+    bitmap_display: Option<BitmapConfig>,
+    multicore: MulticoreConfig,
     sidebar_visible: bool,
     idx: usize,
     dynamic_index: DynamicIndex,
@@ -80,6 +186,12 @@ impl FactoryComponent for CPUSimulation {
         gtk::Box {
             set_orientation: gtk::Orientation::Vertical,
             set_vexpand: true,
+            adw::Banner {
+                #[watch]
+                set_title: self.exit_status.as_deref().unwrap_or_default(),
+                #[watch]
+                set_revealed: self.exit_status.is_some(),
+            },
             #[name = "flap"]
             adw::Flap {
                 connect_reveal_flap_notify[sender] => move |val| {
@@ -110,6 +222,33 @@ impl FactoryComponent for CPUSimulation {
                         add_titled[Some("Component"), "Component"] = self.component_view.widget() {} -> {
                             set_icon_name: Some(icon_name::PROCESSOR),
                         },
+                        add_titled[Some("Cache"), "Cache"] = self.cache_view.widget() {} -> {
+                            set_icon_name: Some("view-grid-symbolic"),
+                        },
+                        add_titled[Some("Console"), "Console"] = self.console_view.widget() {} -> {
+                            set_icon_name: Some("utilities-terminal-symbolic"),
+                        },
+                        add_titled[Some("Bitmap"), "Bitmap"] = self.bitmap_view.widget() {} -> {
+                            set_icon_name: Some("image-x-generic-symbolic"),
+                        },
+                        add_titled[Some("Statistics"), "Statistics"] = self.statistics_view.widget() {} -> {
+                            set_icon_name: Some("utilities-system-monitor-symbolic"),
+                        },
+                        add_titled[Some("Profile"), "Profile"] = self.profile_view.widget() {} -> {
+                            set_icon_name: Some("find-location-symbolic"),
+                        },
+                        add_titled[Some("Call Stack"), "Call Stack"] = self.call_stack_view.widget() {} -> {
+                            set_icon_name: Some("view-list-symbolic"),
+                        },
+                        add_titled[Some("Stack"), "Stack"] = self.stack_view.widget() {} -> {
+                            set_icon_name: Some("view-continuous-symbolic"),
+                        },
+                        add_titled[Some("Symbols"), "Symbols"] = self.symbol_view.widget() {} -> {
+                            set_icon_name: Some("edit-find-symbolic"),
+                        },
+                        add_titled[Some("Breakpoints"), "Breakpoints"] = self.breakpoint_view.widget() {} -> {
+                            set_icon_name: Some("process-stop-symbolic"),
+                        },
                    },
                 },
             },
@@ -145,12 +284,28 @@ impl FactoryComponent for CPUSimulation {
                 },
                 pack_end = &gtk::Button {
                     #[watch]
-                    set_sensitive: !self.cpu_running,
+                    set_sensitive: !self.cpu_running && self.exit_status.is_none(),
+                    set_icon_name: icon_name::ARROW_STEP_OUT_FILLED,
+                    connect_clicked[sender] => move |_| { sender.input(SimulationMsg::StepOut) },
+                    set_tooltip_text: Some("Step Out"),
+                },
+                pack_end = &gtk::Button {
+                    #[watch]
+                    set_sensitive: !self.cpu_running && self.exit_status.is_none(),
+                    set_icon_name: icon_name::ARROW_STEP_OVER_FILLED,
+                    connect_clicked[sender] => move |_| { sender.input(SimulationMsg::StepOver) },
+                    set_tooltip_text: Some("Step Over"),
+                },
+                pack_end = &gtk::Button {
+                    #[watch]
+                    set_sensitive: !self.cpu_running && self.exit_status.is_none(),
                     set_icon_name: icon_name::ARROW_STEP_IN_RIGHT_FILLED,
                     connect_clicked[sender] => move |_| { sender.input(SimulationMsg::Step) },
                     set_tooltip_text: Some("Step Forward"),
                 },
                 pack_end = &gtk::ToggleButton {
+                   #[watch]
+                   set_sensitive: self.cpu_running || self.exit_status.is_none(),
                    #[watch]
                    set_active: self.cpu_running,
                    #[watch]
@@ -182,28 +337,113 @@ impl FactoryComponent for CPUSimulation {
     fn init_model(count: Self::Init, idx: &DynamicIndex, sender: FactorySender<Self>) -> Self {
         let simple_view = SimpleView::builder()
             .launch(())
-            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+            .forward(sender.input_sender(), |msg| match msg {
+                SimpleViewOutput::SetRegister(core, reg, value) => {
+                    SimulationMsg::SetRegister(core, reg, value)
+                }
+                SimpleViewOutput::SetMemory(addr, bytes) => SimulationMsg::SetMemory(addr, bytes),
+            });
 
         let component_view = ComponentView::builder()
             .launch(())
             .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
 
+        let cache_view = CacheView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let console_view = ConsoleView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let bitmap_view = BitmapView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let statistics_view = StatisticsView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let profile_view = ProfileView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let breakpoint_view =
+            BreakpointView::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    BreakpointViewOutput::RemoveBreakpoint(line) => {
+                        SimulationMsg::ToggleBreakpoint(line)
+                    }
+                    BreakpointViewOutput::AddWatchpoint(target, len, trigger, condition) => {
+                        SimulationMsg::AddWatchpoint(target, len, trigger, condition)
+                    }
+                    BreakpointViewOutput::RemoveWatchpoint(idx) => {
+                        SimulationMsg::RemoveWatchpoint(idx)
+                    }
+                });
+
+        let call_stack_view =
+            CallStackView::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    CallStackOutput::ShowLine(line) => SimulationMsg::ShowLine(line),
+                });
+
+        let stack_view = StackView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |_| SimulationMsg::Ignore);
+
+        let symbol_view = SymbolView::builder()
+            .launch(())
+            .forward(sender.input_sender(), |msg| match msg {
+                SymbolOutput::Show(symbol) => SimulationMsg::ShowSymbol(symbol),
+            });
+
         let asm_view =
             AsmView::builder()
                 .launch(())
                 .forward(sender.input_sender(), |msg| match msg {
                     AsmViewOutput::SaveFile(file_string) => SimulationMsg::SaveFile(file_string),
+                    AsmViewOutput::ToggleBreakpoint(line) => SimulationMsg::ToggleBreakpoint(line),
+                    AsmViewOutput::SetBreakpointCondition(line, condition) => {
+                        SimulationMsg::SetBreakpointCondition(line, condition)
+                    }
+                    AsmViewOutput::RunToCursor(line) => SimulationMsg::RunToCursor(line),
                 });
 
         Self {
             simple_view,
             component_view,
+            cache_view,
+            console_view,
+            bitmap_view,
+            statistics_view,
+            profile_view,
+            breakpoint_view,
+            call_stack_view,
+            stack_view,
+            symbol_view,
             asm_view,
             history: History::new(10),
+            dirty: DirtyRegions::default(),
             curr_asm: String::default(),
             curr_file: String::default(),
+            data_len: 0,
+            source_map: SourceMap::default(),
+            breakpoints: Breakpoints::default(),
+            watchpoints: Vec::new(),
             app_to_thread: None,
             cpu_running: false,
+            memory_stale: true,
+            exit_status: None,
+            halt_address: None,
+            memory_architecture: MemoryArchitecture::default(),
+            data_cache: None,
+            instruction_cache: None,
+            address_translation: AddressTranslation::default(),
+            bitmap_display: None,
+            multicore: MulticoreConfig::default(),
             sidebar_visible: false,
             idx: count,
             dynamic_index: idx.clone(),
@@ -220,13 +460,9 @@ impl FactoryComponent for CPUSimulation {
                     Ok(contents) => {
                         match parse(&contents) {
                             Ok((inst_mem, data_mem)) => {
-                                self.history.reset(SingleCycleCPU::new_from_memory(
-                                    inst_mem.clone(),
-                                    data_mem,
-                                ));
-                                self.asm_view
-                                    .emit(AsmViewMsg::LoadFile(contents.clone(), inst_mem));
+                                self.load_program(inst_mem.clone(), data_mem);
                                 self.curr_asm = contents;
+                                self.show_source(inst_mem);
                                 sender.input(SimulationMsg::UpdateViews);
                             }
                             Err(err) => sender.input(SimulationMsg::ShowMessage(err)),
@@ -238,8 +474,7 @@ impl FactoryComponent for CPUSimulation {
             SimulationMsg::SaveFile(contents) => {
                 match parse(&contents) {
                     Ok((inst_mem, data_mem)) => {
-                        self.history
-                            .reset(SingleCycleCPU::new_from_memory(inst_mem.clone(), data_mem));
+                        self.load_program(inst_mem.clone(), data_mem);
                         self.curr_asm = contents.clone();
                         sender.input(SimulationMsg::UpdateViews);
                         sender
@@ -254,38 +489,75 @@ impl FactoryComponent for CPUSimulation {
                 };
             }
             SimulationMsg::FileSaved => {
-                self.asm_view.emit(AsmViewMsg::LoadFile(
-                    self.curr_asm.clone(),
-                    self.history.get_curr().instruction_memory.clone(),
-                ));
+                let inst_mem = self.history.get_curr().instruction_memory().clone();
+                self.show_source(inst_mem);
             }
             SimulationMsg::Step => {
-                let curr = self.history.get_curr().clone();
-                self.history.append(curr);
-                self.history.get_curr().step();
-                if let Some(error) = self.history.get_curr().get_error() {
+                let mut cpu = MulticoreCPU::clone(self.history.get_curr());
+                cpu.set_watchpoints(&self.watchpoints);
+                cpu.step();
+                if let Some(error) = cpu.get_error() {
                     sender.input(SimulationMsg::ShowMessage(error));
                 }
+                if let Some(hit) = cpu.get_watch_hit() {
+                    sender.input(SimulationMsg::ShowMessage(hit.to_string()));
+                }
+                self.append(cpu);
                 sender.input(SimulationMsg::UpdateViews);
             }
-            SimulationMsg::Run => {
-                self.asm_view.emit(AsmViewMsg::SetCanSave(false));
-                let (app_tx, thread_rx) = mpsc::channel::<()>();
-                self.cpu_running = true;
-
-                self.app_to_thread = Some(app_tx);
-
-                let mut cpu_copy = self.history.get_curr().clone();
-                sender.spawn_oneshot_command(move || {
-                    while cpu_copy.get_error().is_none() {
-                        cpu_copy.step();
-                        if thread_rx.try_recv().is_ok() {
-                            break;
-                        }
-                    }
-                    SimulationCmd::ThreadFinished(cpu_copy)
+            SimulationMsg::StepOver => {
+                let cpu: &MulticoreCPU = self.history.get_curr();
+                let call = cpu.scheduled_core().and_then(|core| {
+                    let pc = cpu.cores()[core].get_register(RegisterKind::RegPC);
+                    let inst = cpu.instruction_memory().get_instruction(pc)?;
+                    is_call(inst).then(|| RunUntil::Return {
+                        core,
+                        addr: pc.wrapping_add(4),
+                        sp: cpu.cores()[core].get_register(RegisterKind::Reg29),
+                    })
+                });
+                match call {
+                    Some(until) => self.run(&sender, until),
+                    None => sender.input(SimulationMsg::Step),
+                }
+            }
+            SimulationMsg::StepOut => {
+                let cpu: &MulticoreCPU = self.history.get_curr();
+                let until = cpu.scheduled_core().and_then(|core| {
+                    let frame = cpu.cores()[core].get_call_stack().frames().next()?;
+                    Some(RunUntil::Return {
+                        core,
+                        addr: frame.return_address(),
+                        sp: frame.sp,
+                    })
                 });
+                match until {
+                    Some(until) => self.run(&sender, until),
+                    None => sender.input(SimulationMsg::ShowMessage(String::from(
+                        "Not inside a call, there is no frame to step out of",
+                    ))),
+                }
             }
+            SimulationMsg::RunToCursor(line) => match self.source_map.addresses(line).next() {
+                Some(pc) => self.run(&sender, RunUntil::Address(pc)),
+                None => sender.input(SimulationMsg::ShowMessage(format!(
+                    "No instruction on line {}",
+                    line + 1
+                ))),
+            },
+            SimulationMsg::ShowLine(line) => self.asm_view.emit(AsmViewMsg::ShowLine(line)),
+            SimulationMsg::ShowSymbol(symbol) => {
+                self.asm_view.emit(AsmViewMsg::ShowLine(symbol.line));
+                match symbol.kind {
+                    SymbolKind::Text => self.asm_view.emit(AsmViewMsg::ShowAddress(symbol.addr)),
+                    SymbolKind::Data => {
+                        self.simple_view
+                            .emit(CPUViewMessage::ShowAddress(symbol.addr));
+                        show_page(self.simple_view.widget());
+                    }
+                }
+            }
+            SimulationMsg::Run => self.run(&sender, RunUntil::Break),
             SimulationMsg::Break => match &self.app_to_thread {
                 Some(tx) => {
                     if tx.send(()).is_ok() {
@@ -300,28 +572,115 @@ impl FactoryComponent for CPUSimulation {
             SimulationMsg::ResetSimulation => {
                 match parse(&self.curr_asm) {
                     Ok((inst_mem, data_mem)) => {
-                        self.history
-                            .reset(SingleCycleCPU::new_from_memory(inst_mem.clone(), data_mem));
-                        self.asm_view
-                            .emit(AsmViewMsg::LoadFile(self.curr_asm.clone(), inst_mem));
+                        self.load_program(inst_mem.clone(), data_mem);
+                        self.show_source(inst_mem);
                         sender.input(SimulationMsg::UpdateViews);
                     }
                     Err(err) => sender.input(SimulationMsg::ShowMessage(err)),
                 };
             }
             SimulationMsg::UpdateViews => {
-                self.simple_view.emit(CPUViewMessage::Update(Box::new(
-                    self.history.get_curr().clone(),
-                )));
-                self.component_view.emit(CPUViewMessage::Update(Box::new(
-                    self.history.get_curr().clone(),
-                )));
-                self.asm_view.emit(AsmViewMsg::SetLine(
-                    self.history.get_curr().get_register(RegisterKind::RegPC),
-                ));
-                self.asm_view.emit(AsmViewMsg::UpdateTheme);
+                let cpu = self.history.get_curr().clone();
+                let previous = self.history.get_prev().cloned();
+                let dirty = std::mem::take(&mut self.dirty);
+                self.update_views(cpu, previous, dirty);
             }
             SimulationMsg::ResizeHistory(size) => self.history.resize(size),
+            SimulationMsg::SetHaltAddress(address) => {
+                self.halt_address = address;
+                Arc::make_mut(self.history.get_curr()).set_halt_address(address);
+            }
+            SimulationMsg::SetMemoryArchitecture(architecture) => {
+                self.memory_architecture = architecture;
+                if !self.cpu_running && !self.curr_asm.is_empty() {
+                    sender.input(SimulationMsg::ResetSimulation);
+                }
+            }
+            SimulationMsg::ConfigureCaches(data, instruction) => {
+                self.data_cache = data;
+                self.instruction_cache = instruction;
+                if !self.cpu_running && !self.curr_asm.is_empty() {
+                    sender.input(SimulationMsg::ResetSimulation);
+                }
+            }
+            SimulationMsg::SetAddressTranslation(translation) => {
+                self.address_translation = translation;
+                if !self.cpu_running && !self.curr_asm.is_empty() {
+                    sender.input(SimulationMsg::ResetSimulation);
+                }
+            }
+            SimulationMsg::ConfigureBitmapDisplay(config) => {
+                self.bitmap_display = config;
+                if !self.cpu_running && !self.curr_asm.is_empty() {
+                    sender.input(SimulationMsg::ResetSimulation);
+                }
+            }
+            SimulationMsg::ConfigureCores(config) => {
+                self.multicore = config;
+                if !self.cpu_running && !self.curr_asm.is_empty() {
+                    sender.input(SimulationMsg::ResetSimulation);
+                }
+            }
+            SimulationMsg::ToggleBreakpoint(line) => {
+                if self.breakpoints.contains(line) {
+                    self.breakpoints.remove(line);
+                } else if let Err(err) = self.breakpoints.insert(line, None, &self.source_map) {
+                    sender.input(SimulationMsg::ShowMessage(err));
+                }
+                self.show_breakpoints();
+            }
+            SimulationMsg::SetBreakpointCondition(line, condition) => {
+                let condition = match condition.trim() {
+                    "" => Ok(None),
+                    text => text.parse::<Condition>().map(Some),
+                };
+                match condition.and_then(|condition| {
+                    self.breakpoints.insert(line, condition, &self.source_map)
+                }) {
+                    Ok(()) => self.show_breakpoints(),
+                    Err(err) => sender.input(SimulationMsg::ShowMessage(err)),
+                }
+            }
+            SimulationMsg::AddWatchpoint(target, len, trigger, condition) => {
+                match parse_watchpoint(&target, len, trigger, &condition) {
+                    Ok(watchpoint) => {
+                        self.watchpoints.push(watchpoint);
+                        self.show_watchpoints();
+                    }
+                    Err(err) => sender.input(SimulationMsg::ShowMessage(err)),
+                }
+            }
+            SimulationMsg::RemoveWatchpoint(idx) => {
+                if idx < self.watchpoints.len() {
+                    self.watchpoints.remove(idx);
+                }
+                self.show_watchpoints();
+            }
+            SimulationMsg::SetRegister(..) | SimulationMsg::SetMemory(..) if self.cpu_running => {
+                sender.input(SimulationMsg::ShowMessage(String::from(
+                    "Stop the program before editing its state",
+                )));
+            }
+            SimulationMsg::SetRegister(core, reg, value) => {
+                let mut cpu = MulticoreCPU::clone(self.history.get_curr());
+                cpu.set_core_register(core, reg, value);
+                self.append(cpu);
+                sender.input(SimulationMsg::UpdateViews);
+            }
+            SimulationMsg::SetMemory(addr, bytes) => {
+                let mut cpu = MulticoreCPU::clone(self.history.get_curr());
+                let mapped = bytes.iter().enumerate().all(|(offset, byte)| {
+                    cpu.set_memory_byte(addr.wrapping_add(offset as u32), *byte)
+                });
+                if mapped {
+                    self.append(cpu);
+                } else {
+                    sender.input(SimulationMsg::ShowMessage(format!(
+                        "Memory at offset 0x{addr:x} is not mapped"
+                    )));
+                }
+                sender.input(SimulationMsg::UpdateViews);
+            }
             SimulationMsg::ShowSidebar(visible) => self.sidebar_visible = visible,
             SimulationMsg::ChangeRadix(radix) => {
                 self.simple_view.emit(CPUViewMessage::ChangeRadix(radix));
@@ -329,10 +688,12 @@ impl FactoryComponent for CPUSimulation {
             }
             SimulationMsg::Undo => {
                 self.history.undo();
+                self.memory_stale = true;
                 sender.input(SimulationMsg::UpdateViews);
             }
             SimulationMsg::Redo => {
                 self.history.redo();
+                self.memory_stale = true;
                 sender.input(SimulationMsg::UpdateViews);
             }
             SimulationMsg::Ignore => {}
@@ -341,14 +702,194 @@ impl FactoryComponent for CPUSimulation {
 
     fn update_cmd(&mut self, message: Self::CommandOutput, sender: FactorySender<Self>) {
         match message {
+            SimulationCmd::RunProgress(cpu, dirty) => {
+                if self.cpu_running {
+                    let previous = self.history.get_curr().clone();
+                    self.update_views(cpu, Some(previous), dirty);
+                }
+            }
             SimulationCmd::ThreadFinished(cpu) => {
                 self.cpu_running = false;
-                self.history.append(cpu.clone());
+                self.app_to_thread = None;
+                self.asm_view.emit(AsmViewMsg::SetCanSave(true));
+                self.append(cpu);
                 sender.input(SimulationMsg::UpdateViews);
                 if let Some(error) = self.history.get_curr().get_error() {
                     sender.input(SimulationMsg::ShowMessage(error));
                 }
             }
+            SimulationCmd::BreakpointHit(line) => sender.input(SimulationMsg::ShowMessage(
+                format!("Stopped at breakpoint on line {}", line + 1),
+            )),
+            SimulationCmd::WatchpointHit(hit) => {
+                sender.input(SimulationMsg::ShowMessage(hit.to_string()))
+            }
+        }
+    }
+}
+
+impl CPUSimulation {
+    /// Runs the program on a background thread until `until`, a breakpoint
+    /// or a watchpoint stops it, or the user breaks through the channel.
+    fn run(&mut self, sender: &FactorySender<Self>, until: RunUntil) {
+        self.asm_view.emit(AsmViewMsg::SetCanSave(false));
+        let (app_tx, thread_rx) = mpsc::channel::<()>();
+        self.cpu_running = true;
+
+        self.app_to_thread = Some(app_tx);
+
+        let mut cpu_copy = MulticoreCPU::clone(self.history.get_curr());
+        cpu_copy.set_watchpoints(&self.watchpoints);
+        let breakpoints = self.breakpoints.clone();
+        sender.spawn_command(move |out| {
+            let mut last_refresh = Instant::now();
+            while cpu_copy.get_error().is_none() && !cpu_copy.is_halted() {
+                cpu_copy.step();
+                if thread_rx.try_recv().is_ok() {
+                    break;
+                }
+                if let Some(line) = breakpoints.hit(&cpu_copy.cores()[cpu_copy.current_core()]) {
+                    out.emit(SimulationCmd::BreakpointHit(line));
+                    break;
+                }
+                if let Some(hit) = cpu_copy.get_watch_hit() {
+                    out.emit(SimulationCmd::WatchpointHit(hit));
+                    break;
+                }
+                if until.reached(&cpu_copy) {
+                    break;
+                }
+                if last_refresh.elapsed() >= RUN_REFRESH_INTERVAL {
+                    let dirty = cpu_copy.take_dirty_memory();
+                    out.emit(SimulationCmd::RunProgress(
+                        Arc::new(cpu_copy.clone()),
+                        dirty,
+                    ));
+                    last_refresh = Instant::now();
+                }
+            }
+            out.emit(SimulationCmd::ThreadFinished(cpu_copy));
+        });
+    }
+
+    /// Makes `cpu` the current state, keeping the memory it wrote for the
+    /// next view update.
+    fn append(&mut self, mut cpu: MulticoreCPU) {
+        self.dirty.merge(cpu.take_dirty_memory());
+        self.history.append(Arc::new(cpu));
+    }
+
+    fn load_program(&mut self, inst_mem: Vec<u32>, data_mem: Vec<u8>) {
+        self.data_len = data_mem.len();
+        let mut cpu = MulticoreCPU::new_from_memory(inst_mem, data_mem.into(), self.multicore);
+        cpu.set_halt_address(self.halt_address);
+        cpu.set_memory_architecture(self.memory_architecture);
+        cpu.configure_caches(self.data_cache, self.instruction_cache);
+        cpu.set_address_translation(self.address_translation);
+        cpu.attach_device(Box::new(KeyboardDisplay::new()));
+        cpu.attach_device(Box::new(Timer::new()));
+        if let Some(config) = self.bitmap_display {
+            cpu.attach_device(Box::new(BitmapDisplay::new(config)));
         }
+        self.history.reset(Arc::new(cpu));
+        self.dirty = DirtyRegions::default();
+        self.memory_stale = true;
+    }
+
+    /// Shows the current source and its machine code in the editor and
+    /// relates both to the profile and the breakpoints.
+    fn show_source(&mut self, inst_mem: Vec<u32>) {
+        self.source_map = SourceMap::new(&self.curr_asm, &inst_mem, self.data_len);
+        self.breakpoints.remap(&self.source_map);
+        self.asm_view.emit(AsmViewMsg::LoadFile(
+            self.curr_asm.clone(),
+            inst_mem,
+            self.source_map.clone(),
+        ));
+        self.profile_view.emit(ProfileMsg::LoadSource(
+            self.curr_asm.clone(),
+            self.source_map.clone(),
+        ));
+        self.call_stack_view
+            .emit(CallStackMsg::LoadSource(self.source_map.clone()));
+        self.stack_view
+            .emit(StackMsg::LoadSource(self.source_map.clone()));
+        self.symbol_view
+            .emit(SymbolMsg::LoadSource(self.source_map.clone()));
+        self.simple_view.emit(CPUViewMessage::SetLabels(
+            self.source_map.data_labels().to_vec(),
+        ));
+        self.show_breakpoints();
+    }
+
+    fn show_breakpoints(&self) {
+        self.asm_view.emit(AsmViewMsg::SetBreakpoints(
+            self.breakpoints
+                .iter()
+                .map(|(line, condition)| (line, condition.map(|c| c.to_string())))
+                .collect(),
+        ));
+        self.breakpoint_view.emit(BreakpointViewMsg::SetBreakpoints(
+            self.breakpoints
+                .iter()
+                .map(|(line, condition)| {
+                    (
+                        line,
+                        self.curr_asm
+                            .lines()
+                            .nth(line)
+                            .unwrap_or_default()
+                            .to_string(),
+                        condition.map(|c| c.to_string()),
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    fn show_watchpoints(&self) {
+        self.breakpoint_view.emit(BreakpointViewMsg::SetWatchpoints(
+            self.watchpoints.iter().map(|w| w.to_string()).collect(),
+        ));
+    }
+
+    /// Shows `cpu` in every view, highlighting what changed since `previous`.
+    /// Only the memory in `dirty` is redrawn unless the views are stale.
+    fn update_views(
+        &mut self,
+        cpu: Arc<MulticoreCPU>,
+        previous: Option<Arc<MulticoreCPU>>,
+        dirty: DirtyRegions,
+    ) {
+        let dirty = (!std::mem::take(&mut self.memory_stale)).then_some(dirty);
+        self.exit_status = cpu.get_halt_reason().map(|reason| reason.to_string());
+        self.simple_view.emit(CPUViewMessage::Previous(previous));
+        self.simple_view.emit(CPUViewMessage::DirtyMemory(dirty));
+        self.simple_view.emit(CPUViewMessage::Update(cpu.clone()));
+        self.component_view
+            .emit(CPUViewMessage::Update(cpu.clone()));
+        self.cache_view.emit(CPUViewMessage::Update(cpu.clone()));
+        self.bitmap_view.emit(CPUViewMessage::Update(cpu.clone()));
+        self.statistics_view
+            .emit(CPUViewMessage::Update(cpu.clone()));
+        self.asm_view
+            .emit(AsmViewMsg::SetLine(cpu.get_register(RegisterKind::RegPC)));
+        self.asm_view
+            .emit(AsmViewMsg::SetProfile(cpu.get_profile()));
+        self.asm_view.emit(AsmViewMsg::UpdateTheme);
+        self.profile_view.emit(ProfileMsg::Update(cpu.clone()));
+        self.call_stack_view.emit(CallStackMsg::Update(cpu.clone()));
+        self.stack_view.emit(StackMsg::Update(cpu.clone()));
+        self.console_view.emit(ConsoleMsg::Update(cpu));
+    }
+}
+
+/// Switches the view stack holding `page` to it.
+fn show_page(page: &impl IsA<gtk::Widget>) {
+    if let Some(stack) = page
+        .ancestor(adw::ViewStack::static_type())
+        .and_then(|stack| stack.downcast::<adw::ViewStack>().ok())
+    {
+        stack.set_visible_child(page);
     }
 }
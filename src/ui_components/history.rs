@@ -19,16 +19,21 @@ impl<T: Clone + Default> History<T> {
         &mut self.curr
     }
 
+    /// The entry before the current one.
+    pub fn get_prev(&self) -> Option<&T> {
+        self.undo.last()
+    }
+
     pub fn append(&mut self, elem: T) {
         self.redo.clear();
 
+        let prev = std::mem::replace(&mut self.curr, elem);
         if self.undo.len() >= self.size {
             self.undo.rotate_left(1);
-            self.undo[self.size - 1] = self.curr.clone();
+            self.undo[self.size - 1] = prev;
         } else {
-            self.undo.push(self.curr.clone());
+            self.undo.push(prev);
         }
-        self.curr = elem;
     }
 
     pub fn reset(&mut self, elem: T) {
@@ -49,15 +54,13 @@ impl<T: Clone + Default> History<T> {
 
     pub fn undo(&mut self) {
         if let Some(elem) = self.undo.pop() {
-            self.redo.push(self.curr.clone());
-            self.curr = elem;
+            self.redo.push(std::mem::replace(&mut self.curr, elem));
         }
     }
 
     pub fn redo(&mut self) {
         if let Some(elem) = self.redo.pop() {
-            self.undo.push(self.curr.clone());
-            self.curr = elem;
+            self.undo.push(std::mem::replace(&mut self.curr, elem));
         }
     }
 
@@ -1,20 +1,48 @@
-use crate::cpu::single_cycle_cpu::SingleCycleCPU;
+use std::sync::Arc;
+
+use crate::cpu::{
+    cpu_interface::RegisterKind, dirty_regions::DirtyRegions, multicore::MulticoreCPU,
+};
 
 use self::column_views::Radices;
 
 pub mod asm_view;
+pub mod bitmap_view;
+pub mod breakpoint_view;
+pub mod cache_view;
+pub mod call_stack_view;
 pub mod column_views;
 pub mod component_view;
+pub mod console_view;
 pub mod cpu_simulation;
 pub mod history;
 pub mod preferences;
+pub mod profile_view;
 pub mod simple_view;
+pub mod stack_view;
+pub mod statistics_view;
+pub mod symbol_view;
 
 #[derive(Debug)]
 pub enum CPUViewMessage {
-    Update(Box<SingleCycleCPU>),
+    Update(Arc<MulticoreCPU>),
+    /// The state before the last step, sent ahead of `Update` so views can
+    /// show what changed.
+    Previous(Option<Arc<MulticoreCPU>>),
+    /// Memory written since the last `Update`, sent ahead of it. `None` when
+    /// all of memory may have changed, such as after a reset or an undo.
+    DirtyMemory(Option<DirtyRegions>),
     ChangeRadix(Radices),
     Resize((i32, i32)),
+    SelectCore(usize),
+    /// A register of the shown core was edited.
+    SetRegister(RegisterKind, u32),
+    /// Memory was edited, from an offset into `.data`.
+    SetMemory(u32, Vec<u8>),
+    /// Data labels with their addresses.
+    SetLabels(Vec<(String, u32)>),
+    /// Scrolls memory to an absolute address.
+    ShowAddress(u32),
     None,
 }
 
@@ -2,6 +2,59 @@ use relm4::{adw::prelude::*, prelude::*};
 use relm4_icons::icon_name;
 
 use super::column_views::Radices;
+use crate::cpu::{
+    cache::{CacheConfig, ReplacementPolicy, WritePolicy},
+    data_memory::MemoryArchitecture,
+    devices::bitmap_display::BitmapConfig,
+    mmu::{AddressTranslation, TlbConfig},
+    multicore::{Interleaving, MulticoreConfig},
+};
+
+const CACHE_SIZES: [u32; 8] = [256, 512, 1024, 2048, 4096, 8192, 16384, 32768];
+const CACHE_SIZE_NAMES: [&str; 8] = [
+    "256 B", "512 B", "1 KiB", "2 KiB", "4 KiB", "8 KiB", "16 KiB", "32 KiB",
+];
+const BLOCK_SIZES: [u32; 6] = [4, 8, 16, 32, 64, 128];
+const BLOCK_SIZE_NAMES: [&str; 6] = ["4 B", "8 B", "16 B", "32 B", "64 B", "128 B"];
+const ASSOCIATIVITIES: [u32; 6] = [1, 2, 4, 8, 16, u32::MAX];
+const ASSOCIATIVITY_NAMES: [&str; 6] = [
+    "Direct mapped",
+    "2-way",
+    "4-way",
+    "8-way",
+    "16-way",
+    "Fully associative",
+];
+const TLB_ENTRIES: [u32; 5] = [4, 8, 16, 32, 64];
+const TLB_ENTRY_NAMES: [&str; 5] = ["4", "8", "16", "32", "64"];
+const CORE_COUNTS: [usize; 4] = [1, 2, 4, 8];
+const CORE_COUNT_NAMES: [&str; 4] = ["1", "2", "4", "8"];
+const QUANTUMS: [u32; 4] = [1, 10, 100, 1000];
+const QUANTUM_NAMES: [&str; 4] = ["1", "10", "100", "1000"];
+const BITMAP_UNIT_SIZES: [u32; 6] = [1, 2, 4, 8, 16, 32];
+const BITMAP_UNIT_SIZE_NAMES: [&str; 6] = ["1", "2", "4", "8", "16", "32"];
+const BITMAP_DIMENSIONS: [u32; 5] = [64, 128, 256, 512, 1024];
+const BITMAP_DIMENSION_NAMES: [&str; 5] = ["64", "128", "256", "512", "1024"];
+/// Framebuffer bases MARS offers, except the memory map one, where the
+/// keyboard, display and timer registers already are.
+const BITMAP_BASES: [u32; 4] = [0x10000000, 0x10008000, 0x10010000, 0x10040000];
+const BITMAP_BASE_NAMES: [&str; 4] = [
+    "0x10000000 (global data)",
+    "0x10008000 ($gp)",
+    "0x10010000 (static data)",
+    "0x10040000 (heap)",
+];
+
+/// The halt address typed in `text`, `Some(None)` for an empty field and
+/// `None` if it is not a hex address, which leaves the current one set.
+fn parse_halt_address(text: &str) -> Option<Option<u32>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(None);
+    }
+    let hex = text.strip_prefix("0x").unwrap_or(text);
+    u32::from_str_radix(hex, 16).ok().map(Some)
+}
 
 #[derive(Debug)]
 pub enum ColorScheme {
@@ -15,6 +68,19 @@ pub struct Preferences {
     color_scheme: ColorScheme,
     pub history_size: usize,
     pub radix: Radices,
+    pub halt_address: Option<u32>,
+    pub memory_architecture: MemoryArchitecture,
+    pub cache_config: CacheConfig,
+    pub data_cache: bool,
+    pub instruction_cache: bool,
+    pub tlb_config: TlbConfig,
+    pub tlb: bool,
+    pub bitmap_config: BitmapConfig,
+    pub bitmap_display: bool,
+    pub cores: usize,
+    pub random_interleaving: bool,
+    pub quantum: u32,
+    pub seed: u64,
 }
 
 #[derive(Debug)]
@@ -22,12 +88,39 @@ pub enum UpdatePreferencesInput {
     ColorScheme(ColorScheme),
     HistorySize(usize),
     Radix(Radices),
+    HaltAddress(Option<u32>),
+    MemoryArchitecture(MemoryArchitecture),
+    DataCache(bool),
+    InstructionCache(bool),
+    CacheSize(u32),
+    CacheBlockSize(u32),
+    CacheAssociativity(u32),
+    CacheReplacement(ReplacementPolicy),
+    CacheWritePolicy(WritePolicy),
+    Tlb(bool),
+    TlbEntries(u32),
+    TlbAssociativity(u32),
+    BitmapDisplay(bool),
+    BitmapUnitSize(u32),
+    BitmapWidth(u32),
+    BitmapHeight(u32),
+    BitmapBase(u32),
+    Cores(usize),
+    RandomInterleaving(bool),
+    Quantum(u32),
+    Seed(u64),
 }
 
 #[derive(Debug)]
 pub enum UpdatePreferencesOutput {
     HistorySize(usize),
     Radix(Radices),
+    HaltAddress(Option<u32>),
+    MemoryArchitecture(MemoryArchitecture),
+    Caches(Option<CacheConfig>, Option<CacheConfig>),
+    AddressTranslation(AddressTranslation),
+    BitmapDisplay(Option<BitmapConfig>),
+    Multicore(MulticoreConfig),
     Theme,
 }
 
@@ -96,6 +189,271 @@ impl Component for Preferences {
                                     },
                                 },
                             },
+                            add = &adw::PreferencesGroup {
+                                set_title: "Simulation",
+                                adw::EntryRow {
+                                    set_title: "Halt Address (hex, empty for none)",
+                                    set_show_apply_button: true,
+                                    connect_apply[sender] => move |entry_row| {
+                                        match parse_halt_address(&entry_row.text()) {
+                                            Some(address) => {
+                                                entry_row.remove_css_class("error");
+                                                sender.input_sender()
+                                                    .send(UpdatePreferencesInput::HaltAddress(address))
+                                                    .unwrap();
+                                            }
+                                            None => entry_row.add_css_class("error"),
+                                        }
+                                    },
+                                },
+                                adw::ComboRow {
+                                    set_title: "Memory Architecture",
+                                    set_subtitle: "Changing this resets the simulation",
+                                    set_model: Some(&gtk::StringList::new(&[
+                                        "Harvard",
+                                        "Von Neumann",
+                                        "Von Neumann (read-only text)",
+                                    ])),
+                                    set_selected: match model.memory_architecture {
+                                        MemoryArchitecture::Harvard => 0,
+                                        MemoryArchitecture::VonNeumann { protect_text: false } => 1,
+                                        MemoryArchitecture::VonNeumann { protect_text: true } => 2,
+                                    },
+                                    connect_selected_notify[sender] => move |combo_row| {
+                                        let architecture = match combo_row.selected() {
+                                            0 => MemoryArchitecture::Harvard,
+                                            1 => MemoryArchitecture::VonNeumann { protect_text: false },
+                                            _ => MemoryArchitecture::VonNeumann { protect_text: true },
+                                        };
+                                        sender.input_sender().send(UpdatePreferencesInput::MemoryArchitecture(architecture)).unwrap()
+                                    }
+                                },
+                            },
+                            add = &adw::PreferencesGroup {
+                                set_title: "Cache",
+                                set_description: Some("Changing these resets the simulation"),
+                                adw::ExpanderRow {
+                                    set_title: "Data Cache",
+                                    set_show_enable_switch: true,
+                                    set_enable_expansion: model.data_cache,
+                                    connect_enable_expansion_notify[sender] => move |row| {
+                                        sender.input_sender().send(UpdatePreferencesInput::DataCache(row.enables_expansion())).unwrap()
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Size",
+                                        set_model: Some(&gtk::StringList::new(&CACHE_SIZE_NAMES)),
+                                        set_selected: CACHE_SIZES.iter().position(|size| *size == model.cache_config.size).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(size) = CACHE_SIZES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::CacheSize(*size)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Block Size",
+                                        set_model: Some(&gtk::StringList::new(&BLOCK_SIZE_NAMES)),
+                                        set_selected: BLOCK_SIZES.iter().position(|size| *size == model.cache_config.block_size).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(size) = BLOCK_SIZES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::CacheBlockSize(*size)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Associativity",
+                                        set_model: Some(&gtk::StringList::new(&ASSOCIATIVITY_NAMES)),
+                                        set_selected: ASSOCIATIVITIES.iter().position(|ways| *ways == model.cache_config.associativity).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(ways) = ASSOCIATIVITIES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::CacheAssociativity(*ways)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Replacement Policy",
+                                        set_model: Some(&gtk::StringList::new(&[
+                                            "LRU",
+                                            "FIFO",
+                                            "Random",
+                                        ])),
+                                        set_selected: match model.cache_config.replacement {
+                                            ReplacementPolicy::Lru => 0,
+                                            ReplacementPolicy::Fifo => 1,
+                                            ReplacementPolicy::Random => 2,
+                                        },
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            match combo_row.selected() {
+                                            0 => sender.input_sender().send(UpdatePreferencesInput::CacheReplacement(ReplacementPolicy::Lru)).unwrap(),
+                                            1 => sender.input_sender().send(UpdatePreferencesInput::CacheReplacement(ReplacementPolicy::Fifo)).unwrap(),
+                                            _ => sender.input_sender().send(UpdatePreferencesInput::CacheReplacement(ReplacementPolicy::Random)).unwrap(),
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Write Policy",
+                                        set_model: Some(&gtk::StringList::new(&[
+                                            "Write-back, write-allocate",
+                                            "Write-through, no write-allocate",
+                                        ])),
+                                        set_selected: match model.cache_config.write_policy {
+                                            WritePolicy::WriteBack => 0,
+                                            WritePolicy::WriteThrough => 1,
+                                        },
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            match combo_row.selected() {
+                                            0 => sender.input_sender().send(UpdatePreferencesInput::CacheWritePolicy(WritePolicy::WriteBack)).unwrap(),
+                                            _ => sender.input_sender().send(UpdatePreferencesInput::CacheWritePolicy(WritePolicy::WriteThrough)).unwrap(),
+                                            }
+                                        }
+                                    },
+                                },
+                                adw::ActionRow {
+                                    set_title: "Instruction Cache",
+                                    set_subtitle: "Uses the same geometry as the data cache",
+                                    add_suffix = &gtk::Switch {
+                                        set_valign: gtk::Align::Center,
+                                        set_active: model.instruction_cache,
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input_sender().send(UpdatePreferencesInput::InstructionCache(switch.is_active())).unwrap()
+                                        }
+                                    },
+                                },
+                            },
+                            add = &adw::PreferencesGroup {
+                                set_title: "Virtual Memory",
+                                set_description: Some("Changing these resets the simulation"),
+                                adw::ExpanderRow {
+                                    set_title: "Software TLB",
+                                    set_subtitle: "Translate user addresses through a TLB filled by exception handlers",
+                                    set_show_enable_switch: true,
+                                    set_enable_expansion: model.tlb,
+                                    connect_enable_expansion_notify[sender] => move |row| {
+                                        sender.input_sender().send(UpdatePreferencesInput::Tlb(row.enables_expansion())).unwrap()
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Entries",
+                                        set_model: Some(&gtk::StringList::new(&TLB_ENTRY_NAMES)),
+                                        set_selected: TLB_ENTRIES.iter().position(|entries| *entries == model.tlb_config.entries).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(entries) = TLB_ENTRIES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::TlbEntries(*entries)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Associativity",
+                                        set_model: Some(&gtk::StringList::new(&ASSOCIATIVITY_NAMES)),
+                                        set_selected: ASSOCIATIVITIES.iter().position(|ways| *ways == model.tlb_config.associativity).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(ways) = ASSOCIATIVITIES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::TlbAssociativity(*ways)).unwrap()
+                                            }
+                                        }
+                                    },
+                                },
+                            },
+                            add = &adw::PreferencesGroup {
+                                set_title: "Multicore",
+                                set_description: Some("Changing these resets the simulation"),
+                                adw::ComboRow {
+                                    set_title: "Cores",
+                                    set_subtitle: "Every core runs the program against the same memory",
+                                    set_model: Some(&gtk::StringList::new(&CORE_COUNT_NAMES)),
+                                    set_selected: CORE_COUNTS.iter().position(|cores| *cores == model.cores).unwrap_or_default() as u32,
+                                    connect_selected_notify[sender] => move |combo_row| {
+                                        if let Some(cores) = CORE_COUNTS.get(combo_row.selected() as usize) {
+                                            sender.input_sender().send(UpdatePreferencesInput::Cores(*cores)).unwrap()
+                                        }
+                                    }
+                                },
+                                adw::ComboRow {
+                                    set_title: "Interleaving",
+                                    set_model: Some(&gtk::StringList::new(&[
+                                        "Round robin",
+                                        "Seeded random",
+                                    ])),
+                                    set_selected: model.random_interleaving as u32,
+                                    connect_selected_notify[sender] => move |combo_row| {
+                                        sender.input_sender().send(UpdatePreferencesInput::RandomInterleaving(combo_row.selected() == 1)).unwrap()
+                                    }
+                                },
+                                adw::ComboRow {
+                                    set_title: "Instructions per Turn",
+                                    set_subtitle: "Used by round robin interleaving",
+                                    set_model: Some(&gtk::StringList::new(&QUANTUM_NAMES)),
+                                    set_selected: QUANTUMS.iter().position(|quantum| *quantum == model.quantum).unwrap_or_default() as u32,
+                                    connect_selected_notify[sender] => move |combo_row| {
+                                        if let Some(quantum) = QUANTUMS.get(combo_row.selected() as usize) {
+                                            sender.input_sender().send(UpdatePreferencesInput::Quantum(*quantum)).unwrap()
+                                        }
+                                    }
+                                },
+                                adw::EntryRow {
+                                    set_title: "Random Seed",
+                                    set_text: &model.seed.to_string(),
+                                    set_input_purpose: gtk::InputPurpose::Digits,
+                                    set_show_apply_button: true,
+                                    connect_apply[sender] => move |entry_row| {
+                                        sender.input_sender()
+                                            .send(UpdatePreferencesInput::Seed(
+                                                entry_row.text().to_string().parse::<u64>().unwrap_or(1)
+                                            )).unwrap();
+                                    },
+                                },
+                            },
+                            add = &adw::PreferencesGroup {
+                                set_title: "Devices",
+                                set_description: Some("Changing these resets the simulation"),
+                                adw::ExpanderRow {
+                                    set_title: "Bitmap Display",
+                                    set_subtitle: "Framebuffer of 0x00RRGGBB words, one per unit",
+                                    set_show_enable_switch: true,
+                                    set_enable_expansion: model.bitmap_display,
+                                    connect_enable_expansion_notify[sender] => move |row| {
+                                        sender.input_sender().send(UpdatePreferencesInput::BitmapDisplay(row.enables_expansion())).unwrap()
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Unit Size in Pixels",
+                                        set_model: Some(&gtk::StringList::new(&BITMAP_UNIT_SIZE_NAMES)),
+                                        set_selected: BITMAP_UNIT_SIZES.iter().position(|value| *value == model.bitmap_config.unit_size).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(value) = BITMAP_UNIT_SIZES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::BitmapUnitSize(*value)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Display Width in Pixels",
+                                        set_model: Some(&gtk::StringList::new(&BITMAP_DIMENSION_NAMES)),
+                                        set_selected: BITMAP_DIMENSIONS.iter().position(|value| *value == model.bitmap_config.width).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(value) = BITMAP_DIMENSIONS.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::BitmapWidth(*value)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Display Height in Pixels",
+                                        set_model: Some(&gtk::StringList::new(&BITMAP_DIMENSION_NAMES)),
+                                        set_selected: BITMAP_DIMENSIONS.iter().position(|value| *value == model.bitmap_config.height).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(value) = BITMAP_DIMENSIONS.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::BitmapHeight(*value)).unwrap()
+                                            }
+                                        }
+                                    },
+                                    add_row = &adw::ComboRow {
+                                        set_title: "Base Address",
+                                        set_model: Some(&gtk::StringList::new(&BITMAP_BASE_NAMES)),
+                                        set_selected: BITMAP_BASES.iter().position(|value| *value == model.bitmap_config.base).unwrap_or_default() as u32,
+                                        connect_selected_notify[sender] => move |combo_row| {
+                                            if let Some(value) = BITMAP_BASES.get(combo_row.selected() as usize) {
+                                                sender.input_sender().send(UpdatePreferencesInput::BitmapBase(*value)).unwrap()
+                                            }
+                                        }
+                                    },
+                                },
+                            },
                             add = &adw::PreferencesGroup {
                                 set_title: "Display",
                                 adw::ComboRow {
@@ -138,6 +496,22 @@ impl Component for Preferences {
             color_scheme: ColorScheme::Default,
             history_size: 10,
             radix: Radices::Hex,
+            halt_address: None,
+            memory_architecture: MemoryArchitecture::default(),
+            cache_config: CacheConfig::default(),
+            data_cache: false,
+            instruction_cache: false,
+            tlb_config: TlbConfig {
+                entries: 16,
+                associativity: u32::MAX,
+            },
+            tlb: false,
+            bitmap_config: BitmapConfig::default(),
+            bitmap_display: false,
+            cores: 1,
+            random_interleaving: false,
+            quantum: 1,
+            seed: 1,
         };
 
         let widgets = view_output!();
@@ -170,6 +544,98 @@ impl Component for Preferences {
                         .unwrap();
                 }
             }
+            UpdatePreferencesInput::HaltAddress(address) => {
+                if self.halt_address != address {
+                    self.halt_address = address;
+                    sender
+                        .output(UpdatePreferencesOutput::HaltAddress(address))
+                        .unwrap();
+                }
+            }
+            UpdatePreferencesInput::MemoryArchitecture(architecture) => {
+                if self.memory_architecture != architecture {
+                    self.memory_architecture = architecture;
+                    sender
+                        .output(UpdatePreferencesOutput::MemoryArchitecture(architecture))
+                        .unwrap();
+                }
+            }
+            UpdatePreferencesInput::DataCache(enabled) => {
+                self.data_cache = enabled;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::InstructionCache(enabled) => {
+                self.instruction_cache = enabled;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::CacheSize(size) => {
+                self.cache_config.size = size;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::CacheBlockSize(block_size) => {
+                self.cache_config.block_size = block_size;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::CacheAssociativity(associativity) => {
+                self.cache_config.associativity = associativity;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::CacheReplacement(replacement) => {
+                self.cache_config.replacement = replacement;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::CacheWritePolicy(write_policy) => {
+                self.cache_config.write_policy = write_policy;
+                self.emit_caches(&sender);
+            }
+            UpdatePreferencesInput::Tlb(enabled) => {
+                self.tlb = enabled;
+                self.emit_address_translation(&sender);
+            }
+            UpdatePreferencesInput::TlbEntries(entries) => {
+                self.tlb_config.entries = entries;
+                self.emit_address_translation(&sender);
+            }
+            UpdatePreferencesInput::TlbAssociativity(associativity) => {
+                self.tlb_config.associativity = associativity;
+                self.emit_address_translation(&sender);
+            }
+            UpdatePreferencesInput::BitmapDisplay(enabled) => {
+                self.bitmap_display = enabled;
+                self.emit_bitmap_display(&sender);
+            }
+            UpdatePreferencesInput::BitmapUnitSize(unit_size) => {
+                self.bitmap_config.unit_size = unit_size;
+                self.emit_bitmap_display(&sender);
+            }
+            UpdatePreferencesInput::BitmapWidth(width) => {
+                self.bitmap_config.width = width;
+                self.emit_bitmap_display(&sender);
+            }
+            UpdatePreferencesInput::BitmapHeight(height) => {
+                self.bitmap_config.height = height;
+                self.emit_bitmap_display(&sender);
+            }
+            UpdatePreferencesInput::BitmapBase(base) => {
+                self.bitmap_config.base = base;
+                self.emit_bitmap_display(&sender);
+            }
+            UpdatePreferencesInput::Cores(cores) => {
+                self.cores = cores;
+                self.emit_multicore(&sender);
+            }
+            UpdatePreferencesInput::RandomInterleaving(random) => {
+                self.random_interleaving = random;
+                self.emit_multicore(&sender);
+            }
+            UpdatePreferencesInput::Quantum(quantum) => {
+                self.quantum = quantum;
+                self.emit_multicore(&sender);
+            }
+            UpdatePreferencesInput::Seed(seed) => {
+                self.seed = seed;
+                self.emit_multicore(&sender);
+            }
             UpdatePreferencesInput::Radix(radix) => {
                 if self.radix != radix {
                     self.radix = radix;
@@ -181,3 +647,66 @@ impl Component for Preferences {
         }
     }
 }
+
+impl Preferences {
+    pub fn caches(&self) -> (Option<CacheConfig>, Option<CacheConfig>) {
+        (
+            self.data_cache.then_some(self.cache_config),
+            self.instruction_cache.then_some(self.cache_config),
+        )
+    }
+
+    fn emit_caches(&self, sender: &ComponentSender<Self>) {
+        let (data, instruction) = self.caches();
+        sender
+            .output(UpdatePreferencesOutput::Caches(data, instruction))
+            .unwrap();
+    }
+
+    pub fn address_translation(&self) -> AddressTranslation {
+        if self.tlb {
+            AddressTranslation::Tlb(self.tlb_config)
+        } else {
+            AddressTranslation::Identity
+        }
+    }
+
+    fn emit_address_translation(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(UpdatePreferencesOutput::AddressTranslation(
+                self.address_translation(),
+            ))
+            .unwrap();
+    }
+
+    pub fn bitmap_display(&self) -> Option<BitmapConfig> {
+        self.bitmap_display.then_some(self.bitmap_config)
+    }
+
+    fn emit_bitmap_display(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(UpdatePreferencesOutput::BitmapDisplay(
+                self.bitmap_display(),
+            ))
+            .unwrap();
+    }
+
+    pub fn multicore(&self) -> MulticoreConfig {
+        MulticoreConfig {
+            cores: self.cores,
+            interleaving: if self.random_interleaving {
+                Interleaving::Random { seed: self.seed }
+            } else {
+                Interleaving::RoundRobin {
+                    quantum: self.quantum,
+                }
+            },
+        }
+    }
+
+    fn emit_multicore(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(UpdatePreferencesOutput::Multicore(self.multicore()))
+            .unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::multicore::MulticoreCPU;
+use crate::cpu::profiler::Profile;
+use crate::source_map::SourceMap;
+
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+/// Rows shown in the hottest instruction and source line tables.
+const HOTTEST_ROWS: usize = 20;
+/// Blocks shown in the histogram, the most entered ones.
+const BLOCK_ROWS: usize = 50;
+
+#[derive(Debug)]
+pub enum ProfileMsg {
+    Update(Arc<MulticoreCPU>),
+    LoadSource(String, SourceMap),
+}
+
+/// A titled grid whose rows are replaced on every update. A row may end
+/// with a bar showing a fraction.
+struct Table {
+    root: gtk::Box,
+    grid: gtk::Grid,
+    headings: &'static [&'static str],
+}
+
+impl Table {
+    fn new(title: &str, headings: &'static [&'static str]) -> Self {
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let heading = gtk::Label::builder()
+            .label(title)
+            .xalign(0.0)
+            .css_classes(["title-4"])
+            .build();
+        let grid = gtk::Grid::builder()
+            .row_spacing(2)
+            .column_spacing(15)
+            .build();
+        root.append(&heading);
+        root.append(&grid);
+
+        Self {
+            root,
+            grid,
+            headings,
+        }
+    }
+
+    fn fill(&self, rows: impl Iterator<Item = (Vec<String>, Option<f64>)>) {
+        while let Some(child) = self.grid.first_child() {
+            self.grid.remove(&child);
+        }
+
+        self.headings.iter().enumerate().for_each(|(column, name)| {
+            let label = gtk::Label::builder()
+                .label(*name)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build();
+            self.grid.attach(&label, column as i32, 0, 1, 1);
+        });
+
+        rows.enumerate().for_each(|(row, (cells, bar))| {
+            let row = row as i32 + 1;
+            cells.iter().enumerate().for_each(|(column, cell)| {
+                let label = gtk::Label::builder()
+                    .label(cell)
+                    .xalign(0.0)
+                    .css_classes(["monospace"])
+                    .build();
+                self.grid.attach(&label, column as i32, row, 1, 1);
+            });
+            if let Some(fraction) = bar {
+                let bar = gtk::LevelBar::builder()
+                    .value(fraction)
+                    .width_request(200)
+                    .valign(gtk::Align::Center)
+                    .build();
+                self.grid.attach(&bar, cells.len() as i32, row, 1, 1);
+            }
+        });
+    }
+}
+
+/// Where the program spends its instructions: hottest instructions and
+/// source lines, the instruction mix and a histogram of basic blocks.
+pub struct ProfileView {
+    source_lines: Vec<String>,
+    source_map: SourceMap,
+    instructions: Table,
+    lines: Table,
+    mix: Table,
+    blocks: Table,
+    has_profile: bool,
+}
+
+impl ProfileView {
+    fn source(&self, pc: u32) -> (String, String) {
+        match self.source_map.line(pc) {
+            Some(line) => (
+                (line + 1).to_string(),
+                self.source_lines
+                    .get(line)
+                    .map(|text| text.trim().to_string())
+                    .unwrap_or_default(),
+            ),
+            None => (String::from("-"), String::new()),
+        }
+    }
+
+    fn show(&self, profile: &Profile) {
+        let total = profile.total().max(1) as f64;
+        let share = |count: u64| format!("{:.1}%", count as f64 / total * 100.0);
+
+        self.instructions.fill(
+            profile
+                .hottest(HOTTEST_ROWS)
+                .into_iter()
+                .map(|(pc, count)| {
+                    let (line, text) = self.source(pc);
+                    (
+                        vec![
+                            format!("0x{pc:08x}"),
+                            line,
+                            text,
+                            count.to_string(),
+                            share(count),
+                        ],
+                        None,
+                    )
+                }),
+        );
+
+        let mut lines = BTreeMap::<usize, u64>::new();
+        profile.pcs().for_each(|(pc, count)| {
+            if let Some(line) = self.source_map.line(pc) {
+                *lines.entry(line).or_default() += count;
+            }
+        });
+        let mut lines = lines.into_iter().collect::<Vec<_>>();
+        lines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        self.lines
+            .fill(lines.into_iter().take(HOTTEST_ROWS).map(|(line, count)| {
+                let text = self
+                    .source_lines
+                    .get(line)
+                    .map(|text| text.trim().to_string())
+                    .unwrap_or_default();
+                (
+                    vec![
+                        (line + 1).to_string(),
+                        text,
+                        count.to_string(),
+                        share(count),
+                    ],
+                    None,
+                )
+            }));
+
+        self.mix
+            .fill(profile.mnemonics().into_iter().map(|(name, count)| {
+                (
+                    vec![name.to_string(), count.to_string(), share(count)],
+                    None,
+                )
+            }));
+
+        let mut blocks = profile.blocks().collect::<Vec<_>>();
+        blocks.sort_by(|a, b| b.1.entries.cmp(&a.1.entries).then(a.0.cmp(&b.0)));
+        blocks.truncate(BLOCK_ROWS);
+        blocks.sort_by_key(|(leader, _)| *leader);
+        let most_entries = blocks
+            .iter()
+            .map(|(_, block)| block.entries)
+            .max()
+            .unwrap_or(1) as f64;
+        self.blocks.fill(blocks.into_iter().map(|(leader, block)| {
+            let start = match self.source_map.label(leader) {
+                Some(label) => format!("0x{leader:08x} {label}"),
+                None => format!("0x{leader:08x}"),
+            };
+            (
+                vec![
+                    start,
+                    format!("0x{:08x}", block.end),
+                    self.source(leader).0,
+                    block.entries.to_string(),
+                ],
+                Some(block.entries as f64 / most_entries),
+            )
+        }));
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ProfileView {
+    type Input = ProfileMsg;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::Stack {
+            add_named[Some("empty")] = &adw::StatusPage {
+                set_icon_name: Some("find-location-symbolic"),
+                set_title: "No Profile Yet",
+                set_description: Some("Step or run the program to count where it spends its time"),
+            },
+            add_named[Some("profile")] = &gtk::ScrolledWindow {
+                set_hexpand: true,
+                set_vexpand: true,
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 20,
+                    set_margin_all: 10,
+                    append: &model.instructions.root,
+                    append: &model.lines.root,
+                    append: &model.mix.root,
+                    append: &model.blocks.root,
+                },
+            },
+            #[watch]
+            set_visible_child_name: if model.has_profile { "profile" } else { "empty" },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = ProfileView {
+            source_lines: Vec::new(),
+            source_map: SourceMap::default(),
+            instructions: Table::new(
+                "Hottest Instructions",
+                &["Address", "Line", "Source", "Count", "Share"],
+            ),
+            lines: Table::new(
+                "Hottest Source Lines",
+                &["Line", "Source", "Count", "Share"],
+            ),
+            mix: Table::new("Instruction Mix", &["Instruction", "Count", "Share"]),
+            blocks: Table::new("Basic Blocks", &["Start", "End", "Line", "Entries", ""]),
+            has_profile: false,
+        };
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            ProfileMsg::Update(cpu) => {
+                let profile = cpu.get_profile();
+                self.has_profile = profile.total() > 0;
+                self.show(&profile);
+            }
+            ProfileMsg::LoadSource(source, source_map) => {
+                self.source_lines = source.lines().map(String::from).collect();
+                self.source_map = source_map;
+            }
+        }
+    }
+}
@@ -1,4 +1,13 @@
-use crate::cpu::cpu_interface::CPUInterface;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::cpu::{
+    cp0::CP0_NAMES,
+    cpu_interface::{CPUInterface, RegisterKind},
+    dirty_regions::DirtyRegions,
+    multicore::MulticoreCPU,
+    DATA_MEM_START,
+};
 
 use super::column_views::{memory_view::*, register_view::*};
 use super::CPUViewMessage;
@@ -7,25 +16,55 @@ use num::FromPrimitive;
 use relm4::gtk::traits::BoxExt;
 use relm4::prelude::*;
 
+#[derive(Debug)]
+pub enum SimpleViewOutput {
+    /// Core, register and its new value.
+    SetRegister(usize, RegisterKind, u32),
+    /// Bytes written from an offset into `.data`.
+    SetMemory(u32, Vec<u8>),
+}
+
 pub struct SimpleView {
     register_view: Controller<RegisterView>,
     memory_view: Controller<MemoryView>,
+    cpu: Arc<MulticoreCPU>,
+    /// The state before the last step, to highlight what it changed.
+    previous: Option<Arc<MulticoreCPU>>,
+    /// Memory written since the last update, `None` to show all of it.
+    dirty: Option<DirtyRegions>,
+    core: usize,
+    core_names: gtk::StringList,
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for SimpleView {
     type Input = CPUViewMessage;
-    type Output = ();
+    type Output = SimpleViewOutput;
     type Init = ();
 
     view! {
         #[root]
         gtk::Box {
-            set_orientation: gtk::Orientation::Horizontal,
+            set_orientation: gtk::Orientation::Vertical,
             set_spacing: 5,
             set_margin_all: 5,
-            append: model.register_view.widget(),
-            append: model.memory_view.widget(),
+            gtk::DropDown {
+                set_halign: gtk::Align::Start,
+                set_model: Some(&model.core_names),
+                #[watch]
+                set_visible: model.cpu.cores().len() > 1,
+                set_tooltip_text: Some("Core shown in the register view"),
+                connect_selected_notify[sender] => move |dropdown| {
+                    sender.input(CPUViewMessage::SelectCore(dropdown.selected() as usize))
+                },
+            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 5,
+                set_vexpand: true,
+                append: model.register_view.widget(),
+                append: model.memory_view.widget(),
+            },
         },
     }
 
@@ -34,42 +73,125 @@ impl SimpleComponent for SimpleView {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let register_view: Controller<RegisterView> = RegisterView::builder()
-            .launch(())
-            .forward(sender.input_sender(), |_| CPUViewMessage::None);
+        let register_view: Controller<RegisterView> =
+            RegisterView::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    RegOutput::SetRegister(reg, value) => CPUViewMessage::SetRegister(reg, value),
+                });
 
-        let memory_view: Controller<MemoryView> = MemoryView::builder()
-            .launch(())
-            .forward(sender.input_sender(), |_| CPUViewMessage::None);
+        let memory_view: Controller<MemoryView> =
+            MemoryView::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    MemoryOutput::SetBytes(addr, bytes) => CPUViewMessage::SetMemory(addr, bytes),
+                });
 
         let model = SimpleView {
             register_view,
             memory_view,
+            cpu: Arc::default(),
+            previous: None,
+            dirty: None,
+            core: 0,
+            core_names: gtk::StringList::new(&["Core 0"]),
         };
         let widgets = view_output!();
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         match msg {
             CPUViewMessage::Update(cpu) => {
-                self.register_view.emit(RegMsg::UpdateRegisters(
-                    (0..33)
-                        .map(|idx| cpu.get_register(FromPrimitive::from_i32(idx).unwrap()))
-                        .collect(),
-                ));
-                self.memory_view.emit(MemoryMsg::UpdateMemory(
-                    (0..cpu.get_memory_size())
-                        .map(|idx| cpu.get_memory_byte(idx).unwrap())
-                        .collect(),
-                ));
+                let cores = cpu.cores().len();
+                if self.core_names.n_items() as usize != cores {
+                    let names = (0..cores)
+                        .map(|idx| format!("Core {idx}"))
+                        .collect::<Vec<_>>();
+                    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                    self.core_names.splice(0, self.core_names.n_items(), &names);
+                    self.core = self.core.min(cores - 1);
+                }
+                self.cpu = cpu;
+                self.update_registers();
+                self.update_memory();
+            }
+            CPUViewMessage::Previous(previous) => self.previous = previous,
+            CPUViewMessage::DirtyMemory(dirty) => self.dirty = dirty,
+            CPUViewMessage::SelectCore(core) => {
+                if core < self.cpu.cores().len() {
+                    self.core = core;
+                    self.update_registers();
+                }
             }
             CPUViewMessage::ChangeRadix(radix) => {
                 self.register_view.emit(RegMsg::UpdateRadix(radix));
                 self.memory_view.emit(MemoryMsg::UpdateRadix(radix));
             }
+            CPUViewMessage::SetRegister(reg, value) => sender
+                .output(SimpleViewOutput::SetRegister(self.core, reg, value))
+                .unwrap(),
+            CPUViewMessage::SetMemory(addr, bytes) => sender
+                .output(SimpleViewOutput::SetMemory(addr, bytes))
+                .unwrap(),
+            CPUViewMessage::SetLabels(labels) => {
+                self.memory_view.emit(MemoryMsg::SetLabels(labels))
+            }
+            CPUViewMessage::ShowAddress(addr) => self.memory_view.emit(MemoryMsg::Show(addr)),
             CPUViewMessage::Resize(_) => {}
             CPUViewMessage::None => {}
         }
     }
 }
+
+impl SimpleView {
+    /// Sends the memory view the static data written since the last update,
+    /// or all of it when that is unknown.
+    fn update_memory(&mut self) {
+        let size = self.cpu.get_memory_size();
+        let msg = match self.dirty.take() {
+            Some(dirty) => MemoryMsg::Write(
+                dirty
+                    .within(DATA_MEM_START..DATA_MEM_START + size)
+                    .map(|range| range.start - DATA_MEM_START..range.end - DATA_MEM_START)
+                    .map(|range| (range.start, memory_bytes(&self.cpu, range)))
+                    .collect(),
+            ),
+            None => MemoryMsg::UpdateMemory(
+                memory_bytes(&self.cpu, 0..size),
+                self.previous
+                    .as_deref()
+                    .map(|previous| memory_bytes(previous, 0..previous.get_memory_size())),
+            ),
+        };
+        self.memory_view.emit(msg);
+    }
+
+    /// Shows the registers of the selected core, memory being shared.
+    fn update_registers(&self) {
+        let registers = |cpu: &MulticoreCPU| -> Option<Vec<u32>> {
+            let core = cpu.cores().get(self.core)?;
+            Some(
+                (0..33)
+                    .map(|idx| core.get_register(FromPrimitive::from_i32(idx).unwrap()))
+                    .chain(
+                        CP0_NAMES
+                            .iter()
+                            .map(|(reg, _, _)| core.get_cp0_register(*reg)),
+                    )
+                    .collect(),
+            )
+        };
+        self.register_view.emit(RegMsg::UpdateRegisters(
+            registers(&self.cpu).unwrap_or_default(),
+            self.previous.as_deref().and_then(registers),
+        ));
+    }
+}
+
+/// Bytes of `range`, counted from the start of `.data`.
+fn memory_bytes(cpu: &MulticoreCPU, range: Range<u32>) -> Vec<u8> {
+    range
+        .map(|idx| cpu.get_memory_byte(idx).unwrap_or_default())
+        .collect()
+}
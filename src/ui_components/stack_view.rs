@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use crate::cpu::cpu_interface::{CPUInterface, RegisterKind};
+use crate::cpu::multicore::MulticoreCPU;
+use crate::cpu::registers::REGISTER_NAMES;
+use crate::source_map::SourceMap;
+
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+const COLUMN_NAMES: [&str; 3] = ["Address", "Value", "Notes"];
+/// Words shown above the highest annotated slot, and at least shown at all.
+const MIN_WORDS: u32 = 16;
+/// Limit on the words shown, so a stray `$fp` does not list the whole stack.
+const MAX_WORDS: u32 = 512;
+
+#[derive(Debug)]
+pub enum StackMsg {
+    Update(Arc<MulticoreCPU>),
+    LoadSource(SourceMap),
+}
+
+/// Words of the current core's stack from `$sp` up, noting where `$sp` and
+/// `$fp` point, where each call's frame starts and which slots hold saved
+/// registers. Words the last instruction stored to are highlighted.
+pub struct StackView {
+    source_map: SourceMap,
+    grid: gtk::Grid,
+}
+
+impl StackView {
+    fn name(&self, addr: u32) -> String {
+        match self.source_map.label(addr) {
+            Some(label) => label.to_string(),
+            None => format!("0x{addr:08x}"),
+        }
+    }
+
+    /// Notes for each word address, in the order they are added.
+    fn notes(&self, cpu: &MulticoreCPU, sp: u32, fp: u32) -> Vec<(u32, String)> {
+        let call_stack = cpu.get_call_stack();
+        let mut notes = vec![(sp, String::from("$sp"))];
+        notes.push((fp & !3, String::from("$fp")));
+        notes.extend(call_stack.frames().map(|frame| {
+            (
+                frame.sp & !3,
+                format!("$sp on entry to {}", self.name(frame.target)),
+            )
+        }));
+        notes.extend(call_stack.saved_registers().iter().map(|saved| {
+            (
+                saved.addr & !3,
+                format!("Saved {}", REGISTER_NAMES[saved.reg as usize]),
+            )
+        }));
+        notes.retain(|(addr, _)| *addr >= sp && addr - sp < MAX_WORDS * 4);
+        notes
+    }
+
+    fn show(&self, cpu: &MulticoreCPU) {
+        while let Some(child) = self.grid.first_child() {
+            self.grid.remove(&child);
+        }
+        COLUMN_NAMES.iter().enumerate().for_each(|(column, name)| {
+            let label = gtk::Label::builder()
+                .label(*name)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build();
+            self.grid.attach(&label, column as i32, 0, 1, 1);
+        });
+
+        let sp = cpu.get_register(RegisterKind::Reg29) & !3;
+        let fp = cpu.get_register(RegisterKind::Reg30);
+        let notes = self.notes(cpu, sp, fp);
+        let highest = notes.iter().map(|(addr, _)| *addr).max().unwrap_or(sp);
+        let words = ((highest - sp) / 4 + MIN_WORDS).min(MAX_WORDS);
+        let last_store = cpu.get_last_store();
+
+        (0..words)
+            .map(|word| sp.wrapping_add(word * 4))
+            .map_while(|addr| cpu.get_memory_word(addr).map(|value| (addr, value)))
+            .enumerate()
+            .for_each(|(row, (addr, value))| {
+                let note = notes
+                    .iter()
+                    .filter(|(note_addr, _)| *note_addr == addr)
+                    .map(|(_, note)| note.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let written = last_store.is_some_and(|(start, width)| {
+                    start < addr.wrapping_add(4) && addr < start.wrapping_add(width)
+                });
+                [format!("0x{addr:08x}"), format!("0x{value:08x}"), note]
+                    .into_iter()
+                    .enumerate()
+                    .for_each(|(column, text)| {
+                        let label = gtk::Label::builder()
+                            .label(text)
+                            .xalign(0.0)
+                            .css_classes(if column == 2 {
+                                ["dim-label"]
+                            } else {
+                                ["monospace"]
+                            })
+                            .build();
+                        if written && column < 2 {
+                            label.add_css_class("accent");
+                            label.add_css_class("heading");
+                        }
+                        self.grid
+                            .attach(&label, column as i32, row as i32 + 1, 1, 1);
+                    });
+            });
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for StackView {
+    type Input = StackMsg;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_hexpand: true,
+            set_vexpand: true,
+            #[local_ref]
+            grid -> gtk::Grid {
+                set_row_spacing: 2,
+                set_column_spacing: 20,
+                set_margin_all: 10,
+            },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = StackView {
+            source_map: SourceMap::default(),
+            grid: gtk::Grid::new(),
+        };
+
+        let grid = &model.grid;
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            StackMsg::Update(cpu) => self.show(&cpu),
+            StackMsg::LoadSource(source_map) => self.source_map = source_map,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::cpu::cpu_interface::CPUInterface;
+use crate::cpu::statistics::{InstructionClass, Statistics};
+
+use super::CPUViewMessage;
+use gtk::prelude::*;
+use relm4::prelude::*;
+
+/// Rows above the per-class breakdown.
+const SUMMARY_ROWS: [&str; 3] = ["Cycles", "Instructions", "CPI"];
+
+/// Counters in a grid with one column for the whole machine and, when there
+/// are several cores, one column per core. Class rows also show their share
+/// of retired instructions.
+pub struct StatisticsView {
+    grid: gtk::Grid,
+    columns: Vec<Vec<gtk::Label>>,
+}
+
+impl StatisticsView {
+    fn rebuild(&mut self, cores: usize) {
+        while let Some(child) = self.grid.first_child() {
+            self.grid.remove(&child);
+        }
+
+        let core_columns = core_columns(cores);
+        let headings = [String::from("Total"), String::from("Share")]
+            .into_iter()
+            .chain((0..core_columns).map(|idx| format!("Core {idx}")));
+        headings.enumerate().for_each(|(column, heading)| {
+            let label = gtk::Label::builder()
+                .label(heading)
+                .xalign(1.0)
+                .css_classes(["heading"])
+                .build();
+            self.grid.attach(&label, column as i32 + 1, 0, 1, 1);
+        });
+
+        SUMMARY_ROWS
+            .iter()
+            .copied()
+            .chain(InstructionClass::ALL.iter().map(|class| class.name()))
+            .enumerate()
+            .for_each(|(row, name)| {
+                let label = gtk::Label::builder()
+                    .label(name)
+                    .xalign(0.0)
+                    .css_classes(["heading"])
+                    .build();
+                self.grid.attach(&label, 0, row as i32 + 1, 1, 1);
+            });
+
+        let rows = SUMMARY_ROWS.len() + InstructionClass::ALL.len();
+        self.columns = (0..2 + core_columns)
+            .map(|column| {
+                (0..rows)
+                    .map(|row| {
+                        let label = gtk::Label::builder()
+                            .xalign(1.0)
+                            .css_classes(["monospace"])
+                            .build();
+                        self.grid
+                            .attach(&label, column as i32 + 1, row as i32 + 1, 1, 1);
+                        label
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    fn fill(column: &[gtk::Label], statistics: &Statistics) {
+        let values = [
+            statistics.cycles.to_string(),
+            statistics.instret.to_string(),
+            format!("{:.2}", statistics.cpi()),
+        ]
+        .into_iter()
+        .chain(
+            InstructionClass::ALL
+                .iter()
+                .map(|class| statistics.count(*class).to_string()),
+        );
+        column
+            .iter()
+            .zip(values)
+            .for_each(|(label, value)| label.set_label(&value));
+    }
+
+    fn fill_shares(column: &[gtk::Label], statistics: &Statistics) {
+        column[SUMMARY_ROWS.len()..]
+            .iter()
+            .zip(InstructionClass::ALL)
+            .for_each(|(label, class)| {
+                label.set_label(&match statistics.instret {
+                    0 => String::from("-"),
+                    instret => format!(
+                        "{:.1}%",
+                        statistics.count(class) as f64 / instret as f64 * 100.0
+                    ),
+                })
+            });
+    }
+}
+
+/// A single core is the whole machine, so it gets no column of its own.
+fn core_columns(cores: usize) -> usize {
+    if cores > 1 {
+        cores
+    } else {
+        0
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for StatisticsView {
+    type Input = CPUViewMessage;
+    type Output = ();
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_hexpand: true,
+            set_vexpand: true,
+            #[local_ref]
+            grid -> gtk::Grid {
+                set_row_spacing: 2,
+                set_column_spacing: 20,
+                set_margin_all: 10,
+            },
+        },
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        _sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let mut model = StatisticsView {
+            grid: gtk::Grid::new(),
+            columns: Vec::new(),
+        };
+        model.rebuild(1);
+        Self::fill(&model.columns[0], &Statistics::default());
+        Self::fill_shares(&model.columns[1], &Statistics::default());
+
+        let grid = &model.grid;
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        match msg {
+            CPUViewMessage::Update(cpu) => {
+                let cores = cpu.cores();
+                if self.columns.len() != 2 + core_columns(cores.len()) {
+                    self.rebuild(cores.len());
+                }
+
+                let total = cpu.get_statistics();
+                Self::fill(&self.columns[0], &total);
+                Self::fill_shares(&self.columns[1], &total);
+                self.columns[2..]
+                    .iter()
+                    .zip(cores)
+                    .for_each(|(column, core)| Self::fill(column, &core.get_statistics()));
+            }
+            CPUViewMessage::Previous(_) => {}
+            CPUViewMessage::DirtyMemory(_) => {}
+            CPUViewMessage::ChangeRadix(_) => {}
+            CPUViewMessage::Resize(_) => {}
+            CPUViewMessage::SelectCore(_) => {}
+            CPUViewMessage::SetRegister(_, _) => {}
+            CPUViewMessage::SetMemory(_, _) => {}
+            CPUViewMessage::SetLabels(_) => {}
+            CPUViewMessage::ShowAddress(_) => {}
+            CPUViewMessage::None => {}
+        }
+    }
+}
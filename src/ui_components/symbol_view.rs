@@ -0,0 +1,152 @@
+use crate::source_map::{SourceMap, Symbol, SymbolKind};
+
+use adw::prelude::*;
+use relm4::prelude::*;
+
+#[derive(Debug)]
+pub enum SymbolMsg {
+    LoadSource(SourceMap),
+    Search(String),
+}
+
+#[derive(Debug)]
+pub enum SymbolOutput {
+    /// Shows where the symbol is defined and the memory it names.
+    Show(Symbol),
+}
+
+/// The program's text and data labels with their addresses and sizes,
+/// filtered by name or address.
+pub struct SymbolView {
+    source_map: SourceMap,
+    query: String,
+    text: gtk::ListBox,
+    data: gtk::ListBox,
+}
+
+impl SymbolView {
+    fn list(placeholder: &str) -> gtk::ListBox {
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        list.set_placeholder(Some(
+            &gtk::Label::builder()
+                .label(placeholder)
+                .css_classes(["dim-label"])
+                .margin_top(10)
+                .margin_bottom(10)
+                .build(),
+        ));
+        list
+    }
+
+    /// Whether `symbol` contains the query in its name or hex address.
+    fn matches(&self, symbol: &Symbol) -> bool {
+        let lowered = self.query.trim().to_lowercase();
+        let query = lowered.strip_prefix("0x").unwrap_or(&lowered);
+        symbol.name.to_lowercase().contains(query) || format!("{:08x}", symbol.addr).contains(query)
+    }
+
+    fn show(&self, sender: &ComponentSender<Self>) {
+        [
+            (&self.text, SymbolKind::Text),
+            (&self.data, SymbolKind::Data),
+        ]
+        .into_iter()
+        .for_each(|(list, kind)| {
+            while let Some(child) = list.first_child() {
+                list.remove(&child);
+            }
+            self.source_map
+                .symbols()
+                .iter()
+                .filter(|symbol| symbol.kind == kind && self.matches(symbol))
+                .for_each(|symbol| {
+                    let row = adw::ActionRow::builder()
+                        .title(symbol.name.as_str())
+                        .subtitle(format!(
+                            "0x{:08x}, {} bytes, line {}",
+                            symbol.addr,
+                            symbol.size,
+                            symbol.line + 1
+                        ))
+                        .activatable(true)
+                        .build();
+                    row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+                    let sender = sender.clone();
+                    let symbol = symbol.clone();
+                    row.connect_activated(move |_| {
+                        sender.output(SymbolOutput::Show(symbol.clone())).unwrap()
+                    });
+                    list.append(&row);
+                });
+        });
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for SymbolView {
+    type Input = SymbolMsg;
+    type Output = SymbolOutput;
+    type Init = ();
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_hexpand: true,
+            set_vexpand: true,
+            adw::Clamp {
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 20,
+                    set_margin_all: 10,
+                    gtk::SearchEntry {
+                        set_placeholder_text: Some("Search labels or addresses"),
+                        connect_search_changed[sender] => move |entry| {
+                            sender.input(SymbolMsg::Search(entry.text().to_string()))
+                        },
+                    },
+                    adw::PreferencesGroup {
+                        set_title: "Text",
+                        set_description: Some("Click a label to show it in the editor and the machine code"),
+                        #[local_ref]
+                        text -> gtk::ListBox {},
+                    },
+                    adw::PreferencesGroup {
+                        set_title: "Data",
+                        set_description: Some("Click a label to show it in the editor and the memory view"),
+                        #[local_ref]
+                        data -> gtk::ListBox {},
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = SymbolView {
+            source_map: SourceMap::default(),
+            query: String::new(),
+            text: Self::list("No text labels"),
+            data: Self::list("No data labels"),
+        };
+        let text = &model.text;
+        let data = &model.data;
+
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            SymbolMsg::LoadSource(source_map) => self.source_map = source_map,
+            SymbolMsg::Search(query) => self.query = query,
+        }
+        self.show(&sender);
+    }
+}